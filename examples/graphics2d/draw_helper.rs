@@ -7,7 +7,7 @@ use rsfml::system::vector2::Vector2f;
 use nalgebra::na::Vec2;
 use nalgebra::na;
 use nphysics::aliases::dim2;
-use nphysics::detection::constraint::{RBRB, BallInSocket, Fixed};
+use nphysics::detection::constraint::{RBRB, BallInSocket, Fixed, Distance, ConeTwist, GenericJoint, Gear, Pulley};
 
 pub static DRAW_SCALE: f32 = 20.0;
 
@@ -55,6 +55,39 @@ pub fn draw_colls(window:  &render_window::RenderWindow,
                     &na::translation(&bis.anchor1_pos()),
                     &na::translation(&bis.anchor2_pos()),
                     &Color::new_RGB(255, 0, 0))
+            },
+            Distance(d) => {
+                draw_line(
+                    window,
+                    &d.anchor1_pos(),
+                    &d.anchor2_pos(),
+                    &Color::new_RGB(255, 0, 0))
+            },
+            Pulley(p) => {
+                draw_line(window, &p.anchor1_pos(), p.ground1(), &Color::new_RGB(255, 0, 0));
+                draw_line(window, p.ground1(), p.ground2(), &Color::new_RGB(255, 0, 0));
+                draw_line(window, p.ground2(), &p.anchor2_pos(), &Color::new_RGB(255, 0, 0))
+            },
+            Gear(gr) => {
+                draw_line(
+                    window,
+                    &gr.anchor1().center_of_mass(),
+                    &gr.anchor2().center_of_mass(),
+                    &Color::new_RGB(255, 0, 0))
+            },
+            GenericJoint(g) => {
+                draw_line(
+                    window,
+                    &na::translation(&g.anchor1_pos()),
+                    &na::translation(&g.anchor2_pos()),
+                    &Color::new_RGB(255, 0, 0))
+            },
+            ConeTwist(ct) => {
+                draw_line(
+                    window,
+                    &na::translation(&ct.anchor1_pos()),
+                    &na::translation(&ct.anchor2_pos()),
+                    &Color::new_RGB(255, 0, 0))
             }
         }
     }
@@ -11,7 +11,7 @@ use ncollide::geom::Geom;
 use ncollide::ray;
 use ncollide::ray::Ray;
 use nphysics::aliases::dim3;
-use nphysics::detection::constraint::{RBRB, BallInSocket, Fixed};
+use nphysics::detection::constraint::{RBRB, BallInSocket, Fixed, Distance, ConeTwist, GenericJoint, Gear, Pulley};
 use nphysics::detection::joint::fixed::Fixed;
 use nphysics::detection::joint::anchor::Anchor;
 use nphysics::object::{RigidBody, Dynamic, RB};
@@ -69,21 +69,10 @@ pub fn simulate(builder: proc(&mut Window, &mut GraphicsManager) -> dim3::BodyWo
                             let ray = Ray::new(pos, dir);
 
                             // cast the ray
-                            let mut interferences = ~[];
-                            physics.cast_ray(&ray, &mut interferences);
+                            let hit = physics.cast_ray_closest(&ray, &Bounded::max_value(), |_| true);
 
-                            let mut mintoi = Bounded::max_value();
-                            let mut minb   = None;
-
-                            for (b, toi) in interferences.move_iter() {
-                                if toi < mintoi {
-                                    mintoi = toi;
-                                    minb   = Some(b);
-                                }
-                            }
-
-                            if minb.is_some() {
-                                let b = minb.unwrap();
+                            if hit.is_some() {
+                                let b = hit.unwrap().body;
                                 if b.can_move() {
                                     physics.remove_body(b);
                                     graphics.remove(w, b);
@@ -107,24 +96,18 @@ pub fn simulate(builder: proc(&mut Window, &mut GraphicsManager) -> dim3::BodyWo
                             let ray = Ray::new(pos, dir);
 
                             // cast the ray
-                            let mut interferences = ~[];
-                            physics.cast_ray(&ray, &mut interferences);
+                            let hit = physics.cast_ray_closest(&ray, &Bounded::max_value(), |_| true);
+                            let mut hit_point: Vec3<f32> = na::zero();
 
-                            let mut mintoi = Bounded::max_value();
-                            let mut minb   = None;
+                            match hit {
+                                Some(h) => {
+                                    hit_point = h.point;
 
-                            for (b, toi) in interferences.move_iter() {
-                                if toi < mintoi {
-                                    mintoi = toi;
-                                    minb   = Some(b);
-                                }
-                            }
-
-                            if minb.is_some() {
-                                let b = minb.unwrap();
-                                if b.can_move() {
-                                    grabbed_object = Some(b)
-                                }
+                                    if h.body.can_move() {
+                                        grabbed_object = Some(h.body)
+                                    }
+                                },
+                                None => { }
                             }
 
                             match grabbed_object {
@@ -137,9 +120,9 @@ pub fn simulate(builder: proc(&mut Window, &mut GraphicsManager) -> dim3::BodyWo
 
                                         let rb      = b.to_rigid_body_or_fail();
                                         let _1: dim3::Transform3d<f32> = One::one();
-                                        let attach2 = na::append_translation(&_1, &(ray.orig + ray.dir * mintoi));
+                                        let attach2 = na::append_translation(&_1, &hit_point);
                                         let attach1 = na::inv(&na::transformation(rb.transform_ref())).unwrap() * attach2;
-                                        let anchor1 = Anchor::new(Some(minb.unwrap()), attach1);
+                                        let anchor1 = Anchor::new(Some(b), attach1);
                                         let anchor2 = Anchor::new(None, attach2);
                                         let joint   = @mut Fixed::new(anchor1, anchor2);
                                         grabbed_object_joint = Some(joint);
@@ -320,18 +303,13 @@ pub fn simulate(builder: proc(&mut Window, &mut GraphicsManager) -> dim3::BodyWo
                 None          => { },
                 Some(ref ray) => {
                     // cast a ray
-                    let mut interferences = ~[];
-                    physics.cast_ray(ray, &mut interferences);
-
-                    let mut mintoi = Bounded::max_value();
-
-                    for (_, toi) in interferences.move_iter() {
-                        if toi < mintoi {
-                            mintoi = toi;
-                        }
+                    match physics.cast_ray_closest(ray, &Bounded::max_value(), |_| true) {
+                        Some(hit) => {
+                            w.draw_line(&ray.orig, &hit.point, &Vec3::x());
+                            w.draw_line(&hit.point, &(hit.point + hit.normal), &Vec3::y())
+                        },
+                        None => { }
                     }
-
-                    w.draw_line(&ray.orig, &(ray.orig + ray.dir * mintoi), &Vec3::x())
                 }
             }
 
@@ -372,6 +350,23 @@ fn draw_collisions(window: &mut window::Window, physics: &mut dim3::BodyWorld3d<
             Fixed(f) => {
                 // FIXME: draw the rotation too
                 window.draw_line(&na::translation(&f.anchor1_pos()), &na::translation(&f.anchor2_pos()), &Vec3::y());
+            },
+            Distance(d) => {
+                window.draw_line(&d.anchor1_pos(), &d.anchor2_pos(), &Vec3::y());
+            },
+            Pulley(p) => {
+                window.draw_line(&p.anchor1_pos(), p.ground1(), &Vec3::y());
+                window.draw_line(p.ground1(), p.ground2(), &Vec3::y());
+                window.draw_line(p.ground2(), &p.anchor2_pos(), &Vec3::y());
+            },
+            Gear(gr) => {
+                window.draw_line(&gr.anchor1().center_of_mass(), &gr.anchor2().center_of_mass(), &Vec3::y());
+            },
+            GenericJoint(g) => {
+                window.draw_line(&na::translation(&g.anchor1_pos()), &na::translation(&g.anchor2_pos()), &Vec3::y());
+            },
+            ConeTwist(ct) => {
+                window.draw_line(&na::translation(&ct.anchor1_pos()), &na::translation(&ct.anchor2_pos()), &Vec3::y());
             }
         }
     }
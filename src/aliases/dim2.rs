@@ -6,10 +6,15 @@ use detection::collision::bodies_bodies::{Dispatcher, PairwiseDetector};
 use detection::JointManager;
 use detection::joint::ball_in_socket::BallInSocket;
 use detection::joint::fixed::Fixed;
+use detection::joint::distance::Distance;
+use detection::joint::cone_twist::ConeTwist;
+use detection::joint::generic_joint::GenericJoint;
+use detection::joint::gear::Gear;
+use detection::joint::pulley::Pulley;
 use detection::constraint::Constraint;
 use detection::IslandActivationManager;
 use resolution::AccumulatedImpulseSolver;
-use object::{RigidBody, Body};
+use object::{RigidBody, Body, Multibody, MultibodyLink};
 use world::{World, BodyWorld};
 use object::volumetric::InertiaTensor;
 
@@ -44,6 +49,8 @@ pub type ContactSolver2d<N> = AccumulatedImpulseSolver<N, LV<N>, AV<N>, M<N>, II
 pub type Constraint2d<N> = Constraint<N, LV<N>, AV<N>, M<N>, II<N>>;
 pub type RigidBody2d<N> = RigidBody<N, LV<N>, AV<N>, M<N>, II<N>>; 
 pub type Body2d<N> = Body<N, LV<N>, AV<N>, M<N>, II<N>>; 
+pub type Multibody2d<N> = Multibody<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type MultibodyLink2d<N> = MultibodyLink<N, LV<N>, AV<N>, M<N>, II<N>>;
 
 pub type World2d<N> = World<N, Body2d<N>, Constraint2d<N>>;
 pub type BodyWorld2d<N> = BodyWorld<N, LV<N>, AV<N>, M<N>, II<N>, Vec2<N>>;
@@ -53,6 +60,11 @@ pub type BodyWorld2d<N> = BodyWorld<N, LV<N>, AV<N>, M<N>, II<N>, Vec2<N>>;
  */
 pub type BallInSocket2d<N> = BallInSocket<N, LV<N>, AV<N>, M<N>, II<N>>;
 pub type Fixed2d<N> = Fixed<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type Distance2d<N> = Distance<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type ConeTwist2d<N> = ConeTwist<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type GenericJoint2d<N> = GenericJoint<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type Gear2d<N> = Gear<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type Pulley2d<N> = Pulley<N, LV<N>, AV<N>, M<N>, II<N>>;
 
 /// NOTE: it is a bit unfortunate to have to specialize that for the raw types.
 impl<N: Clone + Num + Algebraic>
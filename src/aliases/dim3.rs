@@ -10,11 +10,17 @@ use detection::constraint::Constraint;
 use detection::JointManager;
 use detection::joint::ball_in_socket::BallInSocket;
 use detection::joint::fixed::Fixed;
+use detection::joint::distance::Distance;
+use detection::joint::cone_twist::ConeTwist;
+use detection::joint::generic_joint::GenericJoint;
+use detection::joint::gear::Gear;
+use detection::joint::pulley::Pulley;
 use detection::IslandActivationManager;
 use resolution::constraint::accumulated_impulse_solver::AccumulatedImpulseSolver;
 use world::{World, BodyWorld};
+use controller::{CharacterController, RaycastVehicle, Wheel};
 use object::volumetric::InertiaTensor;
-use object::{RigidBody, Body};
+use object::{RigidBody, Body, Multibody, MultibodyLink};
 
 type LV<N> = Vec3<N>;
 type AV<N> = Vec3<N>;
@@ -44,6 +50,9 @@ pub type DBVTBroadPhase3d<N> = DBVTBroadPhase<N, LV<N>, Body3d<N>, AABB3d<N>, Di
 pub type PairwiseDetector3d<N> = PairwiseDetector<N, LV<N>, AV<N>, M<N>>;
 pub type DBVTCollisionDetector3d<N> = BodiesBodies<N, LV<N>, AV<N>, M<N>, II<N>, DBVTBroadPhase3d<N>>;
 pub type DBVTSweptBallMotionClamping3d<N> = SweptBallMotionClamping<N, LV<N>, AV<N>, M<N>, II<N>, DBVTBroadPhase3d<N>>;
+pub type DBVTCharacterController3d<N> = CharacterController<N, LV<N>, AV<N>, M<N>, II<N>, DBVTBroadPhase3d<N>>;
+pub type DBVTRaycastVehicle3d<N> = RaycastVehicle<N, LV<N>, AV<N>, M<N>, II<N>, DBVTBroadPhase3d<N>>;
+pub type Wheel3d<N> = Wheel<N, LV<N>>;
 pub type JointManager3d<N> = JointManager<N, LV<N>, AV<N>, M<N>, II<N>>;
 pub type IslandActivationManager3d<N> = IslandActivationManager<N, LV<N>, AV<N>, M<N>, II<N>>; 
 
@@ -52,6 +61,8 @@ pub type ContactSolver3d<N> = AccumulatedImpulseSolver<N, LV<N>, AV<N>, M<N>, II
 pub type Constraint3d<N> = Constraint<N, LV<N>, AV<N>, M<N>, II<N>>;
 pub type RigidBody3d<N> = RigidBody<N, LV<N>, AV<N>, M<N>, II<N>>; 
 pub type Body3d<N> = Body<N, LV<N>, AV<N>, M<N>, II<N>>; 
+pub type Multibody3d<N> = Multibody<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type MultibodyLink3d<N> = MultibodyLink<N, LV<N>, AV<N>, M<N>, II<N>>;
 
 pub type World3d<N> = World<N, Body3d<N>, Constraint3d<N>>;
 pub type BodyWorld3d<N> = BodyWorld<N, LV<N>, AV<N>, M<N>, II<N>, Mat3<N>>;
@@ -61,6 +72,11 @@ pub type BodyWorld3d<N> = BodyWorld<N, LV<N>, AV<N>, M<N>, II<N>, Mat3<N>>;
  */
 pub type BallInSocket3d<N> = BallInSocket<N, LV<N>, AV<N>, M<N>, II<N>>;
 pub type Fixed3d<N> = Fixed<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type Distance3d<N> = Distance<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type ConeTwist3d<N> = ConeTwist<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type GenericJoint3d<N> = GenericJoint<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type Gear3d<N> = Gear<N, LV<N>, AV<N>, M<N>, II<N>>;
+pub type Pulley3d<N> = Pulley<N, LV<N>, AV<N>, M<N>, II<N>>;
 
 /// NOTE: it is a bit unfortunate to have to specialize that for the raw types.
 impl<N: Num + Algebraic + Clone + Mat3MulRhs<N, Mat3<N>>>
@@ -0,0 +1,471 @@
+use std::managed;
+use std::util;
+use std::num::{Zero, Bounded};
+use nalgebra::na::{Transformation, Translation, Rotate, Indexable};
+use nalgebra::na;
+use ncollide::bounding_volume::{AABB, HasAABB, BoundingVolume};
+use ncollide::broad::{RayCastBroadPhase, BoundingVolumeBroadPhase};
+use ncollide::geom::{Geom, AnnotatedPoint};
+use ncollide::narrow::algorithm::johnson_simplex::{RecursionTemplate, JohnsonSimplex};
+use ncollide::narrow::{CollisionDetector, GeomGeom};
+use ncollide::narrow::toi;
+use ncollide::contact::Contact;
+use object::{Body, RigidBody, Static, RB, SB};
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// The maximum number of obstacles a single motion can slide along.
+static MAX_SLIDES: uint = 4;
+
+/// A kinematic character controller.
+///
+/// The character is a static capsule body which must be added to the world. It is only moved by
+/// `move_by`, which sweeps the capsule through the bodies returned by the broad phase and stops it
+/// at the first obstacle before sliding along it. It stops before every body, dynamic ones
+/// included: it never pushes them, and is never pushed back.
+///
+/// The capsule axis is the local `y` axis of the character, which is also its up direction.
+/// Horizontal motions climb steps lower than `max_step_height` and slopes less steep than
+/// `max_slope`. When the character was on the ground before a motion which does not go up, it is
+/// snapped back to the ground if it is closer than `snap_distance`.
+pub struct CharacterController<N, LV, AV, M, II, BF> {
+    priv body:            @mut Body<N, LV, AV, M, II>,
+    priv broad_phase:     @mut BF,
+    priv simplex:         JohnsonSimplex<N, AnnotatedPoint<LV>>,
+    priv up:              LV,
+    priv skin_width:      N,
+    priv max_step_height: N,
+    priv max_slope:       N,
+    priv snap_distance:   N,
+    priv grounded:        bool,
+    priv ground_normal:   LV,
+    priv interferences:   ~[@mut Body<N, LV, AV, M, II>],
+    priv contacts:        ~[Contact<N, LV>]
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
+     BF: 'static + RayCastBroadPhase<LV, Body<N, LV, AV, M, II>> +
+         BoundingVolumeBroadPhase<Body<N, LV, AV, M, II>, AABB<N, LV>>>
+CharacterController<N, LV, AV, M, II, BF> {
+    /// Creates a character with a capsule of the given half height and radius, at the position and
+    /// orientation `transform`.
+    ///
+    /// The broad phase must be the one used by the world collision detector. It is only queried,
+    /// never updated, by the character: the obstacles are found at the positions they had during
+    /// the last world step, and the proxy of the character is updated by the world like the one
+    /// of any other body. The character body is not added to the world automatically.
+    pub fn new(broad_phase: @mut BF,
+               half_height: N,
+               radius:      N,
+               transform:   M)
+               -> CharacterController<N, LV, AV, M, II, BF> {
+        let mut rb = RigidBody::new(Geom::new_capsule(half_height, radius), na::zero(), Static,
+                                    na::zero(), na::zero());
+
+        rb.set_transformation(transform.clone());
+
+        let mut y: LV = na::zero();
+        y.set(1, na::one());
+
+        let template = RecursionTemplate::new(na::dim::<LV>());
+
+        CharacterController {
+            body:            @mut RB(rb),
+            broad_phase:     broad_phase,
+            simplex:         JohnsonSimplex::new(template),
+            up:              transform.rotate(&y),
+            skin_width:      na::cast(0.01),
+            max_step_height: radius * na::cast(0.5),
+            max_slope:       na::cast(0.785398163397448309615660845819875721f64), // 45 degrees
+            snap_distance:   radius * na::cast(0.5),
+            grounded:        false,
+            ground_normal:   na::zero(),
+            interferences:   ~[],
+            contacts:        ~[]
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>,
+     BF: RayCastBroadPhase<LV, Body<N, LV, AV, M, II>> +
+         BoundingVolumeBroadPhase<Body<N, LV, AV, M, II>, AABB<N, LV>>>
+CharacterController<N, LV, AV, M, II, BF> {
+    /// The body of this character.
+    #[inline]
+    pub fn body(&self) -> @mut Body<N, LV, AV, M, II> {
+        self.body
+    }
+
+    /// The world-space up direction of this character.
+    #[inline]
+    pub fn up<'r>(&'r self) -> &'r LV {
+        &self.up
+    }
+
+    /// Whether the character touched the ground during its last motion.
+    #[inline]
+    pub fn is_grounded(&self) -> bool {
+        self.grounded
+    }
+
+    /// The normal of the ground touched during the last motion. Zero if the character is not
+    /// grounded.
+    #[inline]
+    pub fn ground_normal<'r>(&'r self) -> &'r LV {
+        &self.ground_normal
+    }
+
+    /// The distance kept between the capsule and the obstacles.
+    #[inline]
+    pub fn skin_width(&self) -> N {
+        self.skin_width.clone()
+    }
+
+    pub fn set_skin_width(&mut self, skin_width: N) {
+        assert!(skin_width >= na::zero(), "The skin width must not be negative.");
+
+        self.skin_width = skin_width
+    }
+
+    /// The height of the highest step the character can climb.
+    #[inline]
+    pub fn max_step_height(&self) -> N {
+        self.max_step_height.clone()
+    }
+
+    pub fn set_max_step_height(&mut self, max_step_height: N) {
+        assert!(max_step_height >= na::zero(), "The maximum step height must not be negative.");
+
+        self.max_step_height = max_step_height
+    }
+
+    /// The angle of the steepest slope the character can climb.
+    #[inline]
+    pub fn max_slope(&self) -> N {
+        self.max_slope.clone()
+    }
+
+    pub fn set_max_slope(&mut self, max_slope: N) {
+        self.max_slope = max_slope
+    }
+
+    /// The maximum distance the character falls to get back to the ground after a motion.
+    #[inline]
+    pub fn snap_distance(&self) -> N {
+        self.snap_distance.clone()
+    }
+
+    pub fn set_snap_distance(&mut self, snap_distance: N) {
+        assert!(snap_distance >= na::zero(), "The snap distance must not be negative.");
+
+        self.snap_distance = snap_distance
+    }
+
+    /// Moves the character by `displacement`, or less if it is blocked by obstacles.
+    ///
+    /// The horizontal part of the displacement is performed first, stepping up if needed. The
+    /// vertical part is performed next. Returns the displacement actually performed.
+    pub fn move_by(&mut self, displacement: &LV) -> LV {
+        let was_grounded = self.grounded;
+        let start        = self.body.to_rigid_body_or_fail().translation();
+        let vertical_len = na::dot(displacement, &self.up);
+        let vertical     = self.up * vertical_len;
+        let horizontal   = *displacement - vertical;
+
+        self.grounded      = false;
+        self.ground_normal = na::zero();
+
+        if !na::sqnorm(&horizontal).is_zero() {
+            if was_grounded && self.max_step_height > na::zero() {
+                let up          = self.up.clone();
+                let step_height = self.max_step_height.clone();
+                let lifted      = self.move_to_first_obstacle(&up, step_height);
+
+                self.slide(horizontal, true);
+
+                // go back down by the height we climbed, to land on the step
+                self.move_down(lifted, false);
+            }
+            else {
+                self.slide(horizontal, true);
+            }
+        }
+
+        self.slide(vertical, false);
+
+        // stick to the ground when walking down slopes or stairs
+        if was_grounded && !self.grounded && vertical_len <= na::zero() {
+            let snap_distance = self.snap_distance.clone();
+
+            self.move_down(snap_distance, true);
+        }
+
+        self.body.to_rigid_body_or_fail().translation() - start
+    }
+
+    /// Moves along `motion` until the first obstacle, then along the obstacles surfaces.
+    ///
+    /// During an horizontal motion, the character does not slide up steep slopes. During a vertical
+    /// motion, it does not slide along walkable slopes.
+    fn slide(&mut self, motion: LV, horizontal: bool) {
+        let mut remaining = motion;
+
+        for _ in range(0u, MAX_SLIDES) {
+            let mut dir  = remaining.clone();
+            let distance = dir.normalize();
+
+            if distance.is_zero() {
+                break
+            }
+
+            match self.first_obstacle(&dir, distance + self.skin_width) {
+                None => {
+                    self.translate(&remaining);
+                    break
+                },
+                Some((toi, normal)) => {
+                    let _0: N  = na::zero();
+                    let travel = (toi - self.skin_width).max(&_0).min(&distance);
+
+                    self.translate(&(dir * travel));
+
+                    let walkable = self.is_walkable(&normal);
+
+                    if walkable {
+                        self.grounded      = true;
+                        self.ground_normal = normal.clone();
+                    }
+
+                    if !horizontal && walkable {
+                        break
+                    }
+
+                    remaining = dir * (distance - travel);
+
+                    // a steep slope acts like a vertical wall during horizontal motions
+                    let mut plane_normal = normal;
+
+                    if horizontal && !walkable {
+                        plane_normal = plane_normal - self.up * na::dot(&plane_normal, &self.up);
+
+                        if plane_normal.normalize().is_zero() {
+                            break
+                        }
+                    }
+
+                    remaining = remaining - plane_normal * na::dot(&remaining, &plane_normal);
+                }
+            }
+        }
+    }
+
+    /// Moves along the unit direction `dir` by at most `distance`, stopping before the first
+    /// obstacle. Returns the distance travelled.
+    fn move_to_first_obstacle(&mut self, dir: &LV, distance: N) -> N {
+        let travel = match self.first_obstacle(dir, distance + self.skin_width) {
+            Some((toi, _)) => {
+                let _0: N = na::zero();
+                (toi - self.skin_width).max(&_0).min(&distance)
+            },
+            None => distance
+        };
+
+        self.translate(&(*dir * travel));
+
+        travel
+    }
+
+    /// Moves down by at most `distance`, stopping before the first obstacle.
+    ///
+    /// If `only_onto_ground` is set, the character does not move unless it lands on a walkable
+    /// surface.
+    fn move_down(&mut self, distance: N, only_onto_ground: bool) {
+        let down = -self.up;
+
+        match self.first_obstacle(&down, distance + self.skin_width) {
+            Some((toi, normal)) => {
+                let walkable = self.is_walkable(&normal);
+
+                if walkable || !only_onto_ground {
+                    let _0: N = na::zero();
+                    self.translate(&(down * (toi - self.skin_width).max(&_0).min(&distance)));
+                }
+
+                if walkable {
+                    self.grounded      = true;
+                    self.ground_normal = normal;
+                }
+            },
+            None => {
+                if !only_onto_ground {
+                    self.translate(&(down * distance))
+                }
+            }
+        }
+    }
+
+    /// The time of impact and the surface normal of the first obstacle hit by the capsule when it
+    /// moves along the unit direction `dir` by at most `distance`.
+    fn first_obstacle(&mut self, dir: &LV, distance: N) -> Option<(N, LV)> {
+        let transform = self.body.to_rigid_body_or_fail().transform_ref().clone();
+        let geom      = self.body.to_rigid_body_or_fail().geom();
+        let end       = na::append_translation(&transform, &(*dir * distance));
+        let swept     = geom.aabb(&transform).merged(&geom.aabb(&end));
+
+        // moved out to call `self.obstacle_normal` while iterating
+        let mut interferences = util::replace(&mut self.interferences, ~[]);
+
+        self.broad_phase.interferences_with_bounding_volume(&swept, &mut interferences);
+
+        let mut min_toi: N = Bounded::max_value();
+        let mut obstacle   = None;
+
+        for b in interferences.iter() {
+            if !managed::mut_ptr_eq(*b, self.body) {
+                match **b {
+                    SB(_) => fail!("Soft bodies are not yet supported."),
+                    _     => {
+                        let rb  = b.to_rigid_body_or_fail();
+                        let toi = toi::geom_geom(&transform, dir, &distance, geom,
+                                                 rb.transform_ref(), rb.geom());
+
+                        match toi {
+                            Some(t) => if t < min_toi {
+                                let at_toi = na::append_translation(&transform, &(*dir * t));
+
+                                // without contact normal, the obstacle is only grazed: skip it
+                                match self.obstacle_normal(&at_toi, *b) {
+                                    Some(normal) => {
+                                        min_toi  = t;
+                                        obstacle = Some(normal)
+                                    },
+                                    None => { }
+                                }
+                            },
+                            None => { }
+                        }
+                    }
+                }
+            }
+        }
+
+        interferences.clear();
+        self.interferences = interferences;
+
+        match obstacle {
+            Some(normal) => Some((min_toi, normal)),
+            None         => None
+        }
+    }
+
+    /// The normal of the obstacle `b`, pointing toward the capsule positioned at `transform`.
+    ///
+    /// Returns `None` if the contact cannot be computed, which happens when the capsule only grazes
+    /// the obstacle.
+    fn obstacle_normal(&mut self, transform: &M, b: @mut Body<N, LV, AV, M, II>) -> Option<LV> {
+        let geom = self.body.to_rigid_body_or_fail().geom();
+        let rb   = b.to_rigid_body_or_fail();
+        let mut detector: GeomGeom<N, LV, AV, M> = GeomGeom::new(geom, rb.geom(), &self.simplex);
+
+        detector.update(transform, geom, rb.transform_ref(), rb.geom());
+        detector.colls(&mut self.contacts);
+
+        let mut res: LV = na::zero();
+
+        // the contact normals point from the capsule toward the obstacle
+        for c in self.contacts.iter() {
+            res = res - c.normal;
+        }
+
+        self.contacts.clear();
+
+        if res.normalize().is_zero() {
+            None
+        }
+        else {
+            Some(res)
+        }
+    }
+
+    fn is_walkable(&self, normal: &LV) -> bool {
+        na::dot(normal, &self.up) >= self.max_slope.cos()
+    }
+
+    fn translate(&mut self, v: &LV) {
+        self.body.to_mut_rigid_body_or_fail().append_translation(v)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Iso3, Translation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, DBVTCharacterController3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Static, RB};
+    use super::CharacterController;
+
+    fn add_box(world: &mut BodyWorld3d<f64>, half_extents: Vec3<f64>, center: Vec3<f64>) {
+        let mut rb = RigidBody::new(Geom::new_box(half_extents), 0.0f64, Static, 0.3, 0.6);
+
+        rb.append_translation(&center);
+        world.add_body(@mut RB(rb));
+    }
+
+    // a ground whose top is at `y = 0`, a wall whose face is at `x = 2.5`, and a character whose
+    // capsule bottom is at `y = bottom`
+    fn new_scene(bottom: f64) -> (BodyWorld3d<f64>, DBVTCharacterController3d<f64>) {
+        let mut world = BodyWorld::new();
+
+        add_box(&mut world, Vec3::new(10.0, 1.0, 10.0), Vec3::new(0.0, -1.0, 0.0));
+        add_box(&mut world, Vec3::new(0.5, 2.0, 10.0), Vec3::new(3.0, 2.0, 0.0));
+
+        let mut transform: Iso3<f64> = na::one();
+
+        transform.append_translation(&Vec3::new(0.0, bottom + 1.0, 0.0));
+
+        let character = CharacterController::new(world.collison_detector().broad_phase(), 0.5,
+                                                 0.5, transform);
+
+        world.add_body(character.body());
+
+        // registers the obstacles on the broad phase
+        world.step(0.016);
+
+        (world, character)
+    }
+
+    #[test]
+    fn test_character_lands_on_the_ground() {
+        let (_world, mut character) = new_scene(0.5);
+
+        let moved = character.move_by(&Vec3::new(0.0, -1.0, 0.0));
+
+        assert!((moved.y + 0.5).abs() < 0.1);
+        assert!(moved.y > -0.5);
+        assert!(character.is_grounded());
+        assert!(na::norm(&(*character.ground_normal() - Vec3::new(0.0, 1.0, 0.0))) < 1.0e-3);
+    }
+
+    #[test]
+    fn test_character_slides_along_the_walls() {
+        let (_world, mut character) = new_scene(0.1);
+
+        // the capsule is blocked one radius before the wall, and slides along it
+        let moved = character.move_by(&Vec3::new(5.0, 0.0, 5.0));
+
+        assert!(moved.x < 2.0 && moved.x > 1.8);
+        assert!((moved.z - 5.0).abs() < 0.1);
+        assert!(moved.y.abs() < 1.0e-3);
+        assert!(!character.is_grounded());
+    }
+}
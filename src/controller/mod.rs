@@ -0,0 +1,5 @@
+pub use controller::character_controller::CharacterController;
+pub use controller::raycast_vehicle::{RaycastVehicle, Wheel};
+
+pub mod character_controller;
+pub mod raycast_vehicle;
@@ -0,0 +1,788 @@
+use std::managed;
+use std::num::Zero;
+use nalgebra::na::{Transform, Rotate};
+use nalgebra::na;
+use ncollide::bounding_volume::AABB;
+use ncollide::broad::{InterferencesBroadPhase, BoundingVolumeBroadPhase, RayCastBroadPhase};
+use ncollide::ray::Ray;
+use object::{Body, RigidBody, SB};
+use object::volumetric::InertiaTensor;
+use integration::Integrator;
+use detection::collision::bodies_bodies::{BodiesBodies, PairwiseDetector};
+use detection::constraint::Constraint;
+use signal::signal::SignalEmiter;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// A wheel of a raycast vehicle.
+///
+/// The geometry of the wheel is expressed in the local frame of the chassis: the suspension is
+/// attached at `connection` and extends along the unit vector `direction` (usually pointing down).
+/// The unit vectors `forward` and `axle` are the rolling direction and the rotation axis of the
+/// wheel when it is not steered. They must be orthogonal to each other and to `direction`.
+///
+/// The wheel spins around its axle with its own angular velocity, driven by the engine and brake
+/// torques and by the reaction of the longitudinal tire force.
+pub struct Wheel<N, LV> {
+    priv connection:    LV,
+    priv direction:     LV,
+    priv forward:       LV,
+    priv axle:          LV,
+    priv radius:        N,
+    priv rest_length:   N,
+    priv stiffness:     N,
+    priv damping:       N,
+    priv friction:      N,
+    priv inertia:       N,
+    priv peak_slip:     N,
+    priv steering:      N,
+    priv engine_torque: N,
+    priv brake_torque:  N,
+    // state
+    priv in_contact:    bool,
+    priv length:        N,
+    priv contact_point: LV,
+    priv skid:          N,
+    priv spin:          N,
+    priv rotation:      N
+}
+
+impl<N: Clone + NPhysicsScalar, LV: Clone + Zero> Wheel<N, LV> {
+    pub fn new(connection:  LV,
+               direction:   LV,
+               forward:     LV,
+               axle:        LV,
+               radius:      N,
+               rest_length: N)
+               -> Wheel<N, LV> {
+        assert!(radius > na::zero(), "The radius of a wheel must be positive.");
+        assert!(rest_length >= na::zero(), "The suspension rest length must not be negative.");
+
+        Wheel {
+            connection:    connection,
+            direction:     direction,
+            forward:       forward,
+            axle:          axle,
+            radius:        radius.clone(),
+            rest_length:   rest_length.clone(),
+            stiffness:     na::cast(90.0),
+            damping:       na::cast(6.0),
+            friction:      na::cast(1.0),
+            inertia:       radius * radius * na::cast(10.0),
+            peak_slip:     na::cast(0.1),
+            steering:      na::zero(),
+            engine_torque: na::zero(),
+            brake_torque:  na::zero(),
+            in_contact:    false,
+            length:        rest_length,
+            contact_point: na::zero(),
+            skid:          na::zero(),
+            spin:          na::zero(),
+            rotation:      na::zero()
+        }
+    }
+
+    #[inline]
+    pub fn connection<'r>(&'r self) -> &'r LV {
+        &self.connection
+    }
+
+    #[inline]
+    pub fn direction<'r>(&'r self) -> &'r LV {
+        &self.direction
+    }
+
+    #[inline]
+    pub fn forward<'r>(&'r self) -> &'r LV {
+        &self.forward
+    }
+
+    #[inline]
+    pub fn axle<'r>(&'r self) -> &'r LV {
+        &self.axle
+    }
+
+    #[inline]
+    pub fn radius(&self) -> N {
+        self.radius.clone()
+    }
+
+    /// The length of the suspension when it applies no force.
+    #[inline]
+    pub fn rest_length(&self) -> N {
+        self.rest_length.clone()
+    }
+
+    /// The suspension stiffness, per unit of mass carried by the wheel.
+    ///
+    /// Each wheel carries an equal share of the chassis mass: the spring force is `stiffness *
+    /// compression * chassis_mass / num_wheels`, so the same value suits light and heavy
+    /// vehicles. Its unit is `s^-2`: its square root is the natural angular frequency of the
+    /// suspension. The default, 90, gives a frequency of 1.5 Hz, like the suspension of a road car.
+    #[inline]
+    pub fn stiffness(&self) -> N {
+        self.stiffness.clone()
+    }
+
+    pub fn set_stiffness(&mut self, stiffness: N) {
+        assert!(stiffness >= na::zero(), "The suspension stiffness must not be negative.");
+
+        self.stiffness = stiffness
+    }
+
+    /// The suspension damping, per unit of mass carried by the wheel.
+    ///
+    /// The damping force is `damping * compression_velocity * chassis_mass / num_wheels`. Its unit
+    /// is `s^-1`. The default, 6, is a damping ratio of about 0.3 with the default stiffness.
+    #[inline]
+    pub fn damping(&self) -> N {
+        self.damping.clone()
+    }
+
+    pub fn set_damping(&mut self, damping: N) {
+        assert!(damping >= na::zero(), "The suspension damping must not be negative.");
+
+        self.damping = damping
+    }
+
+    /// The ratio between the maximum tire force and the suspension force.
+    #[inline]
+    pub fn friction(&self) -> N {
+        self.friction.clone()
+    }
+
+    pub fn set_friction(&mut self, friction: N) {
+        self.friction = friction
+    }
+
+    /// The moment of inertia of the wheel around its axle.
+    ///
+    /// The default is the one of a uniform disc of mass 20, e.g. a car wheel in kilograms.
+    #[inline]
+    pub fn inertia(&self) -> N {
+        self.inertia.clone()
+    }
+
+    pub fn set_inertia(&mut self, inertia: N) {
+        assert!(inertia > na::zero(), "The inertia of a wheel must be positive.");
+
+        self.inertia = inertia
+    }
+
+    /// The slip ratio at which the longitudinal tire force reaches its maximum.
+    ///
+    /// The slip ratio is the difference between the velocity of the tire surface and the one of
+    /// the ground, divided by the largest of them. Below `peak_slip`, the longitudinal force grows
+    /// linearly with it. The default is 0.1.
+    #[inline]
+    pub fn peak_slip(&self) -> N {
+        self.peak_slip.clone()
+    }
+
+    pub fn set_peak_slip(&mut self, peak_slip: N) {
+        assert!(peak_slip > na::zero(), "The peak slip ratio must be positive.");
+
+        self.peak_slip = peak_slip
+    }
+
+    /// The steering angle, around `direction`, from `forward` toward `axle`.
+    #[inline]
+    pub fn steering(&self) -> N {
+        self.steering.clone()
+    }
+
+    pub fn set_steering(&mut self, steering: N) {
+        self.steering = steering
+    }
+
+    /// The torque applied by the engine to this wheel.
+    #[inline]
+    pub fn engine_torque(&self) -> N {
+        self.engine_torque.clone()
+    }
+
+    pub fn set_engine_torque(&mut self, engine_torque: N) {
+        self.engine_torque = engine_torque
+    }
+
+    /// The maximum torque applied by the brakes to this wheel, opposing its spin.
+    #[inline]
+    pub fn brake_torque(&self) -> N {
+        self.brake_torque.clone()
+    }
+
+    pub fn set_brake_torque(&mut self, brake_torque: N) {
+        assert!(brake_torque >= na::zero(), "The brake torque must not be negative.");
+
+        self.brake_torque = brake_torque
+    }
+
+    /// Whether the wheel touched the ground during the last update.
+    #[inline]
+    pub fn is_in_contact(&self) -> bool {
+        self.in_contact
+    }
+
+    /// The current length of the suspension.
+    #[inline]
+    pub fn suspension_length(&self) -> N {
+        self.length.clone()
+    }
+
+    /// The world-space point where the wheel touches the ground.
+    #[inline]
+    pub fn contact_point<'r>(&'r self) -> &'r LV {
+        &self.contact_point
+    }
+
+    /// How much the tire slips on the ground, between zero (full grip) and one.
+    #[inline]
+    pub fn skid(&self) -> N {
+        self.skid.clone()
+    }
+
+    /// The angular velocity of the wheel around its axle, positive when rolling forward.
+    #[inline]
+    pub fn spin(&self) -> N {
+        self.spin.clone()
+    }
+
+    pub fn set_spin(&mut self, spin: N) {
+        self.spin = spin
+    }
+
+    /// The angle of the wheel around its axle, for display.
+    #[inline]
+    pub fn rotation(&self) -> N {
+        self.rotation.clone()
+    }
+}
+
+impl<N: Clone + NPhysicsScalar, LV: Clone + Add<LV, LV> + Sub<LV, LV> + Mul<N, LV>> Wheel<N, LV> {
+    /// The rolling direction and the rotation axis of the wheel, after steering, in the local frame
+    /// of the chassis.
+    pub fn steered_frame(&self) -> (LV, LV) {
+        let cos = self.steering.cos();
+        let sin = self.steering.sin();
+
+        (self.forward * cos + self.axle * sin, self.axle * cos - self.forward * sin)
+    }
+}
+
+/// A vehicle whose wheels are modeled by ray casts.
+///
+/// Each wheel casts a ray from its suspension connection point along the suspension direction. If
+/// the ray hits a body, the suspension spring pushes the chassis and the hit body apart along the
+/// ground normal. The engine and brake torques change the spin of the wheels. The tire then
+/// applies a longitudinal force depending on the slip ratio between the wheel and the ground,
+/// whose reaction changes the wheel spin back, and a lateral force opposing the lateral slip. The
+/// tire forces are clamped by `friction` times the suspension force. All forces are applied to
+/// the chassis and, with the opposite sign, to the dynamic bodies the wheels stand on.
+///
+/// The vehicle must be added to the world as an integrator. It becomes inert if its chassis is
+/// removed from the world.
+pub struct RaycastVehicle<N, LV, AV, M, II, BF> {
+    priv events:   @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+    priv detector: @mut BodiesBodies<N, LV, AV, M, II, BF>,
+    priv chassis:  @mut Body<N, LV, AV, M, II>,
+    priv wheels:   ~[Wheel<N, LV>],
+    priv enabled:  bool
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
+     BF: 'static + InterferencesBroadPhase<Body<N, LV, AV, M, II>, PairwiseDetector<N, LV, AV, M>> +
+         BoundingVolumeBroadPhase<Body<N, LV, AV, M, II>, AABB<N, LV>> +
+         RayCastBroadPhase<LV, Body<N, LV, AV, M, II>>>
+RaycastVehicle<N, LV, AV, M, II, BF> {
+    /// Creates a vehicle without wheels. The `detector` must be the world collision detector.
+    pub fn new(events:   @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+               detector: @mut BodiesBodies<N, LV, AV, M, II, BF>,
+               chassis:  @mut Body<N, LV, AV, M, II>)
+               -> @mut RaycastVehicle<N, LV, AV, M, II, BF> {
+        assert!(chassis.can_move(), "The chassis of a vehicle must be dynamic.");
+
+        @mut RaycastVehicle {
+            events:   events,
+            detector: detector,
+            chassis:  chassis,
+            wheels:   ~[],
+            enabled:  true
+        }
+    }
+
+    #[inline]
+    pub fn chassis(&self) -> @mut Body<N, LV, AV, M, II> {
+        self.chassis
+    }
+
+    /// Whether the vehicle still applies forces, i.e. its chassis has not been removed from the
+    /// world.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Adds a wheel and returns its index.
+    pub fn add_wheel(&mut self, wheel: Wheel<N, LV>) -> uint {
+        self.wheels.push(wheel);
+
+        self.wheels.len() - 1
+    }
+
+    #[inline]
+    pub fn num_wheels(&self) -> uint {
+        self.wheels.len()
+    }
+
+    #[inline]
+    pub fn wheel<'r>(&'r self, i: uint) -> &'r Wheel<N, LV> {
+        &self.wheels[i]
+    }
+
+    #[inline]
+    pub fn mut_wheel<'r>(&'r mut self, i: uint) -> &'r mut Wheel<N, LV> {
+        &mut self.wheels[i]
+    }
+
+    /// Steers every wheel whose connection point is in front of the chassis center of mass.
+    pub fn set_steering(&mut self, steering: N) {
+        for w in self.wheels.mut_iter() {
+            if na::dot(&w.connection, &w.forward) > na::zero() {
+                w.steering = steering.clone()
+            }
+        }
+    }
+
+    /// Applies `engine_torque` to every wheel.
+    pub fn set_engine_torque(&mut self, engine_torque: N) {
+        for w in self.wheels.mut_iter() {
+            w.engine_torque = engine_torque.clone()
+        }
+    }
+
+    /// Applies `brake_torque` to every wheel.
+    pub fn set_brake_torque(&mut self, brake_torque: N) {
+        for w in self.wheels.mut_iter() {
+            w.set_brake_torque(brake_torque.clone())
+        }
+    }
+
+    /// Updates the wheel `i`: casts its ray, applies its suspension and tire forces, and
+    /// integrates its spin.
+    fn update_wheel(&mut self, i: uint, dt: &N) {
+        let _1: N         = na::one();
+        let transform     = self.chassis.to_rigid_body_or_fail().transform_ref().clone();
+        let num_wheels: N = na::cast(self.wheels.len() as f64);
+        // the part of the chassis mass carried by this wheel
+        let carried       = _1 / self.chassis.to_rigid_body_or_fail().inv_mass() / num_wheels;
+        let wheel         = &mut self.wheels[i];
+        let origin    = transform.transform(&wheel.connection);
+        let down      = transform.rotate(&wheel.direction);
+        let max_toi   = wheel.rest_length + wheel.radius;
+
+        /*
+         * Find the closest ground hit by the suspension ray.
+         */
+        let ray     = Ray::new(origin.clone(), down.clone());
+        let chassis = self.chassis;
+        let hit     = self.detector.cast_ray_closest(&ray, &max_toi, |b| {
+            match *b {
+                SB(_) => false,
+                _     => !managed::mut_ptr_eq(b, chassis)
+            }
+        });
+
+        /*
+         * Engine and brakes.
+         */
+        wheel.spin = wheel.spin + wheel.engine_torque / wheel.inertia * *dt;
+
+        let max_brake = wheel.brake_torque / wheel.inertia * *dt;
+
+        if wheel.spin.abs() <= max_brake {
+            wheel.spin = na::zero()
+        }
+        else {
+            wheel.spin = wheel.spin - wheel.spin.signum() * max_brake
+        }
+
+        let hit = match hit {
+            None    => {
+                wheel.in_contact = false;
+                wheel.length     = wheel.rest_length.clone();
+                wheel.skid       = na::zero();
+                wheel.rotation   = wheel.rotation + wheel.spin * *dt;
+
+                return
+            },
+            Some(h) => h
+        };
+
+        let _0: N  = na::zero();
+        let toi    = hit.toi.clone();
+        let point  = hit.point.clone();
+        let ground = if hit.body.can_move() { Some(hit.body) } else { None };
+
+        match ground {
+            // a sleeping ground would ignore the impulses
+            Some(g) => if !g.is_active() { self.events.request_body_activation(g) },
+            None    => { }
+        }
+
+        // the ground normal, unless the ray hits the back of a surface
+        let up = if na::dot(&hit.normal, &down) < _0 { hit.normal.clone() } else { -down };
+
+        wheel.in_contact    = true;
+        wheel.length        = toi - wheel.radius;
+        wheel.contact_point = point.clone();
+
+        let r1 = point - *chassis.to_rigid_body_or_fail().center_of_mass();
+        let r2 = match ground {
+            Some(g) => point - *g.to_rigid_body_or_fail().center_of_mass(),
+            None    => na::zero()
+        };
+
+        /*
+         * Suspension.
+         */
+        let compression = wheel.rest_length - wheel.length;
+        let approach    = -relative_vel(chassis, &r1, ground, &r2, &up);
+        let spring      = wheel.stiffness * compression + wheel.damping * approach;
+        let force       = (spring * carried).max(&_0);
+        let suspension  = force * *dt;
+
+        apply_impulses(chassis, &r1, ground, &r2, &(up * suspension));
+
+        /*
+         * Tire friction.
+         */
+        let (local_forward, local_axle) = wheel.steered_frame();
+        let forward = tangent(&transform.rotate(&local_forward), &up);
+        let side    = tangent(&transform.rotate(&local_axle), &up);
+
+        let forward_vel = relative_vel(chassis, &r1, ground, &r2, &forward);
+        let side_vel    = relative_vel(chassis, &r1, ground, &r2, &side);
+
+        // the longitudinal force grows with the slip ratio up to `peak_slip`...
+        let surface_vel = wheel.spin * wheel.radius;
+        let slip_vel    = surface_vel - forward_vel;
+        let max_vel     = forward_vel.abs().max(&surface_vel.abs());
+        let slip        = if max_vel.is_zero() { _0.clone() } else { slip_vel / max_vel };
+        let grip        = (slip / wheel.peak_slip).max(&-_1).min(&_1);
+
+        let mut longitudinal = wheel.friction * suspension * grip;
+
+        // ... but it must not reverse the slip during one step, with the wheel inertia
+        let wheel_inv_mass = wheel.radius * wheel.radius / wheel.inertia;
+        let ground_mass    = effective_mass(chassis, &r1, ground, &r2, &forward);
+        let no_slip        = slip_vel / (_1 / ground_mass + wheel_inv_mass);
+
+        if longitudinal.abs() > no_slip.abs() {
+            longitudinal = no_slip
+        }
+
+        // cancel the lateral slip
+        let lateral = -side_vel * effective_mass(chassis, &r1, ground, &r2, &side);
+
+        // friction circle
+        let max_impulse = wheel.friction * suspension;
+        let magnitude   = (longitudinal * longitudinal + lateral * lateral).sqrt();
+        let mut scale   = _1.clone();
+
+        if magnitude > max_impulse {
+            scale = max_impulse / magnitude
+        }
+
+        wheel.skid = _1 - scale;
+
+        let impulse = forward * (longitudinal * scale) + side * (lateral * scale);
+
+        apply_impulses(chassis, &r1, ground, &r2, &impulse);
+
+        /*
+         * Wheel rotation: the tire force slows down a wheel faster than the ground, and the other
+         * way round.
+         */
+        wheel.spin     = wheel.spin - longitudinal * scale * wheel.radius / wheel.inertia;
+        wheel.rotation = wheel.rotation + wheel.spin * *dt;
+    }
+
+    /// Wakes up the chassis if the wheels are driven, and returns whether it is active.
+    fn activate_chassis(&mut self) -> bool {
+        if self.chassis.is_active() {
+            return true
+        }
+
+        let driven = self.wheels.iter().any(|w| !w.engine_torque.is_zero());
+
+        if driven {
+            self.events.request_body_activation(self.chassis);
+        }
+
+        driven
+    }
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
+     BF: 'static + InterferencesBroadPhase<Body<N, LV, AV, M, II>, PairwiseDetector<N, LV, AV, M>> +
+         BoundingVolumeBroadPhase<Body<N, LV, AV, M, II>, AABB<N, LV>> +
+         RayCastBroadPhase<LV, Body<N, LV, AV, M, II>>>
+Integrator<N, Body<N, LV, AV, M, II>> for RaycastVehicle<N, LV, AV, M, II, BF> {
+    #[inline]
+    fn add(&mut self, _: @mut Body<N, LV, AV, M, II>) {
+    }
+
+    fn remove(&mut self, o: @mut Body<N, LV, AV, M, II>) {
+        if managed::mut_ptr_eq(o, self.chassis) {
+            self.enabled = false
+        }
+    }
+
+    fn update(&mut self, dt: N) {
+        if !self.enabled || !self.activate_chassis() {
+            return
+        }
+
+        for i in range(0u, self.wheels.len()) {
+            self.update_wheel(i, &dt)
+        }
+    }
+
+    #[inline]
+    fn priority(&self) -> f64 { 25.0 }
+}
+
+/// The unit projection of `v` on the plane orthogonal to the unit vector `normal`.
+fn tangent<N: Clone + NPhysicsScalar, LV: Clone + NPhysicsDirection<N, AV>, AV>(v:      &LV,
+                                                                               normal: &LV)
+                                                                               -> LV {
+    let mut res = *v - *normal * na::dot(v, normal);
+
+    res.normalize();
+
+    res
+}
+
+/// The velocity of the chassis relative to the ground along `dir`, at the contact point. `r1` and
+/// `r2` are the contact point positions relative to the centers of mass. The ground is `None` if
+/// it cannot move.
+fn relative_vel<N:  Clone + NPhysicsScalar,
+                LV: Clone + NPhysicsDirection<N, AV>,
+                AV: Clone + NPhysicsOrientation<N>,
+                M:  NPhysicsTransform<LV, AV>,
+                II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                chassis: @mut Body<N, LV, AV, M, II>,
+                r1:      &LV,
+                ground:  Option<@mut Body<N, LV, AV, M, II>>,
+                r2:      &LV,
+                dir:     &LV)
+                -> N {
+    let vel1 = point_vel(chassis.to_rigid_body_or_fail(), r1, dir);
+
+    match ground {
+        Some(g) => vel1 - point_vel(g.to_rigid_body_or_fail(), r2, dir),
+        None    => vel1
+    }
+}
+
+fn point_vel<N:  Clone + NPhysicsScalar,
+             LV: Clone + NPhysicsDirection<N, AV>,
+             AV: Clone + NPhysicsOrientation<N>,
+             M:  NPhysicsTransform<LV, AV>,
+             II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+             rb:  &RigidBody<N, LV, AV, M, II>,
+             r:   &LV,
+             dir: &LV)
+             -> N {
+    na::dot(dir, &rb.lin_vel()) + na::dot(&rb.ang_vel(), &na::cross(r, dir))
+}
+
+/// The mass seen by an impulse along `dir` applied at the contact point.
+fn effective_mass<N:  Clone + NPhysicsScalar,
+                  LV: Clone + NPhysicsDirection<N, AV>,
+                  AV: Clone + NPhysicsOrientation<N>,
+                  M:  NPhysicsTransform<LV, AV>,
+                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                  chassis: @mut Body<N, LV, AV, M, II>,
+                  r1:      &LV,
+                  ground:  Option<@mut Body<N, LV, AV, M, II>>,
+                  r2:      &LV,
+                  dir:     &LV)
+                  -> N {
+    let mut inv_mass = inv_point_mass(chassis.to_rigid_body_or_fail(), r1, dir);
+
+    match ground {
+        Some(g) => inv_mass = inv_mass + inv_point_mass(g.to_rigid_body_or_fail(), r2, dir),
+        None    => { }
+    }
+
+    let _1: N = na::one();
+
+    _1 / inv_mass
+}
+
+fn inv_point_mass<N:  Clone + NPhysicsScalar,
+                  LV: Clone + NPhysicsDirection<N, AV>,
+                  AV: Clone + NPhysicsOrientation<N>,
+                  M:  NPhysicsTransform<LV, AV>,
+                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                  rb:  &RigidBody<N, LV, AV, M, II>,
+                  r:   &LV,
+                  dir: &LV)
+                  -> N {
+    let rot = na::cross(r, dir);
+
+    rb.inv_mass() + na::dot(&rot, &rb.inv_inertia().apply(&rot))
+}
+
+/// Applies `impulse` to the chassis, and its opposite to the ground.
+fn apply_impulses<N:  Clone + NPhysicsScalar,
+                  LV: Clone + NPhysicsDirection<N, AV>,
+                  AV: Clone + NPhysicsOrientation<N>,
+                  M:  NPhysicsTransform<LV, AV>,
+                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                  chassis: @mut Body<N, LV, AV, M, II>,
+                  r1:      &LV,
+                  ground:  Option<@mut Body<N, LV, AV, M, II>>,
+                  r2:      &LV,
+                  impulse: &LV) {
+    apply_impulse(chassis.to_mut_rigid_body_or_fail(), r1, impulse);
+
+    match ground {
+        Some(g) => apply_impulse(g.to_mut_rigid_body_or_fail(), r2, &-impulse),
+        None    => { }
+    }
+}
+
+fn apply_impulse<N:  Clone + NPhysicsScalar,
+                 LV: Clone + NPhysicsDirection<N, AV>,
+                 AV: Clone + NPhysicsOrientation<N>,
+                 M:  NPhysicsTransform<LV, AV>,
+                 II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                 rb:      &mut RigidBody<N, LV, AV, M, II>,
+                 r:       &LV,
+                 impulse: &LV) {
+    let lin_vel = rb.lin_vel() + *impulse * rb.inv_mass();
+    let ang_vel = rb.ang_vel() + rb.inv_inertia().apply(&na::cross(r, impulse));
+
+    rb.set_lin_vel(lin_vel);
+    rb.set_ang_vel(ang_vel);
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Translation};
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, DBVTRaycastVehicle3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Static, Dynamic, RB};
+    use integration::Integrator;
+    use super::{RaycastVehicle, Wheel};
+
+    // a four-wheeled vehicle, whose chassis center is at `y = height`, above a ground whose top
+    // is at `y = 0`
+    fn new_vehicle(height: f64) -> (BodyWorld3d<f64>, @mut DBVTRaycastVehicle3d<f64>) {
+        let mut world = BodyWorld::new();
+
+        world.set_gravity(Vec3::new(0.0f64, -9.81, 0.0));
+
+        let mut ground = RigidBody::new(Geom::new_box(Vec3::new(50.0f64, 1.0, 50.0)), 0.0, Static,
+                                        0.3, 0.6);
+
+        ground.append_translation(&Vec3::new(0.0, -1.0, 0.0));
+        world.add_body(@mut RB(ground));
+
+        let mut chassis = RigidBody::new(Geom::new_box(Vec3::new(1.0f64, 0.25, 2.0)), 1.0,
+                                         Dynamic, 0.3, 0.6);
+
+        chassis.append_translation(&Vec3::new(0.0, height, 0.0));
+
+        let chassis = @mut RB(chassis);
+
+        world.add_body(chassis);
+
+        let vehicle = RaycastVehicle::new(world.events(), world.collison_detector(), chassis);
+
+        for &(x, z) in [(-0.8f64, -1.5f64), (0.8, -1.5), (-0.8, 1.5), (0.8, 1.5)].iter() {
+            vehicle.add_wheel(Wheel::new(Vec3::new(x, 0.0, z), Vec3::new(0.0, -1.0, 0.0),
+                                         Vec3::new(0.0, 0.0, 1.0), Vec3::new(1.0, 0.0, 0.0), 0.3,
+                                         0.5));
+        }
+
+        world.add_integrator("vehicle", vehicle);
+
+        (world, vehicle)
+    }
+
+    fn run(world: &mut BodyWorld3d<f64>, num_steps: uint) {
+        for _ in range(0u, num_steps) {
+            world.step(0.016)
+        }
+    }
+
+    #[test]
+    fn test_engine_and_brakes_change_the_wheel_spin() {
+        // the wheels are far above the ground
+        let (_world, vehicle) = new_vehicle(10.0);
+
+        // the inertia of the wheels is 0.3 * 0.3 * 10
+        vehicle.set_engine_torque(2.0);
+        vehicle.update(0.1);
+
+        assert!(!vehicle.wheel(0).is_in_contact());
+        assert!((vehicle.wheel(0).spin() - 2.0 / 0.9 * 0.1).abs() < 1.0e-10);
+
+        vehicle.set_engine_torque(0.0);
+        vehicle.mut_wheel(0).set_spin(10.0);
+        vehicle.set_brake_torque(9.0);
+        vehicle.update(0.1);
+
+        assert!((vehicle.wheel(0).spin() - 9.0).abs() < 1.0e-10);
+
+        // the brakes never reverse the spin
+        vehicle.set_brake_torque(1000.0);
+        vehicle.update(0.1);
+
+        assert!(vehicle.wheel(0).spin() == 0.0);
+    }
+
+    #[test]
+    fn test_suspension_carries_the_chassis() {
+        let (mut world, vehicle) = new_vehicle(0.8);
+
+        run(&mut world, 500);
+
+        // each wheel carries a quarter of the chassis mass, whatever this mass
+        let length = 0.5 - 9.81 / 90.0;
+
+        for i in range(0u, 4) {
+            assert!(vehicle.wheel(i).is_in_contact());
+            assert!((vehicle.wheel(i).suspension_length() - length).abs() < 0.02);
+        }
+    }
+
+    #[test]
+    fn test_engine_drives_the_vehicle_forward() {
+        let (mut world, vehicle) = new_vehicle(0.8);
+
+        run(&mut world, 200);
+        vehicle.set_engine_torque(5.0);
+        run(&mut world, 60);
+
+        let vel  = vehicle.chassis().to_rigid_body_or_fail().lin_vel();
+        let spin = vehicle.wheel(0).spin();
+
+        // the wheels roll with a small slip
+        assert!(vel.z > 0.5);
+        assert!(vel.x.abs() < 1.0e-3);
+        assert!(spin * 0.3 > vel.z * 0.9 && spin * 0.3 < vel.z * 1.2);
+    }
+}
@@ -1,10 +1,11 @@
 use std::ptr;
-use std::num::{Zero, One};
+use std::num::{Zero, One, Bounded};
 use std::borrow;
+use std::util;
 use std::managed;
 use nalgebra::na::{
     Translation, Rotate, Rotation, Transform, AbsoluteRotate, Inv,
-    Cast, Vec, AlgebraicVecExt, Cross, Dim
+    Cast, Vec, AlgebraicVecExt, Cross, Dim, Indexable
 };
 use nalgebra::na;
 use ncollide::geom::AnnotatedPoint;
@@ -15,7 +16,7 @@ use ncollide::narrow::algorithm::johnson_simplex::{RecursionTemplate, JohnsonSim
 use ncollide::narrow::{CollisionDetector, GeomGeom};
 use ncollide::contact::Contact;
 use ncollide::ray::{Ray, RayCastWithTransform};
-use object::{Body, RB, SB};
+use object::{Body, RB, SB, MB};
 use detection::constraint::{Constraint, RBRB};
 use detection::detector::Detector;
 use signal::signal::{SignalEmiter, BodyActivationSignalHandler};
@@ -43,7 +44,7 @@ PairwiseDetector<N, LV, AV, M> {
     }
 }
 
-struct Dispatcher<N, LV, AV, M, II> {
+pub struct Dispatcher<N, LV, AV, M, II> {
     simplex: JohnsonSimplex<N, AnnotatedPoint<LV>>
 }
 
@@ -71,10 +72,13 @@ for Dispatcher<N, LV, AV, M, II> {
     fn dispatch(&self, a: &Body<N, LV, AV, M, II>, b: &Body<N, LV, AV, M, II>)
         -> PairwiseDetector<N, LV, AV, M> {
         match (a, b) {
-            (&RB(ref rb1), &RB(ref rb2)) => {
+            (&SB(_), _) | (_, &SB(_)) => Unsuported,
+            _ => {
+                let rb1 = a.to_rigid_body_or_fail();
+                let rb2 = b.to_rigid_body_or_fail();
+
                 GG(GeomGeom::new(rb1.geom(), rb2.geom(), &self.simplex))
-            },
-            _ => Unsuported
+            }
         }
     }
 
@@ -88,6 +92,8 @@ for Dispatcher<N, LV, AV, M, II> {
 
         match (a, b) {
             (&RB(ref a), &RB(ref b)) => a.can_move() || b.can_move(),
+            // the links of a multibody do not collide with each other
+            (&MB(ref a), &MB(ref b)) => a.multibody() != b.multibody(),
             _ => true
         }
     }
@@ -102,7 +108,8 @@ pub struct BodiesBodies<N, LV, AV, M, II, BF> {
     constraints_collector: ~[Constraint<N, LV, AV, M, II>],
     signals:     @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
     broad_phase: @mut BF,
-    update_bf:   bool
+    update_bf:   bool,
+    num_pairs:   uint
 }
 
 impl<N:  'static + Clone + NPhysicsScalar,
@@ -120,7 +127,8 @@ BodiesBodies<N, LV, AV, M, II, BF> {
             constraints_collector: ~[],
             signals:               events,
             broad_phase:           bf,
-            update_bf:             update_bf
+            update_bf:             update_bf,
+            num_pairs:             0
         };
 
         events.add_body_activation_handler(
@@ -159,6 +167,16 @@ BodiesBodies<N, LV, AV, M, II, BF> {
     fn deactivate(&mut self, body: @mut Body<N, LV, AV, M, II>) {
         self.broad_phase.deactivate(body)
     }
+
+    /// The broad phase used by this detector.
+    pub fn broad_phase(&self) -> @mut BF {
+        self.broad_phase
+    }
+
+    /// The number of pairs reported by the broad phase during the last update.
+    pub fn num_pairs(&self) -> uint {
+        self.num_pairs
+    }
 }
 
 impl<N:  'static + Clone + NPhysicsScalar,
@@ -168,6 +186,7 @@ impl<N:  'static + Clone + NPhysicsScalar,
      II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
      BF: RayCastBroadPhase<LV, Body<N, LV, AV, M, II>>>
 BodiesBodies<N, LV, AV, M, II, BF> {
+    /// Appends every body hit by a ray, with its time of impact, in no particular order.
     pub fn interferences_with_ray(&mut self,
                                   ray: &Ray<LV>,
                                   out: &mut ~[(@mut Body<N, LV, AV, M, II>, N)]) {
@@ -176,16 +195,247 @@ BodiesBodies<N, LV, AV, M, II, BF> {
         self.broad_phase.interferences_with_ray(ray, &mut bodies);
 
         for b in bodies.iter() {
-            match **b {
-                RB(ref rb) => {
-                    match rb.geom().toi_with_transform_and_ray(rb.transform_ref(), ray) {
-                        None    => { },
-                        Some(t) => out.push((*b, t))
+            match cast_ray_on_body(*b, ray) {
+                None         => { },
+                Some((t, _)) => out.push((*b, t))
+            }
+        }
+    }
+
+    /// Appends the bodies hit by a ray, sorted by time of impact.
+    ///
+    /// Only the hits with a time of impact smaller than `max_toi`, on bodies accepted by `filter`,
+    /// are reported.
+    pub fn cast_ray(&mut self,
+                    ray:     &Ray<LV>,
+                    max_toi: &N,
+                    filter:  |@mut Body<N, LV, AV, M, II>| -> bool,
+                    out:     &mut ~[RayHit<N, LV, AV, M, II>]) {
+        let mut bodies = ~[];
+
+        self.broad_phase.interferences_with_ray(ray, &mut bodies);
+
+        let first = out.len();
+
+        for b in bodies.iter() {
+            if filter(*b) {
+                match cast_ray_on_body(*b, ray) {
+                    Some((toi, normal)) => {
+                        if toi <= *max_toi {
+                            out.push(RayHit::new(*b, ray, toi, normal))
+                        }
+                    },
+                    None => { }
+                }
+            }
+        }
+
+        out.mut_slice_from(first).sort_by(|a, b| compare(&a.toi, &b.toi))
+    }
+
+    /// The first body hit by a ray.
+    ///
+    /// Only the hits with a time of impact smaller than `max_toi`, on bodies accepted by `filter`,
+    /// are considered. The bodies are tested by increasing distance of their bounding volume, so
+    /// the search stops as soon as the remaining bodies are too far.
+    pub fn cast_ray_closest(&mut self,
+                            ray:     &Ray<LV>,
+                            max_toi: &N,
+                            filter:  |@mut Body<N, LV, AV, M, II>| -> bool)
+                            -> Option<RayHit<N, LV, AV, M, II>> {
+        let mut bodies = ~[];
+
+        self.broad_phase.interferences_with_ray(ray, &mut bodies);
+
+        let mut candidates = ~[];
+
+        for b in bodies.iter() {
+            if filter(*b) {
+                match ray_aabb(ray, &b.bounding_volume()) {
+                    Some((toi, _)) => {
+                        if toi <= *max_toi {
+                            candidates.push((*b, toi))
+                        }
+                    },
+                    None => { }
+                }
+            }
+        }
+
+        candidates.sort_by(|a, b| {
+            match (a, b) {
+                (&(_, ref ta), &(_, ref tb)) => compare(ta, tb)
+            }
+        });
+
+        let mut res: Option<RayHit<N, LV, AV, M, II>> = None;
+        let mut best = max_toi.clone();
+
+        for &(b, ref bv_toi) in candidates.iter() {
+            if *bv_toi > best {
+                break
+            }
+
+            match cast_ray_on_body(b, ray) {
+                Some((toi, normal)) => {
+                    if toi <= best {
+                        best = toi.clone();
+                        res  = Some(RayHit::new(b, ray, toi, normal))
                     }
                 },
-                SB(_) => fail!("Not yet implemented.")
+                None => { }
             }
         }
+
+        res
+    }
+}
+
+/// A body hit by a ray.
+///
+/// Soft bodies have no surface: the ray is cast on the bounding box of their points, so `toi`,
+/// `point` and `normal` are those of the face of this box hit by the ray.
+pub struct RayHit<N, LV, AV, M, II> {
+    /// The body hit by the ray.
+    body:   @mut Body<N, LV, AV, M, II>,
+    /// The time of impact: the hit point is `ray.orig + ray.dir * toi`.
+    toi:    N,
+    /// The world-space hit point.
+    point:  LV,
+    /// The world-space surface normal at the hit point.
+    normal: LV
+}
+
+impl<N: Clone, LV: Clone + Add<LV, LV> + Mul<N, LV>, AV, M, II> RayHit<N, LV, AV, M, II> {
+    pub fn new(body:   @mut Body<N, LV, AV, M, II>,
+               ray:    &Ray<LV>,
+               toi:    N,
+               normal: LV)
+               -> RayHit<N, LV, AV, M, II> {
+        RayHit {
+            body:   body,
+            point:  ray.orig + ray.dir * toi,
+            toi:    toi,
+            normal: normal
+        }
+    }
+}
+
+impl<N: Clone, LV: Clone, AV, M, II> Clone for RayHit<N, LV, AV, M, II> {
+    fn clone(&self) -> RayHit<N, LV, AV, M, II> {
+        RayHit {
+            body:   self.body,
+            toi:    self.toi.clone(),
+            point:  self.point.clone(),
+            normal: self.normal.clone()
+        }
+    }
+}
+
+/// The time of impact and the surface normal of a ray on a body.
+///
+/// Soft bodies have no surface: they are approximated by the bounding box of their points.
+pub fn cast_ray_on_body<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                        body: @mut Body<N, LV, AV, M, II>,
+                        ray:  &Ray<LV>)
+                        -> Option<(N, LV)> {
+    match *body {
+        SB(ref sb) => ray_aabb(ray, sb.aabb()),
+        _          => {
+            let rb = body.to_rigid_body_or_fail();
+
+            rb.geom().toi_and_normal_with_transform_and_ray(rb.transform_ref(), ray)
+        }
+    }
+}
+
+/// The time of impact and the normal of the face hit by a ray on an AABB.
+///
+/// If the ray starts inside of the box, the time of impact is zero and the normal is opposite to
+/// the ray direction.
+fn ray_aabb<N:  Clone + NPhysicsScalar,
+            LV: Clone + NPhysicsDirection<N, AV>,
+            AV>(
+            ray:  &Ray<LV>,
+            aabb: &AABB<N, LV>)
+            -> Option<(N, LV)> {
+    let _0: N      = na::zero();
+    let _1: N      = na::one();
+    let _M: N      = Bounded::max_value();
+    let mut tmin   = -_M;
+    let mut tmax   = _M.clone();
+    let mut normal = None;
+
+    for k in range(0u, na::dim::<LV>()) {
+        let orig = ray.orig.at(k);
+        let dir  = ray.dir.at(k);
+        let lo   = aabb.mins().at(k);
+        let hi   = aabb.maxs().at(k);
+
+        if dir.is_zero() {
+            if orig < lo || orig > hi {
+                return None
+            }
+        }
+        else {
+            // entering through the `lo` face means hitting a normal pointing toward `-e_k`
+            let mut t1   = (lo - orig) / dir;
+            let mut t2   = (hi - orig) / dir;
+            let mut sign = -_1;
+
+            if t1 > t2 {
+                util::swap(&mut t1, &mut t2);
+                sign = _1.clone();
+            }
+
+            if t1 > tmin {
+                tmin   = t1;
+                normal = Some((k, sign));
+            }
+
+            tmax = tmax.min(&t2);
+
+            if tmin > tmax {
+                return None
+            }
+        }
+    }
+
+    if tmax < _0 {
+        return None
+    }
+
+    match normal {
+        Some((k, sign)) if tmin >= _0 => {
+            let mut n: LV = na::zero();
+
+            n.set(k, sign);
+
+            Some((tmin, n))
+        },
+        _ => {
+            let mut n = -ray.dir;
+
+            n.normalize();
+
+            Some((_0, n))
+        }
+    }
+}
+
+fn compare<N: Ord>(a: &N, b: &N) -> Ordering {
+    if *a < *b {
+        Less
+    }
+    else if *a > *b {
+        Greater
+    }
+    else {
+        Equal
     }
 }
 
@@ -231,7 +481,12 @@ for BodiesBodies<N, LV, AV, M, II, BF> {
             self.broad_phase.update();
         }
 
+        // the pairs are counted while they are updated, so that the statistics are free
+        self.num_pairs = 0;
+
         self.broad_phase.for_each_pair_mut(|b1, b2, cd| {
+            self.num_pairs = self.num_pairs + 1;
+
             match *cd {
                 GG(ref mut d) => {
                     let rb1 = b1.to_rigid_body_or_fail();
@@ -295,3 +550,93 @@ BodyActivationSignalHandler<Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>
         self.deactivate(b)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::managed;
+    use nalgebra::na::{Vec3, Translation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use ncollide::ray::Ray;
+    use aliases::dim3::{BodyWorld3d, Body3d};
+    use world::BodyWorld;
+    use object::{RigidBody, SoftBody, Static, RB, SB};
+    use super::cast_ray_on_body;
+
+    // three balls of radius 0.5 centered at `x = 2`, `x = 5` and `x = 8`, inserted in the given
+    // order, and a ray cast from the origin toward them
+    fn aligned_balls() -> (BodyWorld3d<f64>, ~[@mut Body3d<f64>], Ray<Vec3<f64>>) {
+        let mut world  = BodyWorld::new();
+        let mut bodies = ~[];
+
+        for x in [8.0f64, 2.0, 5.0].iter() {
+            let mut rb = RigidBody::new(Geom::new_ball(0.5f64), 0.0, Static, 0.3, 0.6);
+
+            rb.append_translation(&Vec3::new(*x, 0.0, 0.0));
+
+            let body = @mut RB(rb);
+
+            world.add_body(body);
+            bodies.push(body);
+        }
+
+        // registers the bodies on the broad phase
+        world.step(0.016);
+
+        (world, bodies, Ray::new(na::zero(), Vec3::new(1.0, 0.0, 0.0)))
+    }
+
+    #[test]
+    fn test_ray_hits_are_sorted() {
+        let (world, bodies, ray) = aligned_balls();
+        let mut hits = ~[];
+
+        world.cast_ray(&ray, &100.0, |_| true, &mut hits);
+
+        assert!(hits.len() == 3);
+
+        for (hit, &(i, toi)) in hits.iter().zip([(1u, 1.5f64), (2, 4.5), (0, 7.5)].iter()) {
+            assert!(managed::mut_ptr_eq(hit.body, bodies[i]));
+            assert!((hit.toi - toi).abs() < 1.0e-3);
+            assert!(na::norm(&(hit.point - Vec3::new(toi, 0.0, 0.0))) < 1.0e-3);
+            assert!(na::norm(&(hit.normal - Vec3::new(-1.0, 0.0, 0.0))) < 1.0e-3);
+        }
+    }
+
+    #[test]
+    fn test_ray_cast_filter_and_max_toi() {
+        let (world, bodies, ray) = aligned_balls();
+        let mut hits = ~[];
+
+        world.cast_ray(&ray, &5.0, |_| true, &mut hits);
+
+        assert!(hits.len() == 2);
+
+        let nearest = bodies[1];
+        let closest = world.cast_ray_closest(&ray, &100.0, |b| !managed::mut_ptr_eq(b, nearest));
+
+        assert!(managed::mut_ptr_eq(closest.unwrap().body, bodies[2]));
+        assert!(world.cast_ray_closest(&ray, &1.0, |_| true).is_none());
+    }
+
+    #[test]
+    fn test_ray_cast_on_the_bounding_box_of_soft_bodies() {
+        let points = ~[Vec3::new(0.0f64, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0)];
+        let mut sb = SoftBody::new_from_mesh(points, ~[], ~[], ~[1.0, 1.0], ~[]);
+        let ray    = Ray::new(Vec3::new(-2.0f64, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0));
+
+        let (toi, normal) = cast_ray_on_body(@mut SB(sb.clone()), &ray).unwrap();
+
+        assert!((toi - 2.0).abs() < 1.0e-10);
+        assert!(na::norm(&(normal - Vec3::new(-1.0, 0.0, 0.0))) < 1.0e-10);
+
+        // the bounding box follows the points once updated
+        sb.points()[0].position = Vec3::new(2.0, 0.0, 0.0);
+        sb.points()[1].position = Vec3::new(3.0, 1.0, 1.0);
+        sb.update_aabb();
+
+        let (toi, _) = cast_ray_on_body(@mut SB(sb), &ray).unwrap();
+
+        assert!((toi - 4.0).abs() < 1.0e-10);
+    }
+}
@@ -0,0 +1,297 @@
+use std::util;
+use std::task;
+use std::comm::{Port, Chan};
+use ncollide::geom::Geom;
+use ncollide::bounding_volume::AABB;
+use ncollide::broad::{InterferencesBroadPhase, BoundingVolumeBroadPhase};
+use ncollide::narrow::{CollisionDetector, GeomGeom};
+use object::Body;
+use detection::constraint::Constraint;
+use detection::detector::Detector;
+use detection::collision::bodies_bodies::{BodiesBodies, PairwiseDetector, GG, Unsuported, Dispatcher};
+use signal::signal::SignalEmiter;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform, NPhysicsInertia};
+
+/// The dispatcher to use with the broad phase of a `BodiesBodiesParallel`.
+///
+/// The pairwise detectors it creates do not share anything with the bodies, so they can be sent
+/// to another task.
+pub type DispatcherParallel<N, LV, AV, M, II> = Dispatcher<N, LV, AV, M, II>;
+
+/// A narrow phase update which can be executed on another task.
+struct NarrowPhaseJob<N, LV, AV, M> {
+    detector:  GeomGeom<N, LV, AV, M>,
+    m1:        M,
+    g1:        Geom<N, LV, M>,
+    m2:        M,
+    g2:        Geom<N, LV, M>,
+    num_colls: uint
+}
+
+/// A collision detector which distributes the narrow phase over several tasks.
+///
+/// This behaves exactly like `BodiesBodies` (which it uses for everything but the narrow phase
+/// update). The results are merged, and the collision start/end signals emitted, in the broad
+/// phase pairs order so that the output does not depend on the number of threads.
+pub struct BodiesBodiesParallel<N, LV, AV, M, II, BF> {
+    priv detector:    @mut BodiesBodies<N, LV, AV, M, II, BF>,
+    priv pairs:       ~[Option<(@mut Body<N, LV, AV, M, II>, @mut Body<N, LV, AV, M, II>)>],
+    priv num_threads: uint
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
+     BF: 'static + InterferencesBroadPhase<Body<N, LV, AV, M, II>, PairwiseDetector<N, LV, AV, M>>>
+BodiesBodiesParallel<N, LV, AV, M, II, BF> {
+    pub fn new(events:      @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+               bf:          @mut BF,
+               update_bf:   bool,
+               num_threads: uint) -> @mut BodiesBodiesParallel<N, LV, AV, M, II, BF> {
+        assert!(num_threads != 0, "The number of threads must not be zero.");
+
+        @mut BodiesBodiesParallel {
+            detector:    BodiesBodies::new(events, bf, update_bf),
+            pairs:       ~[],
+            num_threads: num_threads
+        }
+    }
+
+    /// The serial collision detector used for everything but the narrow phase update.
+    pub fn bodies_bodies(&self) -> @mut BodiesBodies<N, LV, AV, M, II, BF> {
+        self.detector
+    }
+
+    /// The number of tasks the narrow phase is distributed over.
+    pub fn num_threads(&self) -> uint {
+        self.num_threads
+    }
+
+    /// Sets the number of tasks the narrow phase is distributed over.
+    pub fn set_num_threads(&mut self, num: uint) {
+        assert!(num != 0, "The number of threads must not be zero.");
+
+        self.num_threads = num
+    }
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
+     BF: 'static +
+         InterferencesBroadPhase<Body<N, LV, AV, M, II>, PairwiseDetector<N, LV, AV, M>> +
+         BoundingVolumeBroadPhase<Body<N, LV, AV, M, II>, AABB<N, LV>>>
+Detector<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>
+for BodiesBodiesParallel<N, LV, AV, M, II, BF> {
+    #[inline]
+    fn add(&mut self, o: @mut Body<N, LV, AV, M, II>) {
+        self.detector.add(o)
+    }
+
+    #[inline]
+    fn remove(&mut self, o: @mut Body<N, LV, AV, M, II>) {
+        self.detector.remove(o)
+    }
+
+    fn update(&mut self) {
+        let detector = self.detector;
+
+        if detector.update_bf {
+            detector.broad_phase.update();
+        }
+
+        /*
+         * Move the pairwise detectors out of the broad phase, together with a copy of the
+         * positions and geometries they need.
+         */
+        let mut jobs = ~[];
+
+        self.pairs.clear();
+
+        detector.broad_phase.for_each_pair_mut(|b1, b2, cd| {
+            match util::replace(cd, Unsuported) {
+                GG(d) => {
+                    let rb1 = b1.to_rigid_body_or_fail();
+                    let rb2 = b2.to_rigid_body_or_fail();
+
+                    jobs.push(NarrowPhaseJob {
+                        num_colls: d.num_colls(),
+                        detector:  d,
+                        m1:        rb1.transform_ref().clone(),
+                        g1:        rb1.geom().clone(),
+                        m2:        rb2.transform_ref().clone(),
+                        g2:        rb2.geom().clone()
+                    });
+
+                    self.pairs.push(Some((b1, b2)))
+                },
+                Unsuported => self.pairs.push(None)
+            }
+        });
+
+        let mut jobs = update_in_parallel(jobs, self.num_threads).move_iter();
+
+        /*
+         * Put the detectors back, in the same order.
+         */
+        let mut i = 0;
+
+        detector.broad_phase.for_each_pair_mut(|_, _, cd| {
+            match self.pairs[i] {
+                Some((b1, b2)) => {
+                    let job       = jobs.next().expect("Broad phase pairs changed during the update.");
+                    let new_ncols = job.detector.num_colls();
+
+                    if job.num_colls == 0 && new_ncols != 0 {
+                        detector.signals.emit_collision_started(b1, b2);
+                    }
+                    else if job.num_colls != 0 && new_ncols == 0 {
+                        detector.signals.emit_collision_ended(b1, b2);
+                    }
+
+                    *cd = GG(job.detector)
+                },
+                None => { }
+            }
+
+            i = i + 1;
+        });
+
+        self.pairs.clear();
+    }
+
+    #[inline]
+    fn interferences(&mut self, out: &mut ~[Constraint<N, LV, AV, M, II>]) {
+        self.detector.interferences(out)
+    }
+
+    #[inline]
+    fn priority(&self) -> f64 { 50.0 }
+}
+
+/// Updates a set of pairwise detectors on `num_threads` tasks and returns them in the same order.
+fn update_in_parallel<N:  'static + Clone + NPhysicsScalar,
+                      LV: 'static + Clone + NPhysicsDirection<N, AV>,
+                      AV: 'static + Clone + NPhysicsOrientation<N>,
+                      M:  'static + Clone + NPhysicsTransform<LV, AV>>(
+                      jobs:        ~[NarrowPhaseJob<N, LV, AV, M>],
+                      num_threads: uint)
+                      -> ~[NarrowPhaseJob<N, LV, AV, M>] {
+    let group_size = jobs.len() / num_threads + 1;
+    let mut ports  = ~[];
+    let mut group  = ~[];
+
+    for job in jobs.move_iter() {
+        group.push(job);
+
+        if group.len() == group_size {
+            ports.push(spawn_group(util::replace(&mut group, ~[])));
+        }
+    }
+
+    if !group.is_empty() {
+        ports.push(spawn_group(group));
+    }
+
+    // collect the results in the same order as they were sent
+    let mut res = ~[];
+
+    for port in ports.iter() {
+        res.push_all_move(port.recv());
+    }
+
+    res
+}
+
+fn spawn_group<N:  'static + Clone + NPhysicsScalar,
+               LV: 'static + Clone + NPhysicsDirection<N, AV>,
+               AV: 'static + Clone + NPhysicsOrientation<N>,
+               M:  'static + Clone + NPhysicsTransform<LV, AV>>(
+               group: ~[NarrowPhaseJob<N, LV, AV, M>])
+               -> Port<~[NarrowPhaseJob<N, LV, AV, M>]> {
+    let (port, chan) = Chan::new();
+
+    do task::spawn {
+        let mut group = group;
+
+        for job in group.mut_iter() {
+            job.detector.update(&job.m1, &job.g1, &job.m2, &job.g2);
+        }
+
+        chan.send(group);
+    }
+
+    port
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Translation};
+    use ncollide::geom::Geom;
+    use aliases::dim3::BodyWorld3d;
+    use world::BodyWorld;
+    use object::{RigidBody, Static, Dynamic, RB};
+    use detection::BodiesBodiesParallel;
+
+    // a ground, four stacks of two balls, and a falling ball
+    fn add_bodies(world: &mut BodyWorld3d<f64>) {
+        world.set_gravity(Vec3::new(0.0f64, -9.81, 0.0));
+
+        let ground = RigidBody::new(Geom::new_plane(Vec3::new(0.0f64, 1.0, 0.0)), 0.0, Static,
+                                    0.3, 0.6);
+
+        world.add_body(@mut RB(ground));
+
+        for (x, y) in [(0.0, 0.45), (0.0, 1.4), (1.5, 0.45), (1.5, 1.4), (3.0, 0.45), (3.0, 1.4),
+                       (4.5, 0.45), (4.5, 1.4), (0.0, 3.0)].iter().map(|&p| p) {
+            let mut rb = RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6);
+
+            rb.append_translation(&Vec3::new(x, y, 0.0));
+
+            world.add_body(@mut RB(rb))
+        }
+    }
+
+    // the positions of the rigid bodies of `world`
+    fn positions(world: &BodyWorld3d<f64>) -> ~[Vec3<f64>] {
+        world.world().objects().iter().map(|o| {
+            match **o {
+                RB(ref rb) => rb.transform_ref().translation(),
+                _          => fail!("Unexpected body.")
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn test_parallel_narrow_phase_matches_the_serial_one() {
+        let mut serial:   BodyWorld3d<f64> = BodyWorld::new();
+        let mut parallel: BodyWorld3d<f64> = BodyWorld::new();
+
+        // replaced before adding the bodies, so that both broad phases see the same operations
+        let detector = BodiesBodiesParallel::new(parallel.events(),
+                                                 parallel.collison_detector().broad_phase(),
+                                                 false,
+                                                 3);
+
+        assert!(parallel.world_mut().replace_detector("collisions", detector).is_some());
+
+        add_bodies(&mut serial);
+        add_bodies(&mut parallel);
+
+        for _ in range(0u, 60) {
+            serial.step(0.016);
+            parallel.step(0.016);
+
+            let num_interferences = serial.statistics().num_interferences;
+
+            assert!(parallel.statistics().num_interferences == num_interferences);
+        }
+
+        assert!(serial.statistics().num_contacts != 0);
+        assert!(positions(&serial) == positions(&parallel));
+    }
+}
@@ -2,11 +2,23 @@ use ncollide::contact::Contact;
 use object::Body;
 use detection::joint::ball_in_socket::BallInSocket;
 use detection::joint::fixed::Fixed;
+use detection::joint::distance::Distance;
+use detection::joint::cone_twist::ConeTwist;
+use detection::joint::generic_joint::GenericJoint;
+use detection::joint::gear::Gear;
+use detection::joint::pulley::Pulley;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
 
 pub enum Constraint<N, LV, AV, M, II> {
     RBRB(@mut Body<N, LV, AV, M, II>, @mut Body<N, LV, AV, M, II>, Contact<N, LV>),
     BallInSocket(@mut BallInSocket<N, LV, AV, M, II>),
     Fixed(@mut Fixed<N, LV, AV, M, II>),
+    Distance(@mut Distance<N, LV, AV, M, II>),
+    ConeTwist(@mut ConeTwist<N, LV, AV, M, II>),
+    GenericJoint(@mut GenericJoint<N, LV, AV, M, II>),
+    Gear(@mut Gear<N, LV, AV, M, II>),
+    Pulley(@mut Pulley<N, LV, AV, M, II>),
 }
 
 impl<N: Clone, LV: Clone, AV, M, II> Clone for Constraint<N, LV, AV, M, II> {
@@ -15,6 +27,33 @@ impl<N: Clone, LV: Clone, AV, M, II> Clone for Constraint<N, LV, AV, M, II> {
             RBRB(a, b, ref c) => RBRB(a, b, c.clone()),
             BallInSocket(bis) => BallInSocket(bis),
             Fixed(f)          => Fixed(f),
+            Distance(d)       => Distance(d),
+            ConeTwist(ct)     => ConeTwist(ct),
+            GenericJoint(g)   => GenericJoint(g),
+            Gear(gr)          => Gear(gr),
+            Pulley(p)         => Pulley(p),
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Constraint<N, LV, AV, M, II> {
+    /// The two bodies linked by this constraint. `None` stands for a joint attached to the ground.
+    pub fn anchors(&self)
+                   -> (Option<@mut Body<N, LV, AV, M, II>>, Option<@mut Body<N, LV, AV, M, II>>) {
+        match *self {
+            RBRB(a, b, _)     => (Some(a), Some(b)),
+            BallInSocket(bis) => (bis.anchor1().body, bis.anchor2().body),
+            Fixed(f)          => (f.anchor1().body, f.anchor2().body),
+            Distance(d)       => (d.anchor1().body, d.anchor2().body),
+            ConeTwist(ct)     => (ct.anchor1().body, ct.anchor2().body),
+            GenericJoint(g)   => (g.anchor1().body, g.anchor2().body),
+            Gear(gr)          => (gr.anchor1().body, gr.anchor2().body),
+            Pulley(p)         => (p.anchor1().body, p.anchor2().body)
         }
     }
 }
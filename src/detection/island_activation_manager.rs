@@ -4,9 +4,10 @@ use nalgebra::na;
 use ncollide::util::hash_map::HashMap;
 use ncollide::util::hash::UintTWHash;
 use integration::Integrator;
+use utils::union_find::{UFindSet, find, union};
 use detection::detector::Detector;
-use detection::constraint::{Constraint, RBRB, BallInSocket, Fixed};
-use object::{Body, RB, SB};
+use detection::constraint::Constraint;
+use object::{Body, RB, SB, MB};
 use signal::signal::{SignalEmiter, BodyActivationRequestHandler,
                      CollisionSignalHandler};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
@@ -15,14 +16,16 @@ use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NP
 struct BodyWithEnergy<N, LV, AV, M, II> {
     // NOTE: that is the place to put a `can_be_deactivated` flag if needed…
     body:   @mut Body<N, LV, AV, M, II>,
-    energy: N
+    energy: N,
+    island: Option<uint>
 }
 
 impl<N, LV, AV, M, II> BodyWithEnergy<N, LV, AV, M, II> {
     pub fn new(body: @mut Body<N, LV, AV, M, II>, energy: N) -> BodyWithEnergy<N, LV, AV, M, II> {
         BodyWithEnergy {
             body:   body,
-            energy: energy
+            energy: energy,
+            island: None
         }
     }
 }
@@ -35,7 +38,10 @@ pub struct IslandActivationManager<N, LV, AV, M, II> {
     bodies:         HashMap<uint, BodyWithEnergy<N, LV, AV, M, II>, UintTWHash>,
     ufind:          ~[UFindSet],
     can_deactivate: ~[bool],
-    collector:      ~[Constraint<N, LV, AV, M, II>]
+    collector:      ~[Constraint<N, LV, AV, M, II>],
+    num_islands:    uint,
+    num_island_ids: uint,
+    partitioned:    Option<uint>
 }
 
 impl<N:  'static + Clone + NPhysicsScalar,
@@ -58,7 +64,10 @@ IslandActivationManager<N, LV, AV, M, II> {
             bodies:         HashMap::new(UintTWHash::new()),
             ufind:          ~[],
             can_deactivate: ~[],
-            collector:      ~[]
+            collector:      ~[],
+            num_islands:    0,
+            num_island_ids: 0,
+            partitioned:    None
         };
 
         let key = ptr::to_mut_unsafe_ptr(res) as uint;
@@ -76,6 +85,40 @@ IslandActivationManager<N, LV, AV, M, II> {
         res
     }
 
+    /// The number of islands of active bodies found during the last update.
+    pub fn num_islands(&self) -> uint {
+        self.num_islands
+    }
+
+    /// Whether the islands found during the last update partition exactly `constraints`.
+    ///
+    /// This is the case when `constraints` were output by the last update, if no other detector
+    /// added any constraint after this one, and if the partition was not invalidated.
+    pub fn partitions(&self, constraints: &[Constraint<N, LV, AV, M, II>]) -> bool {
+        self.partitioned == Some(constraints.len())
+    }
+
+    /// Marks the islands found during the last update as out of date.
+    pub fn invalidate_partition(&mut self) {
+        self.partitioned = None
+    }
+
+    /// The identifier of the island `b` was part of during the last update.
+    ///
+    /// This is `None` if `b` was inactive at that time, including if it has been woken up by the
+    /// update itself. Identifiers are smaller than `self.num_island_ids()` but are not contiguous.
+    pub fn island_id(&self, b: @mut Body<N, LV, AV, M, II>) -> Option<uint> {
+        match self.bodies.find(&(ptr::to_mut_unsafe_ptr(b) as uint)) {
+            Some(b) => b.island,
+            None    => None
+        }
+    }
+
+    /// An upper bound of the island identifiers found during the last update.
+    pub fn num_island_ids(&self) -> uint {
+        self.num_island_ids
+    }
+
     pub fn doit(&mut self) {
         self.mix_factor = self.threshold.clone();
     }
@@ -156,7 +199,8 @@ for IslandActivationManager<N, LV, AV, M, II> {
         // Update bodies energy
         for b in self.bodies.elements_mut().mut_iter() {
             match *b.value.body {
-                RB(ref rb) => {
+                RB(_) | MB(_) => {
+                    let rb = b.value.body.to_rigid_body_or_fail();
                     // NOTE: this is not the kinetic energy, just a hacky value to detect stabilization
                     // FIXME: take the time in account (to make a true RWA)
                     let _1: N = na::one();
@@ -175,6 +219,8 @@ for IslandActivationManager<N, LV, AV, M, II> {
 
     fn interferences(&mut self, out: &mut ~[Constraint<N, LV, AV, M, II>]) {
         // here goes all the magic :p
+        self.partitioned = None;
+
         for d in self.can_deactivate.mut_iter() {
             *d = true
         }
@@ -192,55 +238,31 @@ for IslandActivationManager<N, LV, AV, M, II> {
         }
 
         for c in out.iter() {
-            match *c {
-                RBRB(obj1, obj2, _) =>
+            match c.anchors() {
+                (Some(b1), Some(b2)) =>
                     // we compute islands on awaken objects only
-                    if obj1.is_active() && obj2.is_active() { // FIXME: add a `propagates_forces` function?
-                        union(obj1.index() as uint, obj2.index() as uint, self.ufind)
+                    if b1.is_active() && b2.is_active() { // FIXME: add a `propagates_forces` function?
+                        union(b1.index() as uint, b2.index() as uint, self.ufind)
                     },
-                BallInSocket(bis) => {
-                    match bis.anchor1().body {
-                        Some(b1) => {
-                            if b1.is_active() {
-                                match bis.anchor2().body {
-                                    Some(b2) => {
-                                        if b2.is_active() {
-                                            union(b1.index() as uint, b2.index() as uint, self.ufind)
-                                        }
-                                    },
-                                    None => { }
-                                }
-                            }
-                        },
-                        None => { }
-                    }
-                },
-                Fixed(f) => { // FIXME: code duplication from the BallInSocket variant
-                    match f.anchor1().body {
-                        Some(b1) => {
-                            if b1.is_active() {
-                                match f.anchor2().body {
-                                    Some(b2) => {
-                                        if b2.is_active() {
-                                            union(b1.index() as uint, b2.index() as uint, self.ufind)
-                                        }
-                                    },
-                                    None => { }
-                                }
-                            }
-                        },
-                        None => { }
-                    }
-                }
+                _ => { }
             }
         }
 
         // find out whether islands can be deactivated
+        self.num_islands = 0;
+
         for i in range(0u, self.ufind.len()) {
             let root = find(i, self.ufind);
-            self.can_deactivate[root] = self.can_deactivate[root] && self.can_deactivate(i)
+            self.can_deactivate[root] = self.can_deactivate[root] && self.can_deactivate(i);
+            self.bodies.elements_mut()[i].value.island = Some(root);
+
+            if root == i {
+                self.num_islands = self.num_islands + 1;
+            }
         }
 
+        self.num_island_ids = self.ufind.len();
+
         // NOTE: this one is tricky
         // deactivate islands having only deactivable objects
 
@@ -267,31 +289,17 @@ for IslandActivationManager<N, LV, AV, M, II> {
 
         // remove every collision between pair of deactivated bodies
         out.retain(|o| {
-            match *o {
-                RBRB(obj1, obj2, _) => obj1.is_active() || obj2.is_active(),
-                BallInSocket(bis)   => {
-                    let good = match bis.anchor1().body {
-                        None    => false,
-                        Some(b) => b.is_active()
-                    };
+            let (b1, b2) = o.anchors();
 
-                    good || match bis.anchor2().body {
-                            None    => false,
-                            Some(b) => b.is_active()
-                        }
-                }
-                Fixed(f) => { // FIXME: code duplication from the BallInSocket varient
-                    let good = match f.anchor1().body {
-                        None    => false,
-                        Some(b) => b.is_active()
-                    };
+            let good = match b1 {
+                None    => false,
+                Some(b) => b.is_active()
+            };
 
-                    good || match f.anchor2().body {
-                            None    => false,
-                            Some(b) => b.is_active()
-                        }
+            good || match b2 {
+                    None    => false,
+                    Some(b) => b.is_active()
                 }
-            }
         });
 
         /*
@@ -300,37 +308,21 @@ for IslandActivationManager<N, LV, AV, M, II> {
         let mut i = 0u;
         while i != out.len() { // we use a `while` loop because `out.len()` might change
             let mut to_activate = None;
+            let (b1, b2)        = out[i].anchors();
 
-            match out[i] {
-                RBRB(obj1, obj2, _) => {
-                    if !obj1.is_active() && obj1.can_move() { // FIXME: ? && obj1.is_activable() {
-                        to_activate = Some(obj1);
-                    }
-                    else if !obj2.is_active() && obj2.can_move() { // FIXME: ? && obj2.is_activable() {
-                        to_activate = Some(obj2);
-                    }
+            match b1 {
+                Some(b) => if !b.is_active() && b.can_move() { // FIXME: ? && b.is_activable() {
+                    to_activate = Some(b)
                 },
-                BallInSocket(bis) => {
-                    match bis.anchor1().body {
-                        None    => { },
-                        Some(b) => if !b.is_active() && b.can_move() { to_activate = Some(b) }
-                    }
-
-                    match bis.anchor2().body {
-                        None    => { },
-                        Some(b) => if !b.is_active() && b.can_move() { to_activate = Some(b) }
-                    }
-                },
-                Fixed(f) => { // FIXME: code duplication from BallInSocket
-                    match f.anchor1().body {
-                        None    => { },
-                        Some(b) => if !b.is_active() && b.can_move() { to_activate = Some(b) }
-                    }
-
-                    match f.anchor2().body {
-                        None    => { },
-                        Some(b) => if !b.is_active() && b.can_move() { to_activate = Some(b) }
-                    }
+                None => { }
+            }
+
+            if to_activate.is_none() {
+                match b2 {
+                    Some(b) => if !b.is_active() && b.can_move() { // FIXME: ? && b.is_activable() {
+                        to_activate = Some(b)
+                    },
+                    None => { }
                 }
             }
 
@@ -345,6 +337,8 @@ for IslandActivationManager<N, LV, AV, M, II> {
 
             i = i + 1;
         }
+
+        self.partitioned = Some(out.len());
     }
 
     #[inline]
@@ -353,61 +347,6 @@ for IslandActivationManager<N, LV, AV, M, II> {
     }
 }
 
-/*
- * Union find algorithm to build the collision islands
- */
-struct UFindSet {
-    parent: uint,
-    rank:   uint
-}
-
-impl UFindSet {
-    #[inline]
-    pub fn new(key: uint) -> UFindSet {
-        UFindSet {
-            parent: key,
-            rank:   0
-        }
-    }
-
-    #[inline]
-    pub fn reinit(&mut self, key: uint) {
-        self.parent = key;
-        self.rank   = 0;
-    }
-}
-
-fn find(x: uint, sets: &mut [UFindSet]) -> uint {
-    if sets[x].parent != x {
-        sets[x].parent = find(sets[x].parent, sets);
-    }
-
-    sets[x].parent
-}
- 
-fn union(x: uint, y: uint, sets: &mut [UFindSet]) {
-     let x_root = find(x, sets);
-     let y_root = find(y, sets);
-
-     if x_root == y_root {
-         return
-     }
-
-     let rankx = sets[x_root].rank;
-     let ranky = sets[y_root].rank;
-
-     if rankx < ranky {
-         sets[x_root].parent = y_root
-     }
-     else if rankx > ranky {
-         sets[y_root].parent = x_root
-     }
-     else {
-         sets[y_root].parent = x_root;
-         sets[x_root].rank   = rankx + 1
-     }
-}
-
 impl<N:  'static + Clone + NPhysicsScalar,
      LV: 'static + Clone + NPhysicsDirection<N, AV>,
      AV: 'static + Clone + NPhysicsOrientation<N>,
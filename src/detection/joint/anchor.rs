@@ -1,4 +1,4 @@
-use object::{Body, RB, SB};
+use object::{Body, RB, SB, MB};
 use nalgebra::na;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
@@ -8,6 +8,15 @@ pub struct Anchor<N, LV, AV, M, II, P> {
     position: P
 }
 
+impl<N, LV, AV, M, II, P: Clone> Clone for Anchor<N, LV, AV, M, II, P> {
+    fn clone(&self) -> Anchor<N, LV, AV, M, II, P> {
+        Anchor {
+            body:     self.body,
+            position: self.position.clone()
+        }
+    }
+}
+
 impl<N, LV, AV, M, II, P> Anchor<N, LV, AV, M, II, P> {
     pub fn new(body: Option<@mut Body<N, LV, AV, M, II>>, position: P) -> Anchor<N, LV, AV, M, II, P> {
         Anchor {
@@ -29,7 +38,8 @@ Anchor<N, LV, AV, M, II, P> {
             Some(b) => {
                 match *b {
                     RB(ref rb) => rb.center_of_mass().clone(),
-                    SB(_)      => fail!("Not yet implemented.")
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().center_of_mass().clone()
                 }
             },
             None => na::zero()
@@ -1,13 +1,52 @@
+use std::any::Any;
 use nalgebra::na::Transform;
 use detection::joint::anchor::Anchor;
-use object::{RB, SB};
+use resolution::constraint::softness::Softness;
+use object::{RB, SB, MB};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
+/// A limit on the rotation allowed by a ball-in-socket joint.
+///
+/// The angle between `axis1` and `axis2`, respectively expressed in the local frames of the
+/// first and second bodies of the joint, cannot exceed `max_angle` (in radians).
+#[deriving(Eq, ToStr, Clone)]
+pub struct AngularLimit<N, LV> {
+    axis1:     LV,
+    axis2:     LV,
+    max_angle: N
+}
+
+impl<N, LV> AngularLimit<N, LV> {
+    pub fn new(axis1: LV, axis2: LV, max_angle: N) -> AngularLimit<N, LV> {
+        AngularLimit {
+            axis1:     axis1,
+            axis2:     axis2,
+            max_angle: max_angle
+        }
+    }
+}
+
 pub struct BallInSocket<N, LV, AV, M, II> {
-    priv up_to_date: bool,
-    priv anchor1:    Anchor<N, LV, AV, M, II, LV>,
-    priv anchor2:    Anchor<N, LV, AV, M, II, LV>,
+    priv up_to_date:    bool,
+    priv anchor1:       Anchor<N, LV, AV, M, II, LV>,
+    priv anchor2:       Anchor<N, LV, AV, M, II, LV>,
+    priv softness:      Option<Softness<N>>,
+    priv angular_limit: Option<AngularLimit<N, LV>>,
+    priv user_data:     Option<@Any>
+}
+
+impl<N: Clone, LV: Clone, AV, M, II> Clone for BallInSocket<N, LV, AV, M, II> {
+    fn clone(&self) -> BallInSocket<N, LV, AV, M, II> {
+        BallInSocket {
+            up_to_date:    false,
+            anchor1:       self.anchor1.clone(),
+            anchor2:       self.anchor2.clone(),
+            softness:      self.softness.clone(),
+            angular_limit: self.angular_limit.clone(),
+            user_data:     self.user_data
+        }
+    }
 }
 
 impl<N, LV, AV, M, II> BallInSocket<N, LV, AV, M, II> {
@@ -15,9 +54,12 @@ impl<N, LV, AV, M, II> BallInSocket<N, LV, AV, M, II> {
                anchor2: Anchor<N, LV, AV, M, II, LV>)
                -> BallInSocket<N, LV, AV, M, II> {
         BallInSocket {
-            up_to_date: false,
-            anchor1:    anchor1,
-            anchor2:    anchor2
+            up_to_date:    false,
+            anchor1:       anchor1,
+            anchor2:       anchor2,
+            softness:      None,
+            angular_limit: None,
+            user_data:     None
         }
     }
 
@@ -36,6 +78,38 @@ impl<N, LV, AV, M, II> BallInSocket<N, LV, AV, M, II> {
     pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
         &self.anchor2
     }
+
+    /// The softness of this joint, `None` if it is rigid.
+    pub fn softness<'r>(&'r self) -> &'r Option<Softness<N>> {
+        &self.softness
+    }
+
+    /// Makes this joint behave like a damped spring, or rigid again with `None`.
+    pub fn set_softness(&mut self, softness: Option<Softness<N>>) {
+        self.up_to_date = false;
+        self.softness   = softness
+    }
+
+    /// The limit on the rotation allowed by this joint, `None` if the rotation is free.
+    pub fn angular_limit<'r>(&'r self) -> &'r Option<AngularLimit<N, LV>> {
+        &self.angular_limit
+    }
+
+    /// Limits the rotation allowed by this joint, or frees it with `None`.
+    pub fn set_angular_limit(&mut self, angular_limit: Option<AngularLimit<N, LV>>) {
+        self.up_to_date    = false;
+        self.angular_limit = angular_limit
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
 }
 
 impl<N:  Clone + NPhysicsScalar,
@@ -63,7 +137,8 @@ BallInSocket<N, LV, AV, M, II> {
             Some(b) => {
                 match *b {
                     RB(ref rb) => rb.transform_ref().transform(&self.anchor1.position),
-                    SB(_)      => fail!("Not yet implemented.")
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref().transform(&self.anchor1.position)
                 }
             },
             None => self.anchor1.position.clone()
@@ -75,7 +150,8 @@ BallInSocket<N, LV, AV, M, II> {
             Some(b) => {
                 match *b {
                     RB(ref rb) => rb.transform_ref().transform(&self.anchor2.position),
-                    SB(_)      => fail!("Not yet implemented.")
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref().transform(&self.anchor2.position)
                 }
             },
             None => self.anchor2.position.clone()
@@ -0,0 +1,165 @@
+use std::any::Any;
+use nalgebra::na;
+use detection::joint::anchor::Anchor;
+use object::{RB, SB, MB};
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// A ball-in-socket joint with limited rotations.
+///
+/// The twist axis of each anchor is the first axis of its local frame. The angle between the two
+/// twist axes (the swing) cannot exceed the swing limit, and the rotation of the second anchor
+/// around its twist axis relative to the first one (the twist) is kept between the twist limits.
+/// In 2d, there is no swing and the twist is the relative rotation of the two anchors.
+///
+/// All the angles are in radians.
+pub struct ConeTwist<N, LV, AV, M, II> {
+    priv up_to_date:  bool,
+    priv anchor1:     Anchor<N, LV, AV, M, II, M>,
+    priv anchor2:     Anchor<N, LV, AV, M, II, M>,
+    priv swing_limit: N,
+    priv min_twist:   N,
+    priv max_twist:   N,
+    priv user_data:   Option<@Any>
+}
+
+impl<N: Clone, LV, AV, M: Clone, II> Clone for ConeTwist<N, LV, AV, M, II> {
+    fn clone(&self) -> ConeTwist<N, LV, AV, M, II> {
+        ConeTwist {
+            up_to_date:  false,
+            anchor1:     self.anchor1.clone(),
+            anchor2:     self.anchor2.clone(),
+            swing_limit: self.swing_limit.clone(),
+            min_twist:   self.min_twist.clone(),
+            max_twist:   self.max_twist.clone(),
+            user_data:   self.user_data
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+ConeTwist<N, LV, AV, M, II> {
+    pub fn new(anchor1:     Anchor<N, LV, AV, M, II, M>,
+               anchor2:     Anchor<N, LV, AV, M, II, M>,
+               swing_limit: N,
+               min_twist:   N,
+               max_twist:   N)
+               -> ConeTwist<N, LV, AV, M, II> {
+        assert!(swing_limit >= na::zero(), "The swing limit must not be negative.");
+        assert!(min_twist <= max_twist, "The minimum twist must not exceed the maximum twist.");
+
+        ConeTwist {
+            up_to_date:  false,
+            anchor1:     anchor1,
+            anchor2:     anchor2,
+            swing_limit: swing_limit,
+            min_twist:   min_twist,
+            max_twist:   max_twist,
+            user_data:   None
+        }
+    }
+
+    pub fn up_to_date(&self) -> bool {
+        self.up_to_date
+    }
+
+    pub fn update(&mut self) {
+        self.up_to_date = true
+    }
+
+    pub fn anchor1<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, M> {
+        &self.anchor1
+    }
+
+    pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, M> {
+        &self.anchor2
+    }
+
+    /// The maximum angle between the twist axes of the two anchors.
+    pub fn swing_limit(&self) -> N {
+        self.swing_limit.clone()
+    }
+
+    pub fn set_swing_limit(&mut self, swing_limit: N) {
+        assert!(swing_limit >= na::zero(), "The swing limit must not be negative.");
+
+        if swing_limit != self.swing_limit {
+            self.up_to_date  = false;
+            self.swing_limit = swing_limit
+        }
+    }
+
+    /// The minimum rotation around the twist axis.
+    pub fn min_twist(&self) -> N {
+        self.min_twist.clone()
+    }
+
+    /// The maximum rotation around the twist axis.
+    pub fn max_twist(&self) -> N {
+        self.max_twist.clone()
+    }
+
+    pub fn set_twist_limits(&mut self, min_twist: N, max_twist: N) {
+        assert!(min_twist <= max_twist, "The minimum twist must not exceed the maximum twist.");
+
+        if min_twist != self.min_twist || max_twist != self.max_twist {
+            self.up_to_date = false;
+            self.min_twist  = min_twist;
+            self.max_twist  = max_twist
+        }
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
+    pub fn set_local1(&mut self, local1: M) {
+        if local1 != self.anchor1.position {
+            self.up_to_date = false;
+            self.anchor1.position = local1
+        }
+    }
+
+    pub fn set_local2(&mut self, local2: M) {
+        if local2 != self.anchor2.position {
+            self.up_to_date = false;
+            self.anchor2.position = local2
+        }
+    }
+
+    pub fn anchor1_pos(&self) -> M {
+        match self.anchor1.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref() * self.anchor1.position,
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref() * self.anchor1.position
+                }
+            },
+            None => self.anchor1.position.clone()
+        }
+    }
+
+    pub fn anchor2_pos(&self) -> M {
+        match self.anchor2.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref() * self.anchor2.position,
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref() * self.anchor2.position
+                }
+            },
+            None => self.anchor2.position.clone()
+        }
+    }
+}
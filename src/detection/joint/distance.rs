@@ -0,0 +1,170 @@
+use std::any::Any;
+use nalgebra::na::Transform;
+use nalgebra::na;
+use detection::joint::anchor::Anchor;
+use resolution::constraint::softness::Softness;
+use object::{RB, SB, MB};
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// A joint keeping the distance between two anchors between a minimum and a maximum length.
+///
+/// With a minimum length of zero, this is a rope: the anchors can move freely as long as they
+/// are closer than the maximum length. With equal minimum and maximum lengths, this is a rigid
+/// rod.
+pub struct Distance<N, LV, AV, M, II> {
+    priv up_to_date: bool,
+    priv anchor1:    Anchor<N, LV, AV, M, II, LV>,
+    priv anchor2:    Anchor<N, LV, AV, M, II, LV>,
+    priv min_length: N,
+    priv max_length: N,
+    priv softness:   Option<Softness<N>>,
+    priv user_data:  Option<@Any>
+}
+
+impl<N: Clone, LV: Clone, AV, M, II> Clone for Distance<N, LV, AV, M, II> {
+    fn clone(&self) -> Distance<N, LV, AV, M, II> {
+        Distance {
+            up_to_date: false,
+            anchor1:    self.anchor1.clone(),
+            anchor2:    self.anchor2.clone(),
+            min_length: self.min_length.clone(),
+            max_length: self.max_length.clone(),
+            softness:   self.softness.clone(),
+            user_data:  self.user_data
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Distance<N, LV, AV, M, II> {
+    pub fn new(anchor1:    Anchor<N, LV, AV, M, II, LV>,
+               anchor2:    Anchor<N, LV, AV, M, II, LV>,
+               min_length: N,
+               max_length: N)
+               -> Distance<N, LV, AV, M, II> {
+        assert!(min_length >= na::zero() && min_length <= max_length,
+                "The lengths of a distance joint must satisfy 0 <= min_length <= max_length.");
+
+        Distance {
+            up_to_date: false,
+            anchor1:    anchor1,
+            anchor2:    anchor2,
+            min_length: min_length,
+            max_length: max_length,
+            softness:   None,
+            user_data:  None
+        }
+    }
+
+    /// Creates a rope: a distance joint with a minimum length of zero.
+    pub fn new_rope(anchor1: Anchor<N, LV, AV, M, II, LV>,
+                    anchor2: Anchor<N, LV, AV, M, II, LV>,
+                    length:  N)
+                    -> Distance<N, LV, AV, M, II> {
+        Distance::new(anchor1, anchor2, na::zero(), length)
+    }
+
+    pub fn up_to_date(&self) -> bool {
+        self.up_to_date
+    }
+
+    pub fn update(&mut self) {
+        self.up_to_date = true
+    }
+
+    pub fn anchor1<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
+        &self.anchor1
+    }
+
+    pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
+        &self.anchor2
+    }
+
+    /// The minimum distance between the two anchors.
+    pub fn min_length(&self) -> N {
+        self.min_length.clone()
+    }
+
+    /// The maximum distance between the two anchors.
+    pub fn max_length(&self) -> N {
+        self.max_length.clone()
+    }
+
+    /// Sets the minimum and maximum distances between the two anchors.
+    pub fn set_lengths(&mut self, min_length: N, max_length: N) {
+        assert!(min_length >= na::zero() && min_length <= max_length,
+                "The lengths of a distance joint must satisfy 0 <= min_length <= max_length.");
+
+        if min_length != self.min_length || max_length != self.max_length {
+            self.up_to_date = false;
+            self.min_length = min_length;
+            self.max_length = max_length;
+        }
+    }
+
+    /// The softness of this joint, `None` if it is rigid.
+    pub fn softness<'r>(&'r self) -> &'r Option<Softness<N>> {
+        &self.softness
+    }
+
+    /// Makes this joint behave like a damped spring, or rigid again with `None`.
+    pub fn set_softness(&mut self, softness: Option<Softness<N>>) {
+        self.up_to_date = false;
+        self.softness   = softness
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
+    pub fn set_local1(&mut self, local1: LV) {
+        if local1 != self.anchor1.position {
+            self.up_to_date = false;
+            self.anchor1.position = local1
+        }
+    }
+
+    pub fn set_local2(&mut self, local2: LV) {
+        if local2 != self.anchor2.position {
+            self.up_to_date = false;
+            self.anchor2.position = local2
+        }
+    }
+
+    pub fn anchor1_pos(&self) -> LV {
+        match self.anchor1.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref().transform(&self.anchor1.position),
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref().transform(&self.anchor1.position)
+                }
+            },
+            None => self.anchor1.position.clone()
+        }
+    }
+
+    pub fn anchor2_pos(&self) -> LV {
+        match self.anchor2.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref().transform(&self.anchor2.position),
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref().transform(&self.anchor2.position)
+                }
+            },
+            None => self.anchor2.position.clone()
+        }
+    }
+}
@@ -1,5 +1,7 @@
+use std::any::Any;
 use detection::joint::anchor::Anchor;
-use object::{RB, SB};
+use resolution::constraint::softness::Softness;
+use object::{RB, SB, MB};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
@@ -7,6 +9,20 @@ pub struct Fixed<N, LV, AV, M, II> {
     priv up_to_date: bool,
     priv anchor1:    Anchor<N, LV, AV, M, II, M>,
     priv anchor2:    Anchor<N, LV, AV, M, II, M>,
+    priv softness:   Option<Softness<N>>,
+    priv user_data:  Option<@Any>
+}
+
+impl<N: Clone, LV, AV, M: Clone, II> Clone for Fixed<N, LV, AV, M, II> {
+    fn clone(&self) -> Fixed<N, LV, AV, M, II> {
+        Fixed {
+            up_to_date: false,
+            anchor1:    self.anchor1.clone(),
+            anchor2:    self.anchor2.clone(),
+            softness:   self.softness.clone(),
+            user_data:  self.user_data
+        }
+    }
 }
 
 impl<N:  NPhysicsScalar,
@@ -21,7 +37,9 @@ Fixed<N, LV, AV, M, II> {
         Fixed {
             up_to_date: false,
             anchor1:    anchor1,
-            anchor2:    anchor2
+            anchor2:    anchor2,
+            softness:   None,
+            user_data:  None
         }
     }
 
@@ -41,6 +59,27 @@ Fixed<N, LV, AV, M, II> {
         &self.anchor2
     }
 
+    /// The softness of this joint, `None` if it is rigid.
+    pub fn softness<'r>(&'r self) -> &'r Option<Softness<N>> {
+        &self.softness
+    }
+
+    /// Makes this joint behave like a damped spring, or rigid again with `None`.
+    pub fn set_softness(&mut self, softness: Option<Softness<N>>) {
+        self.up_to_date = false;
+        self.softness   = softness
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
     pub fn set_local1(&mut self, local1: M) {
         if local1 != self.anchor1.position {
             self.up_to_date = false;
@@ -60,7 +99,8 @@ Fixed<N, LV, AV, M, II> {
             Some(b) => {
                 match *b {
                     RB(ref rb) => rb.transform_ref() * self.anchor1.position,
-                    SB(_)      => fail!("Not yet implemented.")
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref() * self.anchor1.position
                 }
             },
             None => self.anchor1.position.clone()
@@ -72,7 +112,8 @@ Fixed<N, LV, AV, M, II> {
             Some(b) => {
                 match *b {
                     RB(ref rb) => rb.transform_ref() * self.anchor2.position,
-                    SB(_)      => fail!("Not yet implemented.")
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref() * self.anchor2.position
                 }
             },
             None => self.anchor2.position.clone()
@@ -0,0 +1,135 @@
+use std::any::Any;
+use detection::joint::anchor::Anchor;
+use resolution::constraint::cone_twist_equation;
+use object::{RB, SB, MB};
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// A constraint coupling the rotations of two bodies.
+///
+/// The position of each anchor is a unit rotation axis expressed in the local frame of its body.
+/// The angular velocity of the second body around its axis is kept equal to `ratio` times the
+/// angular velocity of the first body around its axis. Only the velocities are coupled: the
+/// relative angle of the bodies may drift over time.
+pub struct Gear<N, LV, AV, M, II> {
+    priv up_to_date: bool,
+    priv anchor1:    Anchor<N, LV, AV, M, II, AV>,
+    priv anchor2:    Anchor<N, LV, AV, M, II, AV>,
+    priv ratio:      N,
+    priv user_data:  Option<@Any>
+}
+
+impl<N: Clone, LV, AV: Clone, M, II> Clone for Gear<N, LV, AV, M, II> {
+    fn clone(&self) -> Gear<N, LV, AV, M, II> {
+        Gear {
+            up_to_date: false,
+            anchor1:    self.anchor1.clone(),
+            anchor2:    self.anchor2.clone(),
+            ratio:      self.ratio.clone(),
+            user_data:  self.user_data
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Gear<N, LV, AV, M, II> {
+    pub fn new(anchor1: Anchor<N, LV, AV, M, II, AV>,
+               anchor2: Anchor<N, LV, AV, M, II, AV>,
+               ratio:   N)
+               -> Gear<N, LV, AV, M, II> {
+        Gear {
+            up_to_date: false,
+            anchor1:    anchor1,
+            anchor2:    anchor2,
+            ratio:      ratio,
+            user_data:  None
+        }
+    }
+
+    pub fn up_to_date(&self) -> bool {
+        self.up_to_date
+    }
+
+    pub fn update(&mut self) {
+        self.up_to_date = true
+    }
+
+    pub fn anchor1<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, AV> {
+        &self.anchor1
+    }
+
+    pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, AV> {
+        &self.anchor2
+    }
+
+    /// The ratio between the angular velocity of the second body and the one of the first body.
+    pub fn ratio(&self) -> N {
+        self.ratio.clone()
+    }
+
+    pub fn set_ratio(&mut self, ratio: N) {
+        if ratio != self.ratio {
+            self.up_to_date = false;
+            self.ratio      = ratio
+        }
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
+    pub fn set_local1(&mut self, local1: AV) {
+        if local1 != self.anchor1.position {
+            self.up_to_date = false;
+            self.anchor1.position = local1
+        }
+    }
+
+    pub fn set_local2(&mut self, local2: AV) {
+        if local2 != self.anchor2.position {
+            self.up_to_date = false;
+            self.anchor2.position = local2
+        }
+    }
+
+    /// The world-space rotation axis of the first body.
+    pub fn axis1(&self) -> AV {
+        world_axis(&self.anchor1)
+    }
+
+    /// The world-space rotation axis of the second body.
+    pub fn axis2(&self) -> AV {
+        world_axis(&self.anchor2)
+    }
+}
+
+fn world_axis<N:  Clone + NPhysicsScalar,
+              LV: Clone + NPhysicsDirection<N, AV>,
+              AV: Clone + NPhysicsOrientation<N>,
+              M:  Clone + NPhysicsTransform<LV, AV>,
+              II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+              anchor: &Anchor<N, LV, AV, M, II, AV>)
+              -> AV {
+    match anchor.body {
+        Some(b) => {
+            match *b {
+                RB(ref rb) => cone_twist_equation::local_to_world_axis(rb.transform_ref(),
+                                                                       &anchor.position),
+                SB(_)      => fail!("Not yet implemented."),
+                MB(ref mb) => cone_twist_equation::local_to_world_axis(mb.rigid_body().transform_ref(),
+                                                                       &anchor.position)
+            }
+        },
+        None => anchor.position.clone()
+    }
+}
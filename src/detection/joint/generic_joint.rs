@@ -0,0 +1,225 @@
+use std::any::Any;
+use std::vec;
+use nalgebra::na;
+use detection::joint::anchor::Anchor;
+use object::{RB, SB, MB};
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// The allowed relative motion along (or around) one axis of a generic joint.
+#[deriving(Eq, ToStr, Clone)]
+pub enum AxisMotion<N> {
+    /// No relative motion.
+    Locked,
+    /// Unconstrained relative motion.
+    Free,
+    /// `Limited(min, max)`: the relative position (or angle) is kept between `min` and `max`.
+    Limited(N, N)
+}
+
+/// A motor driving the relative velocity along (or around) one axis of a generic joint.
+#[deriving(Eq, ToStr, Clone)]
+pub struct Motor<N> {
+    /// The relative velocity the motor tries to reach.
+    target_vel: N,
+    /// The maximum force (or torque) the motor can apply.
+    max_force:  N
+}
+
+impl<N> Motor<N> {
+    pub fn new(target_vel: N, max_force: N) -> Motor<N> {
+        Motor {
+            target_vel: target_vel,
+            max_force:  max_force
+        }
+    }
+}
+
+/// A joint with configurable motion along each translation and rotation axis.
+///
+/// The axes are those of the local frame of the first anchor. The relative position is measured
+/// between the origins of the two anchors frames, and the relative angles are the components of
+/// the rotation vector of the second anchor frame relative to the first one. Each axis can also
+/// have a motor.
+///
+/// A generic joint with every axis locked is equivalent to a `Fixed` joint, and one with only its
+/// translation axes locked to a `BallInSocket` joint.
+pub struct GenericJoint<N, LV, AV, M, II> {
+    priv up_to_date:     bool,
+    priv anchor1:        Anchor<N, LV, AV, M, II, M>,
+    priv anchor2:        Anchor<N, LV, AV, M, II, M>,
+    priv linear:         ~[AxisMotion<N>],
+    priv angular:        ~[AxisMotion<N>],
+    priv linear_motors:  ~[Option<Motor<N>>],
+    priv angular_motors: ~[Option<Motor<N>>],
+    priv user_data:      Option<@Any>
+}
+
+impl<N: Clone, LV, AV, M: Clone, II> Clone for GenericJoint<N, LV, AV, M, II> {
+    fn clone(&self) -> GenericJoint<N, LV, AV, M, II> {
+        GenericJoint {
+            up_to_date:     false,
+            anchor1:        self.anchor1.clone(),
+            anchor2:        self.anchor2.clone(),
+            linear:         self.linear.clone(),
+            angular:        self.angular.clone(),
+            linear_motors:  self.linear_motors.clone(),
+            angular_motors: self.angular_motors.clone(),
+            user_data:      self.user_data
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+GenericJoint<N, LV, AV, M, II> {
+    /// Creates a generic joint with every axis locked.
+    pub fn new(anchor1: Anchor<N, LV, AV, M, II, M>,
+               anchor2: Anchor<N, LV, AV, M, II, M>)
+               -> GenericJoint<N, LV, AV, M, II> {
+        GenericJoint {
+            up_to_date:     false,
+            anchor1:        anchor1,
+            anchor2:        anchor2,
+            linear:         vec::from_elem(na::dim::<LV>(), Locked),
+            angular:        vec::from_elem(na::dim::<AV>(), Locked),
+            linear_motors:  vec::from_elem(na::dim::<LV>(), None),
+            angular_motors: vec::from_elem(na::dim::<AV>(), None),
+            user_data:      None
+        }
+    }
+
+    /// Creates a generic joint with its translation axes locked and its rotation axes free.
+    pub fn new_ball_in_socket(anchor1: Anchor<N, LV, AV, M, II, M>,
+                              anchor2: Anchor<N, LV, AV, M, II, M>)
+                              -> GenericJoint<N, LV, AV, M, II> {
+        let mut res = GenericJoint::new(anchor1, anchor2);
+
+        for a in res.angular.mut_iter() {
+            *a = Free
+        }
+
+        res
+    }
+
+    pub fn up_to_date(&self) -> bool {
+        self.up_to_date
+    }
+
+    pub fn update(&mut self) {
+        self.up_to_date = true
+    }
+
+    pub fn anchor1<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, M> {
+        &self.anchor1
+    }
+
+    pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, M> {
+        &self.anchor2
+    }
+
+    /// The allowed motion along the `i`-th translation axis.
+    pub fn linear_axis<'r>(&'r self, i: uint) -> &'r AxisMotion<N> {
+        &self.linear[i]
+    }
+
+    pub fn set_linear_axis(&mut self, i: uint, motion: AxisMotion<N>) {
+        check_motion(&motion);
+
+        self.up_to_date = false;
+        self.linear[i]  = motion
+    }
+
+    /// The allowed motion around the `i`-th rotation axis.
+    pub fn angular_axis<'r>(&'r self, i: uint) -> &'r AxisMotion<N> {
+        &self.angular[i]
+    }
+
+    pub fn set_angular_axis(&mut self, i: uint, motion: AxisMotion<N>) {
+        check_motion(&motion);
+
+        self.up_to_date  = false;
+        self.angular[i]  = motion
+    }
+
+    /// The motor driving the `i`-th translation axis.
+    pub fn linear_motor<'r>(&'r self, i: uint) -> &'r Option<Motor<N>> {
+        &self.linear_motors[i]
+    }
+
+    pub fn set_linear_motor(&mut self, i: uint, motor: Option<Motor<N>>) {
+        self.up_to_date       = false;
+        self.linear_motors[i] = motor
+    }
+
+    /// The motor driving the `i`-th rotation axis.
+    pub fn angular_motor<'r>(&'r self, i: uint) -> &'r Option<Motor<N>> {
+        &self.angular_motors[i]
+    }
+
+    pub fn set_angular_motor(&mut self, i: uint, motor: Option<Motor<N>>) {
+        self.up_to_date        = false;
+        self.angular_motors[i] = motor
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
+    pub fn set_local1(&mut self, local1: M) {
+        if local1 != self.anchor1.position {
+            self.up_to_date = false;
+            self.anchor1.position = local1
+        }
+    }
+
+    pub fn set_local2(&mut self, local2: M) {
+        if local2 != self.anchor2.position {
+            self.up_to_date = false;
+            self.anchor2.position = local2
+        }
+    }
+
+    pub fn anchor1_pos(&self) -> M {
+        match self.anchor1.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref() * self.anchor1.position,
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref() * self.anchor1.position
+                }
+            },
+            None => self.anchor1.position.clone()
+        }
+    }
+
+    pub fn anchor2_pos(&self) -> M {
+        match self.anchor2.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref() * self.anchor2.position,
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref() * self.anchor2.position
+                }
+            },
+            None => self.anchor2.position.clone()
+        }
+    }
+}
+
+fn check_motion<N: NPhysicsScalar>(motion: &AxisMotion<N>) {
+    match *motion {
+        Limited(ref min, ref max) =>
+            assert!(*min <= *max, "The lower limit must not exceed the upper limit."),
+        _                         => { }
+    }
+}
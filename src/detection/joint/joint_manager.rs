@@ -4,7 +4,12 @@ use ncollide::util::hash::UintTWHash;
 use detection::detector::Detector;
 use detection::joint::ball_in_socket::BallInSocket;
 use detection::joint::fixed::Fixed;
-use detection::constraint::{Constraint, BallInSocket, Fixed, RBRB};
+use detection::joint::distance::Distance;
+use detection::joint::cone_twist::ConeTwist;
+use detection::joint::generic_joint::GenericJoint;
+use detection::joint::gear::Gear;
+use detection::joint::pulley::Pulley;
+use detection::constraint::{Constraint, BallInSocket, Fixed, Distance, ConeTwist, GenericJoint, Gear, Pulley, RBRB};
 use object::Body;
 use signal::signal::SignalEmiter;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
@@ -47,6 +52,46 @@ impl<N: 'static, LV: 'static, AV: 'static, M: 'static, II: 'static> JointManager
     pub fn remove_fixed(&mut self, joint: @mut Fixed<N, LV, AV, M, II>) {
         self.joints.remove(&(ptr::to_mut_unsafe_ptr(joint) as uint));
     }
+
+    pub fn add_distance(&mut self, joint: @mut Distance<N, LV, AV, M, II>) {
+        self.joints.insert(ptr::to_mut_unsafe_ptr(joint) as uint, Distance(joint));
+    }
+
+    pub fn remove_distance(&mut self, joint: @mut Distance<N, LV, AV, M, II>) {
+        self.joints.remove(&(ptr::to_mut_unsafe_ptr(joint) as uint));
+    }
+
+    pub fn add_cone_twist(&mut self, joint: @mut ConeTwist<N, LV, AV, M, II>) {
+        self.joints.insert(ptr::to_mut_unsafe_ptr(joint) as uint, ConeTwist(joint));
+    }
+
+    pub fn remove_cone_twist(&mut self, joint: @mut ConeTwist<N, LV, AV, M, II>) {
+        self.joints.remove(&(ptr::to_mut_unsafe_ptr(joint) as uint));
+    }
+
+    pub fn add_generic_joint(&mut self, joint: @mut GenericJoint<N, LV, AV, M, II>) {
+        self.joints.insert(ptr::to_mut_unsafe_ptr(joint) as uint, GenericJoint(joint));
+    }
+
+    pub fn remove_generic_joint(&mut self, joint: @mut GenericJoint<N, LV, AV, M, II>) {
+        self.joints.remove(&(ptr::to_mut_unsafe_ptr(joint) as uint));
+    }
+
+    pub fn add_gear(&mut self, joint: @mut Gear<N, LV, AV, M, II>) {
+        self.joints.insert(ptr::to_mut_unsafe_ptr(joint) as uint, Gear(joint));
+    }
+
+    pub fn remove_gear(&mut self, joint: @mut Gear<N, LV, AV, M, II>) {
+        self.joints.remove(&(ptr::to_mut_unsafe_ptr(joint) as uint));
+    }
+
+    pub fn add_pulley(&mut self, joint: @mut Pulley<N, LV, AV, M, II>) {
+        self.joints.insert(ptr::to_mut_unsafe_ptr(joint) as uint, Pulley(joint));
+    }
+
+    pub fn remove_pulley(&mut self, joint: @mut Pulley<N, LV, AV, M, II>) {
+        self.joints.remove(&(ptr::to_mut_unsafe_ptr(joint) as uint));
+    }
 }
 
 impl<N:  NPhysicsScalar,
@@ -66,15 +111,13 @@ Detector<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>> for JointManag
         // would avoid traversing the joint list to find that a body does not have any joint.
         for elts in self.joints.elements().iter() {
             match elts.value {
-                BallInSocket(bis) => {
-                    bis.anchor2().body.map(|b| keys_to_remove.push(ptr::to_mut_unsafe_ptr(b) as uint));
-                    bis.anchor1().body.map(|b| keys_to_remove.push(ptr::to_mut_unsafe_ptr(b) as uint));
-                },
-                Fixed(f) => {
-                    f.anchor2().body.map(|b| keys_to_remove.push(ptr::to_mut_unsafe_ptr(b) as uint));
-                    f.anchor1().body.map(|b| keys_to_remove.push(ptr::to_mut_unsafe_ptr(b) as uint));
+                RBRB(_, _, _) => fail!("Internal error: a contact RBRB should not be here."),
+                _             => {
+                    let (b1, b2) = elts.value.anchors();
+
+                    b2.map(|b| keys_to_remove.push(ptr::to_mut_unsafe_ptr(b) as uint));
+                    b1.map(|b| keys_to_remove.push(ptr::to_mut_unsafe_ptr(b) as uint));
                 }
-                RBRB(_, _, _) => fail!("Internal error: a contact RBRB should not be here.")
             }
         }
 
@@ -88,34 +131,29 @@ Detector<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>> for JointManag
 
     fn interferences(&mut self, constraint: &mut ~[Constraint<N, LV, AV, M, II>]) {
         for joint in self.joints.elements().iter() {
-            match joint.value {
-                BallInSocket(bis) =>
-                    if !bis.up_to_date() {
-                        // the joint has been invalidated by the user: wake up the attached bodies
-                        bis.update();
-                        match bis.anchor1().body {
-                            Some(b) => self.events.request_body_activation(b),
-                            None    => { }
-                        }
-                        match bis.anchor2().body {
-                            Some(b) => self.events.request_body_activation(b),
-                            None    => { }
-                        }
-                },
-                Fixed(bis) => // FIXME: code duplication from BallInSocket
-                    if !bis.up_to_date() {
-                        // the joint has been invalidated by the user: wake up the attached bodies
-                        bis.update();
-                        match bis.anchor1().body {
-                            Some(b) => self.events.request_body_activation(b),
-                            None    => { }
-                        }
-                        match bis.anchor2().body {
-                            Some(b) => self.events.request_body_activation(b),
-                            None    => { }
-                        }
-                },
-                RBRB(_, _ , _) => { }
+            let invalidated = match joint.value {
+                BallInSocket(bis) => if bis.up_to_date() { false } else { bis.update(); true },
+                Fixed(f)          => if f.up_to_date() { false } else { f.update(); true },
+                Distance(d)       => if d.up_to_date() { false } else { d.update(); true },
+                ConeTwist(ct)     => if ct.up_to_date() { false } else { ct.update(); true },
+                GenericJoint(g)   => if g.up_to_date() { false } else { g.update(); true },
+                Gear(gr)          => if gr.up_to_date() { false } else { gr.update(); true },
+                Pulley(p)         => if p.up_to_date() { false } else { p.update(); true },
+                RBRB(_, _ , _)    => false
+            };
+
+            if invalidated {
+                // the joint has been invalidated by the user: wake up the attached bodies
+                let (b1, b2) = joint.value.anchors();
+
+                match b1 {
+                    Some(b) => self.events.request_body_activation(b),
+                    None    => { }
+                }
+                match b2 {
+                    Some(b) => self.events.request_body_activation(b),
+                    None    => { }
+                }
             }
 
             constraint.push(joint.value.clone())
@@ -0,0 +1,178 @@
+use std::any::Any;
+use nalgebra::na::Transform;
+use nalgebra::na;
+use detection::joint::anchor::Anchor;
+use object::{RB, SB, MB};
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// A pulley: two anchors hanging from a rope running over two fixed points.
+///
+/// The rope goes from the first anchor to `ground1`, then from `ground2` to the second anchor.
+/// The sum of the first rope length and of `ratio` times the second rope length never exceeds
+/// `length`: while the rope is taut, pulling one anchor up by one unit lowers the other one by
+/// `1 / ratio`, but the rope goes slack if the anchors are pushed toward the ground points.
+pub struct Pulley<N, LV, AV, M, II> {
+    priv up_to_date: bool,
+    priv anchor1:    Anchor<N, LV, AV, M, II, LV>,
+    priv anchor2:    Anchor<N, LV, AV, M, II, LV>,
+    priv ground1:    LV,
+    priv ground2:    LV,
+    priv ratio:      N,
+    priv length:     N,
+    priv user_data:  Option<@Any>
+}
+
+impl<N: Clone, LV: Clone, AV, M, II> Clone for Pulley<N, LV, AV, M, II> {
+    fn clone(&self) -> Pulley<N, LV, AV, M, II> {
+        Pulley {
+            up_to_date: false,
+            anchor1:    self.anchor1.clone(),
+            anchor2:    self.anchor2.clone(),
+            ground1:    self.ground1.clone(),
+            ground2:    self.ground2.clone(),
+            ratio:      self.ratio.clone(),
+            length:     self.length.clone(),
+            user_data:  self.user_data
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Pulley<N, LV, AV, M, II> {
+    pub fn new(anchor1: Anchor<N, LV, AV, M, II, LV>,
+               anchor2: Anchor<N, LV, AV, M, II, LV>,
+               ground1: LV,
+               ground2: LV,
+               ratio:   N,
+               length:  N)
+               -> Pulley<N, LV, AV, M, II> {
+        assert!(ratio > na::zero(), "The ratio of a pulley must be positive.");
+        assert!(length >= na::zero(), "The length of a pulley must not be negative.");
+
+        Pulley {
+            up_to_date: false,
+            anchor1:    anchor1,
+            anchor2:    anchor2,
+            ground1:    ground1,
+            ground2:    ground2,
+            ratio:      ratio,
+            length:     length,
+            user_data:  None
+        }
+    }
+
+    pub fn up_to_date(&self) -> bool {
+        self.up_to_date
+    }
+
+    pub fn update(&mut self) {
+        self.up_to_date = true
+    }
+
+    pub fn anchor1<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
+        &self.anchor1
+    }
+
+    pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
+        &self.anchor2
+    }
+
+    /// The world-space fixed point the first rope runs over.
+    pub fn ground1<'r>(&'r self) -> &'r LV {
+        &self.ground1
+    }
+
+    /// The world-space fixed point the second rope runs over.
+    pub fn ground2<'r>(&'r self) -> &'r LV {
+        &self.ground2
+    }
+
+    pub fn set_grounds(&mut self, ground1: LV, ground2: LV) {
+        self.up_to_date = false;
+        self.ground1    = ground1;
+        self.ground2    = ground2
+    }
+
+    /// The weight of the second rope length.
+    pub fn ratio(&self) -> N {
+        self.ratio.clone()
+    }
+
+    pub fn set_ratio(&mut self, ratio: N) {
+        assert!(ratio > na::zero(), "The ratio of a pulley must be positive.");
+
+        if ratio != self.ratio {
+            self.up_to_date = false;
+            self.ratio      = ratio
+        }
+    }
+
+    /// The total (weighted) length of the rope.
+    pub fn length(&self) -> N {
+        self.length.clone()
+    }
+
+    pub fn set_length(&mut self, length: N) {
+        assert!(length >= na::zero(), "The length of a pulley must not be negative.");
+
+        if length != self.length {
+            self.up_to_date = false;
+            self.length     = length
+        }
+    }
+
+    /// The data attached to this joint by the user.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this joint.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
+    pub fn set_local1(&mut self, local1: LV) {
+        if local1 != self.anchor1.position {
+            self.up_to_date = false;
+            self.anchor1.position = local1
+        }
+    }
+
+    pub fn set_local2(&mut self, local2: LV) {
+        if local2 != self.anchor2.position {
+            self.up_to_date = false;
+            self.anchor2.position = local2
+        }
+    }
+
+    pub fn anchor1_pos(&self) -> LV {
+        match self.anchor1.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref().transform(&self.anchor1.position),
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref().transform(&self.anchor1.position)
+                }
+            },
+            None => self.anchor1.position.clone()
+        }
+    }
+
+    pub fn anchor2_pos(&self) -> LV {
+        match self.anchor2.body {
+            Some(b) => {
+                match *b {
+                    RB(ref rb) => rb.transform_ref().transform(&self.anchor2.position),
+                    SB(_)      => fail!("Not yet implemented."),
+                    MB(ref mb) => mb.rigid_body().transform_ref().transform(&self.anchor2.position)
+                }
+            },
+            None => self.anchor2.position.clone()
+        }
+    }
+}
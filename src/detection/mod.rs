@@ -1,7 +1,7 @@
-pub use detection::collision::bodies_bodies::BodiesBodies;
-// pub use detection::collision::bodies_bodies_parallel::BodiesBodiesParallel;
+pub use detection::collision::bodies_bodies::{BodiesBodies, RayHit};
+pub use detection::collision::bodies_bodies_parallel::BodiesBodiesParallel;
 pub use BodiesBodiesDispatcher = detection::collision::bodies_bodies::Dispatcher;
-// pub use BodiesBodiesDispatcherParallel = detection::collision::bodies_bodies_parallel::DispatcherParallel;
+pub use BodiesBodiesDispatcherParallel = detection::collision::bodies_bodies_parallel::DispatcherParallel;
 pub use detection::joint::joint_manager::JointManager;
 pub use detection::island_activation_manager::IslandActivationManager;
 
@@ -11,7 +11,7 @@ pub mod detector;
 
 pub mod collision {
     pub mod bodies_bodies;
-    // pub mod bodies_bodies_parallel;
+    pub mod bodies_bodies_parallel;
 }
 
 pub mod joint {
@@ -19,6 +19,11 @@ pub mod joint {
     pub mod anchor;
     pub mod ball_in_socket;
     pub mod fixed;
+    pub mod pulley;
+    pub mod gear;
+    pub mod generic_joint;
+    pub mod cone_twist;
+    pub mod distance;
 }
 
 pub mod island_activation_manager;
@@ -1,7 +1,7 @@
 use std::ptr;
 use ncollide::util::hash_map::HashMap;
 use ncollide::util::hash::UintTWHash;
-use object::{Body, RB, SB};
+use object::{Body, RB, SB, MB};
 use integration::Integrator;
 use signal::signal::{SignalEmiter, BodyActivationSignalHandler};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform, NPhysicsInertia};
@@ -65,6 +65,13 @@ Integrator<N, Body<N, LV, AV, M, II>> for BodyDamping<N, LV, AV, M, II> {
                 },
                 SB(_) => {
                     fail!("Not yet implemented.")
+                },
+                MB(ref mut mb) => {
+                    let rb      = mb.mut_rigid_body();
+                    let new_lin = rb.lin_vel() * self.linear_damping;
+                    rb.set_lin_vel(new_lin);
+                    let new_ang = rb.ang_vel() * self.angular_damping;
+                    rb.set_ang_vel(new_ang);
                 }
             }
         }
@@ -1,7 +1,7 @@
 use std::ptr;
 use ncollide::util::hash_map::HashMap;
 use ncollide::util::hash::UintTWHash;
-use object::{Body, RB, SB};
+use object::{Body, RB, SB, MB};
 use integration::Integrator;
 use signal::signal::{SignalEmiter, BodyActivationSignalHandler};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform, NPhysicsInertia};
@@ -79,7 +79,11 @@ BodyForceGenerator<N, LV, AV, M, II> {
                 rb.set_lin_acc(self.lin_acc.clone());
                 rb.set_ang_acc(self.ang_acc.clone());
             },
-            SB(ref mut sb) => sb.acc = self.lin_acc.clone()
+            SB(ref mut sb) => sb.acc = self.lin_acc.clone(),
+            MB(ref mut mb) => {
+                mb.mut_rigid_body().set_lin_acc(self.lin_acc.clone());
+                mb.mut_rigid_body().set_ang_acc(self.ang_acc.clone());
+            }
         }
     }
 }
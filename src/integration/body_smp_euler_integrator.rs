@@ -2,7 +2,7 @@ use std::ptr;
 use nalgebra::na::Transformation;
 use ncollide::util::hash_map::HashMap;
 use ncollide::util::hash::UintTWHash;
-use object::{RB, SB};
+use object::{RB, SB, MB};
 use object::Body;
 use integration::Integrator;
 use integration::euler;
@@ -71,7 +71,8 @@ Integrator<N, Body<N, LV, AV, M, II>> for BodySmpEulerIntegrator<N, LV, AV, M, I
                         rb.set_ang_vel(av);
                     }
                 },
-                SB(_) => fail!("Not yet implemented.")
+                SB(_) => fail!("Not yet implemented."),
+                MB(_) => { } // moved by its multibody
             }
         }
     }
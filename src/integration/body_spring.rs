@@ -0,0 +1,365 @@
+use std::managed;
+use std::num::Zero;
+use nalgebra::na::Transform;
+use nalgebra::na;
+use object::{Body, RigidBody, RB, SB, MB};
+use object::volumetric::InertiaTensor;
+use integration::Integrator;
+use detection::joint::anchor::Anchor;
+use detection::constraint::Constraint;
+use signal::signal::SignalEmiter;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform, NPhysicsInertia};
+
+/// A spring-damper connecting two anchors.
+///
+/// Unlike joints, the spring is not a constraint: its force is applied to the bodies velocities
+/// before their integration. The force is `stiffness * (length - rest_length)` plus `damping`
+/// times the relative velocity of the anchors along the spring. It is integrated explicitly, so
+/// very stiff springs need small time steps.
+///
+/// The spring must be added to the world as an integrator. It becomes inert if one of its bodies
+/// is removed from the world.
+pub struct BodySpring<N, LV, AV, M, II> {
+    priv events:      @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+    priv up_to_date:  bool,
+    priv enabled:     bool,
+    priv anchor1:     Anchor<N, LV, AV, M, II, LV>,
+    priv anchor2:     Anchor<N, LV, AV, M, II, LV>,
+    priv rest_length: N,
+    priv stiffness:   N,
+    priv damping:     N
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>>
+BodySpring<N, LV, AV, M, II> {
+    pub fn new(events:      @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+               anchor1:     Anchor<N, LV, AV, M, II, LV>,
+               anchor2:     Anchor<N, LV, AV, M, II, LV>,
+               rest_length: N,
+               stiffness:   N,
+               damping:     N)
+               -> @mut BodySpring<N, LV, AV, M, II> {
+        @mut BodySpring {
+            events:      events,
+            up_to_date:  false,
+            enabled:     true,
+            anchor1:     anchor1,
+            anchor2:     anchor2,
+            rest_length: rest_length,
+            stiffness:   stiffness,
+            damping:     damping
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+BodySpring<N, LV, AV, M, II> {
+    pub fn anchor1<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
+        &self.anchor1
+    }
+
+    pub fn anchor2<'r>(&'r self) -> &'r Anchor<N, LV, AV, M, II, LV> {
+        &self.anchor2
+    }
+
+    /// The length of the spring when it applies no force.
+    pub fn rest_length(&self) -> N {
+        self.rest_length.clone()
+    }
+
+    pub fn set_rest_length(&mut self, rest_length: N) {
+        self.up_to_date  = false;
+        self.rest_length = rest_length
+    }
+
+    pub fn stiffness(&self) -> N {
+        self.stiffness.clone()
+    }
+
+    pub fn set_stiffness(&mut self, stiffness: N) {
+        self.up_to_date = false;
+        self.stiffness  = stiffness
+    }
+
+    pub fn damping(&self) -> N {
+        self.damping.clone()
+    }
+
+    pub fn set_damping(&mut self, damping: N) {
+        self.up_to_date = false;
+        self.damping    = damping
+    }
+
+    /// Whether the spring still applies forces, i.e. none of its bodies has been removed from the
+    /// world.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn set_local1(&mut self, local1: LV) {
+        if local1 != self.anchor1.position {
+            self.up_to_date = false;
+            self.anchor1.position = local1
+        }
+    }
+
+    pub fn set_local2(&mut self, local2: LV) {
+        if local2 != self.anchor2.position {
+            self.up_to_date = false;
+            self.anchor2.position = local2
+        }
+    }
+
+    pub fn anchor1_pos(&self) -> LV {
+        anchor_pos(&self.anchor1)
+    }
+
+    pub fn anchor2_pos(&self) -> LV {
+        anchor_pos(&self.anchor2)
+    }
+
+    /// Wakes up the bodies of the spring if it was modified by the user, or if one of them is
+    /// active.
+    fn activate_bodies(&mut self) {
+        let active1 = is_active(&self.anchor1);
+        let active2 = is_active(&self.anchor2);
+
+        if !self.up_to_date || active1 || active2 {
+            self.up_to_date = true;
+
+            if !active1 {
+                match self.anchor1.body {
+                    Some(b) => if b.can_move() { self.events.request_body_activation(b) },
+                    None    => { }
+                }
+            }
+
+            if !active2 {
+                match self.anchor2.body {
+                    Some(b) => if b.can_move() { self.events.request_body_activation(b) },
+                    None    => { }
+                }
+            }
+        }
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Integrator<N, Body<N, LV, AV, M, II>> for BodySpring<N, LV, AV, M, II> {
+    #[inline]
+    fn add(&mut self, _: @mut Body<N, LV, AV, M, II>) {
+    }
+
+    fn remove(&mut self, o: @mut Body<N, LV, AV, M, II>) {
+        let attached = |a: &Anchor<N, LV, AV, M, II, LV>| {
+            match a.body {
+                Some(b) => managed::mut_ptr_eq(b, o),
+                None    => false
+            }
+        };
+
+        if attached(&self.anchor1) || attached(&self.anchor2) {
+            self.enabled = false
+        }
+    }
+
+    fn update(&mut self, dt: N) {
+        if !self.enabled {
+            return
+        }
+
+        self.activate_bodies();
+
+        let global1 = self.anchor1_pos();
+        let global2 = self.anchor2_pos();
+        let delta   = global2 - global1;
+        let length  = na::norm(&delta);
+
+        if length.is_zero() {
+            return
+        }
+
+        let dir = delta / length;
+        let r1  = global1 - self.anchor1.center_of_mass();
+        let r2  = global2 - self.anchor2.center_of_mass();
+
+        let rel_vel = anchor_vel(&self.anchor2, &r2, &dir) - anchor_vel(&self.anchor1, &r1, &dir);
+
+        // positive when the spring pulls the anchors together
+        let force   = self.stiffness * (length - self.rest_length) + self.damping * rel_vel;
+        let impulse = force * dt;
+
+        apply_impulse(&self.anchor1, &r1, &(dir * impulse));
+        apply_impulse(&self.anchor2, &r2, &(dir * -impulse));
+    }
+
+    #[inline]
+    fn priority(&self) -> f64 { 25.0 }
+}
+
+fn anchor_pos<N:  Clone + NPhysicsScalar,
+              LV: Clone + NPhysicsDirection<N, AV>,
+              AV: Clone + NPhysicsOrientation<N>,
+              M:  NPhysicsTransform<LV, AV>,
+              II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+              anchor: &Anchor<N, LV, AV, M, II, LV>)
+              -> LV {
+    match anchor.body {
+        Some(b) => {
+            match *b {
+                RB(ref rb) => rb.transform_ref().transform(&anchor.position),
+                SB(_)      => fail!("Not yet implemented."),
+                MB(ref mb) => mb.rigid_body().transform_ref().transform(&anchor.position)
+            }
+        },
+        None => anchor.position.clone()
+    }
+}
+
+fn is_active<N:  Clone + NPhysicsScalar,
+             LV: Clone + NPhysicsDirection<N, AV>,
+             AV: Clone + NPhysicsOrientation<N>,
+             M:  NPhysicsTransform<LV, AV>,
+             II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+             anchor: &Anchor<N, LV, AV, M, II, LV>)
+             -> bool {
+    match anchor.body {
+        Some(b) => b.is_active(),
+        None    => false
+    }
+}
+
+/// The velocity of an anchor point along `dir`. `r` is the anchor position relative to the body
+/// center of mass.
+fn anchor_vel<N:  Clone + NPhysicsScalar,
+              LV: Clone + NPhysicsDirection<N, AV>,
+              AV: Clone + NPhysicsOrientation<N>,
+              M:  NPhysicsTransform<LV, AV>,
+              II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+              anchor: &Anchor<N, LV, AV, M, II, LV>,
+              r:      &LV,
+              dir:    &LV)
+              -> N {
+    match anchor.body {
+        Some(b) => {
+            match *b {
+                RB(ref rb) => rigid_body_vel(rb, r, dir),
+                SB(_)      => fail!("Not yet implemented."),
+                MB(ref mb) => rigid_body_vel(mb.rigid_body(), r, dir)
+            }
+        },
+        None => na::zero()
+    }
+}
+
+/// Applies an impulse to the body of an anchor, at the anchor position.
+fn apply_impulse<N:  Clone + NPhysicsScalar,
+                 LV: Clone + NPhysicsDirection<N, AV>,
+                 AV: Clone + NPhysicsOrientation<N>,
+                 M:  NPhysicsTransform<LV, AV>,
+                 II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                 anchor:  &Anchor<N, LV, AV, M, II, LV>,
+                 r:       &LV,
+                 impulse: &LV) {
+    match anchor.body {
+        Some(b) => {
+            match *b {
+                RB(ref mut rb) => apply_rigid_body_impulse(rb, r, impulse),
+                SB(_)          => fail!("Not yet implemented."),
+                MB(ref mut mb) => apply_rigid_body_impulse(mb.mut_rigid_body(), r, impulse)
+            }
+        },
+        None => { }
+    }
+}
+
+fn rigid_body_vel<N:  Clone + NPhysicsScalar,
+                  LV: Clone + NPhysicsDirection<N, AV>,
+                  AV: Clone + NPhysicsOrientation<N>,
+                  M:  NPhysicsTransform<LV, AV>,
+                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                  rb:  &RigidBody<N, LV, AV, M, II>,
+                  r:   &LV,
+                  dir: &LV)
+                  -> N {
+    na::dot(dir, &rb.lin_vel()) + na::dot(&rb.ang_vel(), &na::cross(r, dir))
+}
+
+fn apply_rigid_body_impulse<N:  Clone + NPhysicsScalar,
+                            LV: Clone + NPhysicsDirection<N, AV>,
+                            AV: Clone + NPhysicsOrientation<N>,
+                            M:  NPhysicsTransform<LV, AV>,
+                            II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                            rb:      &mut RigidBody<N, LV, AV, M, II>,
+                            r:       &LV,
+                            impulse: &LV) {
+    if rb.can_move() && rb.is_active() {
+        let lin_vel = rb.lin_vel() + *impulse * rb.inv_mass();
+        let ang_vel = rb.ang_vel() + rb.inv_inertia().apply(&na::cross(r, impulse));
+
+        rb.set_lin_vel(lin_vel);
+        rb.set_ang_vel(ang_vel);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::Vec3;
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::Body3d;
+    use object::{RigidBody, Dynamic, RB};
+    use integration::Integrator;
+    use detection::joint::anchor::Anchor;
+    use signal::signal::SignalEmiter;
+    use super::BodySpring;
+
+    #[test]
+    fn test_spring_pulls_toward_its_rest_length() {
+        let ball: @mut Body3d<f64> =
+            @mut RB(RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6));
+        let spring = BodySpring::new(@mut SignalEmiter::new(),
+                                     Anchor::new(Some(ball), na::zero()),
+                                     Anchor::new(None, Vec3::new(3.0f64, 0.0, 0.0)),
+                                     1.0,
+                                     10.0,
+                                     0.0);
+
+        spring.update(0.1);
+
+        // the force is 10 * (3 - 1), applied during 0.1
+        let rb = ball.to_rigid_body_or_fail();
+
+        assert!(na::norm(&(rb.lin_vel() - Vec3::new(2.0 * rb.inv_mass(), 0.0, 0.0))) < 1.0e-10);
+    }
+
+    #[test]
+    fn test_spring_is_disabled_by_the_removal_of_its_bodies() {
+        let ball: @mut Body3d<f64> =
+            @mut RB(RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6));
+        let spring = BodySpring::new(@mut SignalEmiter::new(),
+                                     Anchor::new(Some(ball), na::zero()),
+                                     Anchor::new(None, Vec3::new(3.0f64, 0.0, 0.0)),
+                                     1.0,
+                                     10.0,
+                                     1.0);
+
+        spring.remove(ball);
+        spring.update(0.1);
+
+        assert!(!spring.is_enabled());
+        assert!(na::norm(&ball.to_rigid_body_or_fail().lin_vel()) == 0.0);
+    }
+}
@@ -3,6 +3,7 @@ pub use integration::body_exp_euler_integrator::BodyExpEulerIntegrator;
 pub use integration::body_smp_euler_integrator::BodySmpEulerIntegrator;
 pub use integration::body_force_generator::BodyForceGenerator;
 pub use integration::body_damping::BodyDamping;
+pub use integration::body_spring::BodySpring;
 pub use integration::swept_ball_motion_clamping::SweptBallMotionClamping;
 
 pub mod integrator;
@@ -10,5 +11,6 @@ pub mod body_exp_euler_integrator;
 pub mod body_smp_euler_integrator;
 pub mod body_force_generator;
 pub mod body_damping;
+pub mod body_spring;
 pub mod swept_ball_motion_clamping;
 pub mod euler;
@@ -9,7 +9,7 @@ use ncollide::geom::{Geom, Ball};
 use ncollide::broad::{RayCastBroadPhase, BoundingVolumeBroadPhase};
 use ncollide::narrow::toi;
 use integration::Integrator;
-use object::{Body, RB, SB};
+use object::{Body, RB, SB, MB};
 use signal::signal::{SignalEmiter, BodyActivationSignalHandler};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform, NPhysicsInertia};
 
@@ -89,6 +89,7 @@ SweptBallMotionClamping<N, LV, AV, M, II, BF> {
                         rb.transform_ref().translation()));
             },
             SB(_) => fail!("Soft bodies ccd is not yet implemented."),
+            MB(_) => fail!("Multibody links ccd is not yet implemented.")
         }
     }
 
@@ -101,7 +102,7 @@ SweptBallMotionClamping<N, LV, AV, M, II, BF> {
 
                 match *o {
                     RB(ref rb) => ccdo.last_pos = rb.translation(),
-                    SB(_)   => fail!("Not yet implemented.")
+                    _       => fail!("Not yet implemented.")
                 }
 
                 self.objects.insert(key, ccdo);
@@ -187,7 +188,8 @@ for SweptBallMotionClamping<N, LV, AV, M, II, BF> {
                         for b in self.interferences.iter() {
                             if !managed::mut_ptr_eq(*b, o.value.body) {
                                 match **b {
-                                    RB(ref rb) => {
+                                    RB(_) | MB(_) => {
+                                        let rb  = b.to_rigid_body_or_fail();
                                         let toi =
                                             toi::geom_geom(
                                                 &old_transform,
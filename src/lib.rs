@@ -22,10 +22,17 @@ pub mod signal {
     pub mod signal;
 }
 
+pub mod utils {
+    pub mod union_find;
+    pub mod worker_pool;
+}
+
 pub mod integration;
 pub mod detection;
 pub mod resolution;
 
 pub mod world;
 
+pub mod controller;
+
 pub mod object;
@@ -1,12 +1,14 @@
 use std::borrow;
+use std::any::Any;
 use ncollide::bounding_volume::{HasBoundingVolume, AABB};
-use object::{RigidBody, SoftBody};
+use object::{RigidBody, SoftBody, MultibodyLink};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
 pub enum Body<N, LV, AV, M, II> {
     RB(RigidBody<N, LV, AV, M, II>),
-    SB(SoftBody<N, LV>) // FIXME
+    SB(SoftBody<N, LV>), // FIXME
+    MB(MultibodyLink<N, LV, AV, M, II>)
 
 }
 
@@ -19,7 +21,8 @@ Clone for Body<N, LV, AV, M, II> {
     fn clone(&self) -> Body<N, LV, AV, M, II> {
         match *self {
             RB(ref rb) => RB(rb.clone()),
-            SB(ref sb) => SB(sb.clone())
+            SB(ref sb) => SB(sb.clone()),
+            MB(ref mb) => MB(mb.clone())
         }
     }
 }
@@ -34,7 +37,8 @@ Body<N, LV, AV, M, II> {
     pub fn to_rigid_body_or_fail<'r>(&'r self) -> &'r RigidBody<N, LV, AV, M, II> {
         match *self {
             RB(ref rb) => rb,
-            SB(_) => fail!("This is a SoftBody, not a RigidBody.")
+            SB(_) => fail!("This is a SoftBody, not a RigidBody."),
+            MB(ref mb) => mb.rigid_body()
         }
     }
 
@@ -42,7 +46,8 @@ Body<N, LV, AV, M, II> {
     pub fn to_mut_rigid_body_or_fail<'r>(&'r mut self) -> &'r mut RigidBody<N, LV, AV, M, II> {
         match *self {
             RB(ref mut rb) => rb,
-            SB(_) => fail!("This is a SoftBody, not a RigidBody.")
+            SB(_) => fail!("This is a SoftBody, not a RigidBody."),
+            MB(ref mut mb) => mb.mut_rigid_body()
         }
     }
 
@@ -50,7 +55,8 @@ Body<N, LV, AV, M, II> {
     pub fn is_active(&self) -> bool {
         match *self {
             RB(ref rb) => rb.is_active(),
-            SB(ref sb) => sb.is_active()
+            SB(ref sb) => sb.is_active(),
+            MB(ref mb) => mb.rigid_body().is_active()
         }
     }
 
@@ -58,7 +64,8 @@ Body<N, LV, AV, M, II> {
     pub fn can_move(&self) -> bool {
         match *self {
             RB(ref rb) => rb.can_move(),
-            SB(_)   => true
+            SB(_)   => true,
+            MB(_)   => true
         }
     }
 
@@ -66,7 +73,8 @@ Body<N, LV, AV, M, II> {
     pub fn index(&self) -> int {
         match *self {
             RB(ref rb) => rb.index(),
-            SB(ref sb) => sb.index()
+            SB(ref sb) => sb.index(),
+            MB(ref mb) => mb.rigid_body().index()
         }
     }
 
@@ -74,7 +82,8 @@ Body<N, LV, AV, M, II> {
     pub fn set_index(&mut self, index: int) {
         match *self {
             RB(ref mut rb) => rb.set_index(index),
-            SB(ref mut sb)  => sb.set_index(index)
+            SB(ref mut sb)  => sb.set_index(index),
+            MB(ref mut mb)  => mb.mut_rigid_body().set_index(index)
         }
     }
 
@@ -82,7 +91,8 @@ Body<N, LV, AV, M, II> {
     pub fn activate(&mut self) {
         match *self {
             RB(ref mut rb) => rb.activate(),
-            SB(ref mut sb)  => sb.activate()
+            SB(ref mut sb)  => sb.activate(),
+            MB(ref mut mb)  => mb.mut_rigid_body().activate()
         }
     }
 
@@ -90,7 +100,30 @@ Body<N, LV, AV, M, II> {
     pub fn deactivate(&mut self) {
         match *self {
             RB(ref mut rb) => rb.deactivate(),
-            SB(ref mut sb)  => sb.deactivate()
+            SB(ref mut sb)  => sb.deactivate(),
+            MB(ref mut mb)  => mb.mut_rigid_body().deactivate()
+        }
+    }
+
+    /// The data attached to this body by the user, always `None` for a soft body.
+    #[inline]
+    pub fn user_data(&self) -> Option<@Any> {
+        match *self {
+            RB(ref rb) => rb.user_data(),
+            SB(_)      => None,
+            MB(ref mb) => mb.rigid_body().user_data()
+        }
+    }
+
+    /// Attaches arbitrary data to this body.
+    ///
+    /// Fails if this is a soft body: soft bodies cannot hold user data.
+    #[inline]
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        match *self {
+            RB(ref mut rb) => rb.set_user_data(user_data),
+            SB(_)          => fail!("A soft body cannot hold user data."),
+            MB(ref mut mb) => mb.mut_rigid_body().set_user_data(user_data)
         }
     }
 }
@@ -112,7 +145,8 @@ HasBoundingVolume<AABB<N, LV>> for Body<N, LV, AV, M, II> {
     fn bounding_volume(&self) -> AABB<N, LV> {
         match *self {
             RB(ref rb) => rb.bounding_volume(),
-            SB(_)   => fail!("Not yet implemented."),
+            SB(ref sb) => sb.aabb().clone(),
+            MB(ref mb) => mb.rigid_body().bounding_volume()
         }
     }
 }
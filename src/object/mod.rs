@@ -1,8 +1,10 @@
 pub use object::rigid_body::{RigidBody, Static, Dynamic}; // FIXME: rename to StaticBody, DynamicBody ?
 pub use object::soft_body::SoftBody;
-pub use object::body::{Body, RB, SB};
+pub use object::multibody::{Multibody, MultibodyLink, MultibodyJoint, Revolute, Prismatic};
+pub use object::body::{Body, RB, SB, MB};
 
 pub mod rigid_body;
 pub mod soft_body;
 pub mod body;
+pub mod multibody;
 pub mod volumetric;
@@ -0,0 +1,630 @@
+use std::vec;
+use std::ptr;
+use std::managed;
+use nalgebra::na::{Transformation, Translation, Rotation, Rotate, Indexable};
+use nalgebra::na;
+use object::{Body, RigidBody, MB};
+use object::volumetric::InertiaTensor;
+use integration::Integrator;
+use detection::constraint::Constraint;
+use resolution::constraint::cone_twist_equation;
+use resolution::constraint::direct_solver;
+use signal::signal::SignalEmiter;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// The joint attaching a multibody link to its parent.
+///
+/// The axes are expressed in the joint frame.
+#[deriving(Eq, ToStr, Clone)]
+pub enum MultibodyJoint<LV, AV> {
+    /// A rotation around the given unit axis. The joint position is an angle.
+    Revolute(AV),
+    /// A translation along the given unit axis. The joint position is a distance.
+    Prismatic(LV)
+}
+
+/// A link of a multibody.
+///
+/// This is a rigid body whose motion is entirely determined by the joint positions of its
+/// multibody. It collides like any rigid body, but it is not integrated by the body integrators.
+pub struct MultibodyLink<N, LV, AV, M, II> {
+    priv rb:        RigidBody<N, LV, AV, M, II>,
+    priv multibody: uint,
+    priv index:     uint
+}
+
+impl<N:  Send + Freeze + Clone,
+     LV: Send + Freeze + Clone,
+     AV: Clone,
+     M:  Send + Freeze + Clone,
+     II: Clone>
+Clone for MultibodyLink<N, LV, AV, M, II> {
+    fn clone(&self) -> MultibodyLink<N, LV, AV, M, II> {
+        MultibodyLink {
+            rb:        self.rb.clone(),
+            multibody: self.multibody,
+            index:     self.index
+        }
+    }
+}
+
+impl<N, LV, AV, M, II> MultibodyLink<N, LV, AV, M, II> {
+    /// The rigid body moved by this link.
+    #[inline]
+    pub fn rigid_body<'r>(&'r self) -> &'r RigidBody<N, LV, AV, M, II> {
+        &self.rb
+    }
+
+    #[inline]
+    pub fn mut_rigid_body<'r>(&'r mut self) -> &'r mut RigidBody<N, LV, AV, M, II> {
+        &mut self.rb
+    }
+
+    /// An identifier of the multibody this link is part of.
+    #[inline]
+    pub fn multibody(&self) -> uint {
+        self.multibody
+    }
+
+    /// The index of this link on its multibody.
+    #[inline]
+    pub fn index(&self) -> uint {
+        self.index
+    }
+}
+
+/// A tree of rigid bodies connected by joints, simulated in reduced coordinates.
+///
+/// The state of a multibody is the position and velocity of each of its joints, so the joints
+/// cannot be violated: long chains do not stretch like chains of `BallInSocket` joints. The root
+/// links are attached to the `base` frame.
+///
+/// The multibody must be added to the world as an integrator, and each of its links as a body.
+/// At each step, the joint-space mass matrix is built with the composite rigid body algorithm,
+/// and the joint velocities are updated from the joint forces, the Coriolis, centrifugal and
+/// gyroscopic forces, and the momentum the links gained since the last step (from the contacts,
+/// the other integrators, and their accelerations) projected on the joints. The joint positions
+/// are then integrated with the new velocities (semi-implicit Euler), and the links moved
+/// accordingly. Contact impulses are computed as if each link was free, before being projected
+/// on the joints. The links of a multibody never collide with each other.
+///
+/// If the mass matrix is singular (e.g. two joints with the same axis and origin), the joint
+/// velocities are left unchanged by the step.
+pub struct Multibody<N, LV, AV, M, II> {
+    priv events:       @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+    priv enabled:      bool,
+    priv base:         M,
+    priv links:        ~[@mut Body<N, LV, AV, M, II>],
+    priv parents:      ~[Option<uint>],
+    priv joints:       ~[MultibodyJoint<LV, AV>],
+    priv joint_frames: ~[M],
+    priv positions:    ~[N],
+    priv velocities:   ~[N],
+    priv forces:       ~[N]
+}
+
+impl<N:  'static + Clone + NPhysicsScalar,
+     LV: 'static + Clone + NPhysicsDirection<N, AV>,
+     AV: 'static + Clone + NPhysicsOrientation<N>,
+     M:  'static + Clone + NPhysicsTransform<LV, AV>,
+     II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>>
+Multibody<N, LV, AV, M, II> {
+    pub fn new(events: @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+               base:   M)
+               -> @mut Multibody<N, LV, AV, M, II> {
+        @mut Multibody {
+            events:       events,
+            enabled:      true,
+            base:         base,
+            links:        ~[],
+            parents:      ~[],
+            joints:       ~[],
+            joint_frames: ~[],
+            positions:    ~[],
+            velocities:   ~[],
+            forces:       ~[]
+        }
+    }
+
+    /// Adds a link to this multibody, and returns the body which has to be added to the world.
+    ///
+    /// The joint frame is expressed in the local frame of the parent link, or in the base frame if
+    /// `parent` is `None`. The link local frame coincides with the joint frame when the joint
+    /// position is zero. The link mass properties and geometry are those of `rb`, which must be
+    /// dynamic.
+    pub fn add_link(&mut self,
+                    parent:      Option<uint>,
+                    joint_frame: M,
+                    joint:       MultibodyJoint<LV, AV>,
+                    rb:          RigidBody<N, LV, AV, M, II>)
+                    -> @mut Body<N, LV, AV, M, II> {
+        match parent {
+            Some(p) => assert!(p < self.links.len(), "The parent link must already be added."),
+            None    => { }
+        }
+
+        assert!(rb.can_move(), "A multibody link must be dynamic.");
+
+        let link = @mut MB(MultibodyLink {
+            rb:        rb,
+            multibody: ptr::to_mut_unsafe_ptr(self) as uint,
+            index:     self.links.len()
+        });
+
+        self.links.push(link);
+        self.parents.push(parent);
+        self.joints.push(joint);
+        self.joint_frames.push(joint_frame);
+        self.positions.push(na::zero());
+        self.velocities.push(na::zero());
+        self.forces.push(na::zero());
+
+        self.update_links();
+
+        link
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Multibody<N, LV, AV, M, II> {
+    #[inline]
+    pub fn num_links(&self) -> uint {
+        self.links.len()
+    }
+
+    /// The body of the `i`-th link.
+    #[inline]
+    pub fn link(&self, i: uint) -> @mut Body<N, LV, AV, M, II> {
+        self.links[i]
+    }
+
+    /// The parent of the `i`-th link, `None` if it is attached to the base.
+    #[inline]
+    pub fn parent(&self, i: uint) -> Option<uint> {
+        self.parents[i]
+    }
+
+    #[inline]
+    pub fn joint<'r>(&'r self, i: uint) -> &'r MultibodyJoint<LV, AV> {
+        &self.joints[i]
+    }
+
+    /// Whether this multibody is still simulated, i.e. none of its links has been removed from the
+    /// world.
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The frame the root links are attached to.
+    #[inline]
+    pub fn base<'r>(&'r self) -> &'r M {
+        &self.base
+    }
+
+    pub fn set_base(&mut self, base: M) {
+        self.base = base;
+        self.update_links()
+    }
+
+    /// The position (angle or distance) of the joint of the `i`-th link.
+    #[inline]
+    pub fn joint_position(&self, i: uint) -> N {
+        self.positions[i].clone()
+    }
+
+    pub fn set_joint_position(&mut self, i: uint, position: N) {
+        self.positions[i] = position;
+        self.update_links()
+    }
+
+    /// The velocity of the joint of the `i`-th link.
+    #[inline]
+    pub fn joint_velocity(&self, i: uint) -> N {
+        self.velocities[i].clone()
+    }
+
+    pub fn set_joint_velocity(&mut self, i: uint, velocity: N) {
+        self.velocities[i] = velocity;
+        self.update_links()
+    }
+
+    /// The force (or torque) applied by the joint of the `i`-th link.
+    #[inline]
+    pub fn joint_force(&self, i: uint) -> N {
+        self.forces[i].clone()
+    }
+
+    pub fn set_joint_force(&mut self, i: uint, force: N) {
+        self.forces[i] = force
+    }
+
+    /// Wakes up every link if one of them is active. Returns `false` if they are all asleep.
+    fn activate_links(&mut self) -> bool {
+        if !self.links.iter().any(|l| l.is_active()) {
+            return false
+        }
+
+        for l in self.links.iter() {
+            if !l.is_active() {
+                self.events.request_body_activation(*l)
+            }
+        }
+
+        true
+    }
+
+    /// The world-space frame of each joint.
+    fn joint_world_frames(&self) -> ~[M] {
+        let mut res: ~[M] = ~[];
+
+        for i in range(0u, self.links.len()) {
+            let parent_frame = match self.parents[i] {
+                Some(p) => self.links[p].to_rigid_body_or_fail().transform_ref().clone(),
+                None    => self.base.clone()
+            };
+
+            res.push(parent_frame * self.joint_frames[i])
+        }
+
+        res
+    }
+
+    /// Moves the links to match the joint positions, and sets their velocities to match the joint
+    /// velocities.
+    fn update_links(&mut self) {
+        // the parents come first, so their frames are up to date when their children are moved
+        for i in range(0u, self.links.len()) {
+            let parent_frame = match self.parents[i] {
+                Some(p) => self.links[p].to_rigid_body_or_fail().transform_ref().clone(),
+                None    => self.base.clone()
+            };
+
+            let mut motion: M = na::one();
+
+            match self.joints[i] {
+                Revolute(ref axis)  => motion.append_rotation(&(*axis * self.positions[i])),
+                Prismatic(ref axis) => motion.append_translation(&(*axis * self.positions[i]))
+            }
+
+            let frame = parent_frame * self.joint_frames[i] * motion;
+
+            self.links[i].to_mut_rigid_body_or_fail().set_transformation(frame);
+        }
+
+        let jacobians            = self.jacobians();
+        let (lin_vels, ang_vels) = self.link_velocities(jacobians);
+
+        for i in range(0u, self.links.len()) {
+            let rb = self.links[i].to_mut_rigid_body_or_fail();
+
+            rb.set_lin_vel(lin_vels[i].clone());
+            rb.set_ang_vel(ang_vels[i].clone());
+        }
+    }
+
+    /// The linear and angular velocities of each link due to the joint velocities.
+    fn link_velocities(&self, jacobians: &[~[Option<(LV, AV)>]]) -> (~[LV], ~[AV]) {
+        let n = self.links.len();
+        let mut lin_vels = ~[];
+        let mut ang_vels = ~[];
+
+        for i in range(0u, n) {
+            let mut lin_vel: LV = na::zero();
+            let mut ang_vel: AV = na::zero();
+
+            for j in range(0u, n) {
+                match jacobians[i][j] {
+                    Some((ref lin, ref ang)) => {
+                        lin_vel = lin_vel + *lin * self.velocities[j];
+                        ang_vel = ang_vel + *ang * self.velocities[j];
+                    },
+                    None => { }
+                }
+            }
+
+            lin_vels.push(lin_vel);
+            ang_vels.push(ang_vel);
+        }
+
+        (lin_vels, ang_vels)
+    }
+
+    /// The linear and angular accelerations of the center of mass of each link when every joint
+    /// acceleration is zero, i.e. the derivative of the jacobians times the joint velocities.
+    ///
+    /// They are due to the motion of the joint axes and of the lever arms: these are the
+    /// centripetal and Coriolis accelerations.
+    fn bias_accelerations(&self,
+                          jacobians: &[~[Option<(LV, AV)>]],
+                          lin_vels:  &[LV],
+                          ang_vels:  &[AV])
+                          -> ~[(LV, AV)] {
+        let n      = self.links.len();
+        let frames = self.joint_world_frames();
+        let mut res = ~[];
+
+        for i in range(0u, n) {
+            let center      = self.links[i].to_rigid_body_or_fail().center_of_mass().clone();
+            let mut lin: LV = na::zero();
+            let mut ang: AV = na::zero();
+            let mut j       = Some(i);
+
+            loop {
+                match j {
+                    Some(k) => {
+                        let origin = frames[k].translation();
+
+                        // the joint axis and origin move with the parent link
+                        let (parent_ang_vel, origin_vel) = match self.parents[k] {
+                            Some(p) => {
+                                let parent_center = self.links[p].to_rigid_body_or_fail()
+                                                                 .center_of_mass().clone();
+                                let origin_vel    = lin_vels[p] +
+                                    angular_to_linear(&ang_vels[p], &(origin - parent_center));
+
+                                (ang_vels[p].clone(), origin_vel)
+                            },
+                            None => (na::zero(), na::zero())
+                        };
+
+                        match (&self.joints[k], &jacobians[i][k]) {
+                            (&Revolute(_), &Some((_, ref axis))) => {
+                                let axis_vel = angular_cross(&parent_ang_vel, axis);
+                                let arm      = center - origin;
+
+                                lin = lin + (angular_to_linear(&axis_vel, &arm) +
+                                             angular_to_linear(axis, &(lin_vels[i] - origin_vel))) *
+                                            self.velocities[k];
+                                ang = ang + axis_vel * self.velocities[k];
+                            },
+                            (&Prismatic(_), &Some((ref axis, _))) => {
+                                lin = lin + angular_to_linear(&parent_ang_vel, axis) *
+                                            self.velocities[k];
+                            },
+                            (_, &None) => fail!("The jacobian of a link ancestor must be computed.")
+                        }
+
+                        j = self.parents[k]
+                    },
+                    None => break
+                }
+            }
+
+            res.push((lin, ang));
+        }
+
+        res
+    }
+
+    /// The velocities of the center of mass of each link due to a unit velocity of each joint.
+    ///
+    /// The entry `[i][j]` is the linear and angular velocity of the `i`-th link due to the `j`-th
+    /// joint, or `None` if this joint does not move the link.
+    fn jacobians(&self) -> ~[~[Option<(LV, AV)>]] {
+        let n      = self.links.len();
+        let frames = self.joint_world_frames();
+        let mut res = vec::from_fn(n, |_| vec::from_elem(n, None));
+
+        for i in range(0u, n) {
+            let center = self.links[i].to_rigid_body_or_fail().center_of_mass().clone();
+            let mut j  = Some(i);
+
+            loop {
+                match j {
+                    Some(k) => {
+                        res[i][k] = Some(match self.joints[k] {
+                            Revolute(ref axis) => {
+                                let world_axis = cone_twist_equation::local_to_world_axis(
+                                    &frames[k], axis);
+                                let arm        = center - frames[k].translation();
+
+                                (angular_to_linear(&world_axis, &arm), world_axis)
+                            },
+                            Prismatic(ref axis) => (frames[k].rotate(axis), na::zero())
+                        });
+
+                        j = self.parents[k]
+                    },
+                    None => break
+                }
+            }
+        }
+
+        res
+    }
+}
+
+impl<N:  Clone + NPhysicsScalar,
+     LV: Clone + NPhysicsDirection<N, AV>,
+     AV: Clone + NPhysicsOrientation<N>,
+     M:  Clone + NPhysicsTransform<LV, AV>,
+     II: Clone + NPhysicsInertia<N, LV, AV, M>>
+Integrator<N, Body<N, LV, AV, M, II>> for Multibody<N, LV, AV, M, II> {
+    #[inline]
+    fn add(&mut self, _: @mut Body<N, LV, AV, M, II>) {
+    }
+
+    fn remove(&mut self, o: @mut Body<N, LV, AV, M, II>) {
+        if self.links.iter().any(|l| managed::mut_ptr_eq(*l, o)) {
+            self.enabled = false
+        }
+    }
+
+    fn update(&mut self, dt: N) {
+        if !self.enabled || self.links.is_empty() || !self.activate_links() {
+            return
+        }
+
+        let n                    = self.links.len();
+        let jacobians            = self.jacobians();
+        let (lin_vels, ang_vels) = self.link_velocities(jacobians);
+        let bias                 = self.bias_accelerations(jacobians, lin_vels, ang_vels);
+        let mut mass_matrix: ~[N] = vec::from_elem(n * n, na::zero());
+        let mut momentum: ~[N]    = vec::from_fn(n, |j| self.forces[j] * dt);
+
+        // project on the joints the momentum of the links (after the external accelerations have
+        // been applied) minus the change of momentum due to the velocity-dependent forces, and
+        // the inertia of the links (this is the composite rigid body algorithm)
+        for i in range(0u, n) {
+            let rb      = self.links[i].to_rigid_body_or_fail();
+            let _1: N   = na::one();
+            let mass    = _1 / rb.inv_mass();
+            let inertia = na::inv(rb.inv_inertia()).expect("A link inertia must be invertible.");
+            let (ref bias_lin, ref bias_ang) = bias[i];
+
+            let bias_force  = *bias_lin * mass;
+            let bias_torque = inertia.apply(bias_ang) +
+                              angular_cross(&ang_vels[i], &inertia.apply(&ang_vels[i]));
+
+            let lin_mom = (rb.lin_vel() + rb.lin_acc() * dt) * mass - bias_force * dt;
+            let ang_mom = inertia.apply(&(rb.ang_vel() + rb.ang_acc() * dt)) - bias_torque * dt;
+
+            for j in range(0u, n) {
+                match jacobians[i][j] {
+                    Some((ref lin_j, ref ang_j)) => {
+                        momentum[j] = momentum[j] + na::dot(lin_j, &lin_mom) +
+                                      na::dot(ang_j, &ang_mom);
+
+                        for k in range(0u, n) {
+                            match jacobians[i][k] {
+                                Some((ref lin_k, ref ang_k)) => {
+                                    mass_matrix[j * n + k] = mass_matrix[j * n + k] +
+                                        na::dot(lin_j, lin_k) * mass +
+                                        na::dot(ang_j, &inertia.apply(ang_k))
+                                },
+                                None => { }
+                            }
+                        }
+                    },
+                    None => { }
+                }
+            }
+        }
+
+        if direct_solver::solve_dense_system(mass_matrix, momentum) {
+            self.velocities = momentum;
+        }
+
+        for j in range(0u, n) {
+            self.positions[j] = self.positions[j] + self.velocities[j] * dt;
+        }
+
+        self.update_links()
+    }
+
+    #[inline]
+    fn priority(&self) -> f64 { 50.0 }
+}
+
+/// The cross product `a × b` of two angular vectors. It is zero in 2D, where every rotation has
+/// the same axis.
+fn angular_cross<N:  Clone + NPhysicsScalar,
+                 AV: Clone + NPhysicsOrientation<N>>(
+                 a: &AV,
+                 b: &AV)
+                 -> AV {
+    let mut res: AV = na::zero();
+
+    if na::dim::<AV>() == 3 {
+        res.set(0, a.at(1) * b.at(2) - a.at(2) * b.at(1));
+        res.set(1, a.at(2) * b.at(0) - a.at(0) * b.at(2));
+        res.set(2, a.at(0) * b.at(1) - a.at(1) * b.at(0));
+    }
+
+    res
+}
+
+/// The cross product `w × r` of an angular vector with a linear vector.
+fn angular_to_linear<N:  Clone + NPhysicsScalar,
+                     LV: Clone + NPhysicsDirection<N, AV>,
+                     AV: Clone + NPhysicsOrientation<N>>(
+                     w: &AV,
+                     r: &LV)
+                     -> LV {
+    let mut res: LV = na::zero();
+
+    // the k-th component is `dot(e_k, w × r) = dot(w, r × e_k)`
+    for k in range(0u, na::dim::<LV>()) {
+        let mut e_k: LV = na::zero();
+
+        e_k.set(k, na::one());
+        res.set(k, na::dot(w, &na::cross(r, &e_k)));
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Iso3, Translation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{Multibody3d, RigidBody3d};
+    use object::{RigidBody, Dynamic};
+    use integration::Integrator;
+    use signal::signal::SignalEmiter;
+    use super::{Multibody, Revolute, Prismatic};
+
+    fn ball() -> RigidBody3d<f64> {
+        RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6)
+    }
+
+    #[test]
+    fn test_links_follow_the_joints() {
+        let mb: @mut Multibody3d<f64> = Multibody::new(@mut SignalEmiter::new(), na::one());
+        let mut frame: Iso3<f64>      = na::one();
+
+        frame.append_translation(&Vec3::new(1.0, 0.0, 0.0));
+
+        let _     = mb.add_link(None, na::one(), Revolute(Vec3::new(0.0, 0.0, 1.0)), ball());
+        let child = mb.add_link(Some(0), frame, Revolute(Vec3::new(0.0, 0.0, 1.0)), ball());
+        let pi: f64 = Real::pi();
+
+        mb.set_joint_position(0, pi / 2.0);
+        mb.set_joint_velocity(0, 1.0);
+
+        // the child is one unit away from the root, along the rotated `x` axis
+        let rb = child.to_rigid_body_or_fail();
+
+        assert!(na::norm(&(rb.transform_ref().translation() - Vec3::new(0.0, 1.0, 0.0))) < 1.0e-10);
+        assert!(na::norm(&(rb.lin_vel() - Vec3::new(-1.0, 0.0, 0.0))) < 1.0e-10);
+        assert!(na::norm(&(rb.ang_vel() - Vec3::new(0.0, 0.0, 1.0))) < 1.0e-10);
+    }
+
+    #[test]
+    fn test_joint_force_accelerates_the_links() {
+        let mb: @mut Multibody3d<f64> = Multibody::new(@mut SignalEmiter::new(), na::one());
+        let link = mb.add_link(None, na::one(), Prismatic(Vec3::new(1.0, 0.0, 0.0)), ball());
+
+        mb.set_joint_force(0, 2.0);
+        mb.update(0.1);
+
+        let rb  = link.to_rigid_body_or_fail();
+        let vel = 2.0 * 0.1 * rb.inv_mass();
+
+        assert!((mb.joint_velocity(0) - vel).abs() < 1.0e-10);
+        assert!((mb.joint_position(0) - vel * 0.1).abs() < 1.0e-10);
+        assert!(na::norm(&(rb.transform_ref().translation() - Vec3::new(vel * 0.1, 0.0, 0.0))) <
+                1.0e-10);
+        assert!(na::norm(&(rb.lin_vel() - Vec3::new(vel, 0.0, 0.0))) < 1.0e-10);
+    }
+
+    #[test]
+    fn test_multibody_is_disabled_by_the_removal_of_a_link() {
+        let mb: @mut Multibody3d<f64> = Multibody::new(@mut SignalEmiter::new(), na::one());
+        let link = mb.add_link(None, na::one(), Prismatic(Vec3::new(1.0, 0.0, 0.0)), ball());
+
+        mb.set_joint_force(0, 2.0);
+        mb.remove(link);
+        mb.update(0.1);
+
+        assert!(!mb.is_enabled());
+        assert!(mb.joint_velocity(0) == 0.0);
+    }
+}
@@ -1,4 +1,5 @@
 use std::num::Zero;
+use std::any::Any;
 use nalgebra::na::{Transformation, Translation, Rotation};
 use nalgebra::na;
 use ncollide::bounding_volume::{HasBoundingVolume, AABB, HasAABB};
@@ -6,6 +7,7 @@ use ncollide::geom::Geom;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 use object::volumetric::{InertiaTensor, Volumetric};
+use resolution::constraint::softness::Softness;
 // use constraint::index_proxy::{HasIndexProxy, IndexProxy};
 
 #[deriving(ToStr, Eq, Clone, Encodable, Decodable)]
@@ -18,6 +20,7 @@ pub struct RigidBody<N, LV, AV, M, II> {
     priv state:                RigidBodyState,
     priv geom:                 Geom<N, LV, M>,
     priv local_to_world:       M,
+    priv prev_local_to_world:  M,
     priv lin_vel:              LV,
     priv ang_vel:              AV,
     priv inv_mass:             N,
@@ -29,8 +32,10 @@ pub struct RigidBody<N, LV, AV, M, II> {
     priv ang_acc:              AV,
     priv restitution:          N,
     priv friction:             N,
+    priv contact_softness:     Option<Softness<N>>,
     priv index:                int,
-    priv active:               bool
+    priv active:               bool,
+    priv user_data:            Option<@Any>
 }
 
 impl<N:  Send + Freeze + Clone,
@@ -41,22 +46,25 @@ impl<N:  Send + Freeze + Clone,
 Clone for RigidBody<N, LV, AV, M, II> {
     fn clone(&self) -> RigidBody<N, LV, AV, M, II> {
         RigidBody {
-            state:             self.state.clone(),
-            geom:              self.geom.clone(),
-            local_to_world:    self.local_to_world.clone(),
-            lin_vel:           self.lin_vel.clone(),
-            ang_vel:           self.ang_vel.clone(),
-            inv_mass:          self.inv_mass.clone(),
-            ls_inv_inertia:    self.ls_inv_inertia.clone(),
-            inv_inertia:       self.inv_inertia.clone(),
-            ls_center_of_mass: self.ls_center_of_mass.clone(),
-            center_of_mass:    self.center_of_mass.clone(),
-            lin_acc:           self.lin_acc.clone(),
-            ang_acc:           self.ang_acc.clone(),
-            restitution:       self.restitution.clone(),
-            friction:          self.friction.clone(),
-            index:             self.index.clone(),
-            active:            self.active.clone()
+            state:               self.state.clone(),
+            geom:                self.geom.clone(),
+            local_to_world:      self.local_to_world.clone(),
+            prev_local_to_world: self.prev_local_to_world.clone(),
+            lin_vel:             self.lin_vel.clone(),
+            ang_vel:             self.ang_vel.clone(),
+            inv_mass:            self.inv_mass.clone(),
+            ls_inv_inertia:      self.ls_inv_inertia.clone(),
+            inv_inertia:         self.inv_inertia.clone(),
+            ls_center_of_mass:   self.ls_center_of_mass.clone(),
+            center_of_mass:      self.center_of_mass.clone(),
+            lin_acc:             self.lin_acc.clone(),
+            ang_acc:             self.ang_acc.clone(),
+            restitution:         self.restitution.clone(),
+            friction:            self.friction.clone(),
+            contact_softness:    self.contact_softness.clone(),
+            index:               self.index.clone(),
+            active:              self.active.clone(),
+            user_data:           self.user_data
         }
     }
 }
@@ -87,6 +95,14 @@ RigidBody<N, LV, AV, M, II> {
         &'r self.local_to_world
     }
 
+    /// The transformation this body had before the last step of `BodyWorld::advance`, or when it
+    /// was added to the world.
+    ///
+    /// Renderers can blend between this and `transform_ref` to smooth fixed-timestep simulations.
+    pub fn prev_transform_ref<'r>(&'r self) -> &'r M {
+        &'r self.prev_local_to_world
+    }
+
     pub fn geom<'r>(&'r self) -> &'r Geom<N, LV, M> {
         &'r self.geom
     }
@@ -111,6 +127,19 @@ RigidBody<N, LV, AV, M, II> {
         self.friction.clone()
     }
 
+    /// The softness of the contacts involving this body, `None` to use the one of the solver.
+    pub fn contact_softness<'r>(&'r self) -> &'r Option<Softness<N>> {
+        &'r self.contact_softness
+    }
+
+    /// Makes the contacts involving this body behave like damped springs.
+    ///
+    /// This overrides the contact softness of the solver. If both bodies of a contact are soft,
+    /// the softness with the lowest frequency is used.
+    pub fn set_contact_softness(&mut self, softness: Option<Softness<N>>) {
+        self.contact_softness = softness
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
@@ -119,6 +148,18 @@ RigidBody<N, LV, AV, M, II> {
         self.active = true;
     }
 
+    /// The data attached to this body by the user.
+    ///
+    /// It is shared (not deep-copied) when the body is cloned.
+    pub fn user_data(&self) -> Option<@Any> {
+        self.user_data
+    }
+
+    /// Attaches arbitrary data to this body.
+    pub fn set_user_data(&mut self, user_data: Option<@Any>) {
+        self.user_data = user_data
+    }
+
     pub fn new(geom:        Geom<N, LV, M>,
                density:     N,
                state:       RigidBodyState,
@@ -159,6 +200,7 @@ RigidBody<N, LV, AV, M, II> {
                 state:                state,
                 geom:                 geom,
                 local_to_world:       na::one(),
+                prev_local_to_world:  na::one(),
                 lin_vel:              na::zero(),
                 ang_vel:              na::zero(),
                 inv_mass:             inv_mass,
@@ -170,8 +212,10 @@ RigidBody<N, LV, AV, M, II> {
                 ang_acc:              na::zero(),
                 friction:             friction,
                 restitution:          restitution,
+                contact_softness:     None,
                 index:                0,
-                active:               true
+                active:               true,
+                user_data:            None
             };
 
         res.update_center_of_mass();
@@ -305,6 +349,13 @@ Transformation<M> for RigidBody<N, LV, AV, M, II> {
     }
 }
 
+impl<N, LV, AV, M: Clone, II> RigidBody<N, LV, AV, M, II> {
+    /// Records the current transformation as the previous one.
+    pub fn save_transformation(&mut self) {
+        self.prev_local_to_world = self.local_to_world.clone()
+    }
+}
+
 // FIXME: implement Transfomable too
 
 impl<N:  Clone + NPhysicsScalar,
@@ -430,3 +481,45 @@ HasBoundingVolume<AABB<N, LV>> for RigidBody<N, LV, AV, M, II> {
         self.geom.aabb(&self.local_to_world)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::any::{Any, AnyRefExt};
+    use nalgebra::na::Vec3;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{RigidBody3d, Body3d, BallInSocket3d};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::ball_in_socket::BallInSocket;
+    use object::{RigidBody, Dynamic, RB};
+
+    #[test]
+    fn test_user_data_is_shared_by_clones() {
+        let mut rb: RigidBody3d<f64> =
+            RigidBody::new(Geom::new_ball(1.0f64), 1.0, Dynamic, 0.3, 0.6);
+
+        assert!(rb.user_data().is_none());
+
+        rb.set_user_data(Some(@42i as @Any));
+
+        let body: Body3d<f64> = RB(rb.clone());
+
+        assert!(body.user_data().unwrap().as_ref::<int>() == Some(&42));
+        assert!(rb.user_data().unwrap().as_ref::<~str>().is_none());
+
+        rb.set_user_data(None);
+
+        assert!(rb.user_data().is_none());
+        assert!(body.user_data().is_some());
+    }
+
+    #[test]
+    fn test_joint_user_data() {
+        let anchor1 = Anchor::new(None, Vec3::new(0.0f64, 0.0, 0.0));
+        let anchor2 = Anchor::new(None, Vec3::new(1.0f64, 0.0, 0.0));
+        let mut joint: BallInSocket3d<f64> = BallInSocket::new(anchor1, anchor2);
+
+        joint.set_user_data(Some(@7u as @Any));
+
+        assert!(joint.clone().user_data().unwrap().as_ref::<uint>() == Some(&7));
+    }
+}
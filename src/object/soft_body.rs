@@ -1,6 +1,7 @@
 use std::num::Zero;
-use nalgebra::na::AlgebraicVec;
+use nalgebra::na::AlgebraicVecExt;
 use nalgebra::na;
+use ncollide::bounding_volume::{AABB, BoundingVolume};
 
 #[deriving(Clone, Encodable, Decodable)]
 pub struct PointMass<N, V> {
@@ -23,12 +24,13 @@ pub struct SoftBody<N, V> {
     acc:         V,
     points:      ~[PointMass<N, V>],
     constraints: ~[ConstraintsGeometry<N>],
+    aabb:        AABB<N, V>,
     active:      bool,
     index:       int
 }
 
 impl<N: Num + NumCast + Signed + Bounded + Algebraic + Eq + Ord + Clone,
-     V: AlgebraicVec<N> + Clone>
+     V: AlgebraicVecExt<N> + Clone>
      SoftBody<N, V> {
     pub fn new_from_mesh(vbuf:      ~[V],
                          ids1:      ~[i32],
@@ -37,6 +39,7 @@ impl<N: Num + NumCast + Signed + Bounded + Algebraic + Eq + Ord + Clone,
                          stiffness: ~[N]) -> SoftBody<N, V> {
         assert!(vbuf.len() == invmasses.len(),
         "Vertex buffer and mass informations must have the same size.");
+        assert!(!vbuf.is_empty(), "A soft body must have at least one point.");
 
         // create points mass
         let mut points = ~[];
@@ -66,9 +69,12 @@ impl<N: Num + NumCast + Signed + Bounded + Algebraic + Eq + Ord + Clone,
             });
         }
 
+        let aabb = points_aabb(points);
+
         SoftBody {
             points:      points,
             constraints: constraints,
+            aabb:        aabb,
             acc:         na::zero(),
             index:       0,
             active:      true
@@ -76,12 +82,40 @@ impl<N: Num + NumCast + Signed + Bounded + Algebraic + Eq + Ord + Clone,
         }
     }
 
-    pub fn points<'r>(&'r mut self) -> &'r mut ~[PointMass<N, V>] {
-        &'r mut self.points
+    /// The points of this soft body.
+    ///
+    /// `update_aabb` must be called once they are moved.
+    pub fn points<'r>(&'r mut self) -> &'r mut [PointMass<N, V>] {
+        self.points.as_mut_slice()
+    }
+
+    /// Recomputes the bounding box of the points, returned by `aabb`.
+    pub fn update_aabb(&mut self) {
+        self.aabb = points_aabb(self.points)
+    }
+}
+
+/// The bounding box of a non-empty set of points.
+fn points_aabb<N: Num + NumCast + Signed + Bounded + Algebraic + Eq + Ord + Clone,
+               V: AlgebraicVecExt<N> + Clone>(
+               points: &[PointMass<N, V>])
+               -> AABB<N, V> {
+    let mut res = AABB::new(points[0].position.clone(), points[0].position.clone());
+
+    for p in points.slice_from(1).iter() {
+        res = res.merged(&AABB::new(p.position.clone(), p.position.clone()));
     }
+
+    res
 }
 
 impl<N, V> SoftBody<N, V> {
+    /// The bounding box of the points of this soft body, as of the last call to `update_aabb`.
+    #[inline]
+    pub fn aabb<'r>(&'r self) -> &'r AABB<N, V> {
+        &'r self.aabb
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
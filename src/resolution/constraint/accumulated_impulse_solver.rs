@@ -1,30 +1,202 @@
 use std::ptr;
+use std::util;
+use std::num::{Zero, One, Orderable, Signed};
 // use std::rand::RngUtil;
-use nalgebra::na::{Transformation, RotationWithTranslation, CrossMatrix, Row};
+use nalgebra::na::{Transformation, RotationWithTranslation, CrossMatrix, Row, Vec};
 use nalgebra::na;
-use detection::constraint::{Constraint, RBRB, BallInSocket, Fixed};
+use detection::IslandActivationManager;
+use detection::constraint::{Constraint, RBRB, BallInSocket, Fixed, Distance, ConeTwist, GenericJoint, Gear, Pulley};
 use object::Body;
 use resolution::constraint::velocity_constraint::VelocityConstraint;
 use resolution::constraint::contact_equation;
 use resolution::constraint::contact_equation::{CorrectionMode, CorrectionParameters};
 use resolution::constraint::ball_in_socket_equation;
 use resolution::constraint::fixed_equation;
+use resolution::constraint::distance_equation;
+use resolution::constraint::pulley_equation;
+use resolution::constraint::gear_equation;
+use resolution::constraint::generic_joint_equation;
+use resolution::constraint::cone_twist_equation;
+use resolution::constraint::softness::Softness;
 use resolution::solver::Solver;
-use pgs = resolution::constraint::projected_gauss_seidel_solver;
-use resolution::constraint::projected_gauss_seidel_solver::Velocities;
+use resolution::constraint::projected_gauss_seidel_solver::{Velocities, ConstraintOrdering,
+                                                            BlockWorkspace, Sequential, BottomUp};
 use resolution::constraint::impulse_cache::ImpulseCache;
+use resolution::constraint::lcp_solver;
+use resolution::constraint::lcp_solver::{LCPSolver, ProjectedGaussSeidel, Dantzig};
+use pgs = resolution::constraint::projected_gauss_seidel_solver;
+use utils::union_find::{UFindSet, find, union};
+use utils::worker_pool::WorkerPool;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
+/// A set of constraints which do not share any movable body with the other islands.
+struct Island<N, LV, AV, M, II> {
+    bodies:           ~[@mut Body<N, LV, AV, M, II>],
+    contacts:         ~[uint],
+    joints:           ~[uint],
+    needs_correction: bool,
+    system:           IslandSystem<LV, AV, N>
+}
+
+impl<N: Zero + One, LV, AV, M, II> Island<N, LV, AV, M, II> {
+    pub fn new() -> Island<N, LV, AV, M, II> {
+        Island {
+            bodies:           ~[],
+            contacts:         ~[],
+            joints:           ~[],
+            needs_correction: false,
+            system:           IslandSystem::new()
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bodies.clear();
+        self.contacts.clear();
+        self.joints.clear();
+        self.needs_correction = false;
+    }
+}
+
+/// The velocity constraints of an island.
+///
+/// It does not reference any body so that it can be sent to another task.
+struct IslandSystem<LV, AV, N> {
+    restitution:     ~[VelocityConstraint<LV, AV, N>],
+    friction:        ~[VelocityConstraint<LV, AV, N>],
+    MJLambda:        ~[Velocities<LV, AV>],
+    lcp_solver:      LCPSolver,
+    num_iter:        uint,
+    tolerance:       N,
+    omega:           N,
+    ordering:        ConstraintOrdering<LV>,
+    manifolds:       bool,
+    is_lambda_zero:  bool,
+    blocks:          BlockWorkspace<LV, AV, N>,
+    // split impulse correction
+    pseudo:          ~[VelocityConstraint<LV, AV, N>],
+    pseudo_MJLambda: ~[Velocities<LV, AV>],
+    num_pseudo_iter: uint,
+    // results of the last resolution
+    num_iter_used:   uint,
+    residual:        N,
+    fell_back:       bool
+}
+
+impl<LV, AV, N: Zero + One> IslandSystem<LV, AV, N> {
+    pub fn new() -> IslandSystem<LV, AV, N> {
+        IslandSystem {
+            restitution:     ~[],
+            friction:        ~[],
+            MJLambda:        ~[],
+            lcp_solver:      ProjectedGaussSeidel,
+            num_iter:        0,
+            tolerance:       na::zero(),
+            omega:           na::one(),
+            ordering:        Sequential,
+            manifolds:       false,
+            is_lambda_zero:  false,
+            blocks:          BlockWorkspace::new(),
+            pseudo:          ~[],
+            pseudo_MJLambda: ~[],
+            num_pseudo_iter: 0,
+            num_iter_used:   0,
+            residual:        na::zero(),
+            fell_back:       false
+        }
+    }
+}
+
+impl<LV: Zero + Clone, AV: Zero + Clone, N: Zero + Clone> IslandSystem<LV, AV, N> {
+    pub fn resize(&mut self, num_restitution_equations: uint, num_friction_equations: uint) {
+        resize_buffer(&mut self.restitution, num_restitution_equations, VelocityConstraint::new());
+        resize_buffer(&mut self.friction, num_friction_equations, VelocityConstraint::new());
+    }
+}
+
+impl<LV: Vec<N> + Zero + Clone,
+     AV: Vec<N> + Zero + Clone,
+     N:  Num + Orderable + Signed + Clone>
+IslandSystem<LV, AV, N> {
+    pub fn solve(&mut self) {
+        if self.num_iter != 0 {
+            let num_bodies = self.MJLambda.len();
+
+            let (num_iter_used, residual, succeeded) = lcp_solver::solve(
+                &self.lcp_solver,
+                self.restitution,
+                self.friction,
+                self.MJLambda,
+                num_bodies,
+                self.num_iter,
+                &self.tolerance,
+                &self.omega,
+                &self.ordering,
+                &mut self.blocks,
+                self.manifolds,
+                self.is_lambda_zero);
+
+            self.num_iter_used = num_iter_used;
+            self.residual      = residual;
+            self.fell_back     = !succeeded;
+
+            if self.num_pseudo_iter != 0 {
+                // the pseudo-velocities have their own impulses and velocity changes: they never
+                // modify the result of the velocity resolution
+                pgs::projected_gauss_seidel_solve(self.pseudo,
+                                                  self.friction.mut_slice_to(0),
+                                                  self.pseudo_MJLambda,
+                                                  num_bodies,
+                                                  self.num_pseudo_iter,
+                                                  &na::zero(),
+                                                  &self.omega,
+                                                  &self.ordering,
+                                                  None,
+                                                  true);
+            }
+        }
+        else {
+            self.num_iter_used = 0;
+            self.residual      = na::zero();
+            self.fell_back     = false;
+        }
+    }
+}
 
 pub struct AccumulatedImpulseSolver<N, LV, AV, M, II, M2> {
     priv correction:              CorrectionParameters<N>,
     priv cache:                   ImpulseCache<N, LV>,
     priv num_first_order_iter:    uint,
     priv num_second_order_iter:   uint,
-    priv restitution_constraints: ~[VelocityConstraint<LV, AV, N>],
-    priv friction_constraints:    ~[VelocityConstraint<LV, AV, N>],
-    priv MJLambda:                ~[Velocities<LV, AV>]
+    priv num_joint_pos_iter:      uint,
+    priv joint_pos_corr:          N,
+    priv num_threads:             uint,
+    priv min_parallel_cost:       uint,
+    priv workers:                 WorkerPool<IslandSystem<LV, AV, N>>,
+    priv lcp_solver:              LCPSolver,
+    priv max_direct_size:         uint,
+    priv tolerance:               N,
+    priv omega:                   N,
+    priv ordering:                ConstraintOrdering<LV>,
+    priv solve_manifolds:         bool,
+
+    // island partitioning
+    priv activation_manager:      Option<@mut IslandActivationManager<N, LV, AV, M, II>>,
+    priv bodies:                  ~[@mut Body<N, LV, AV, M, II>],
+    priv islands:                 ~[Island<N, LV, AV, M, II>],
+    priv island_ids:              ~[uint],
+    priv ufind:                   ~[UFindSet],
+    priv partitioned:             ~[bool],
+    priv island_roots:            ~[int],
+    priv cache_ids:               ~[(uint, uint)],
+
+    // statistics of the last resolution
+    priv num_contacts:            uint,
+    priv num_joints:              uint,
+    priv num_islands:             uint,
+    priv num_iterations:          uint,
+    priv num_fallbacks:           uint,
+    priv residual:                N
 }
 
 impl<N:  'static + Clone + NPhysicsScalar,
@@ -44,66 +216,523 @@ AccumulatedImpulseSolver<N, LV, AV, M, II, M2> {
         AccumulatedImpulseSolver {
             num_first_order_iter:    num_first_order_iter,
             num_second_order_iter:   num_second_order_iter,
-            restitution_constraints: ~[],
-            friction_constraints:    ~[],
-            MJLambda:                ~[],
+            num_joint_pos_iter:      0,
+            joint_pos_corr:          na::cast(0.2),
+            num_threads:             1,
+            min_parallel_cost:       2048,
+            workers:                 WorkerPool::new(0, solve_system),
+            lcp_solver:              ProjectedGaussSeidel,
+            max_direct_size:         64,
+            tolerance:               na::zero(),
+            omega:                   na::one(),
+            ordering:                Sequential,
+            solve_manifolds:         false,
+            residual:                na::zero(),
+            activation_manager:      None,
+            bodies:                  ~[],
+            islands:                 ~[],
+            island_ids:              ~[],
+            ufind:                   ~[],
+            partitioned:             ~[],
+            island_roots:            ~[],
+            cache_ids:               ~[],
+            num_contacts:            0,
+            num_joints:              0,
+            num_islands:             0,
+            num_iterations:          0,
+            num_fallbacks:           0,
             cache:                   ImpulseCache::new(step, na::dim::<LV>()),
 
             correction: CorrectionParameters {
-                corr_mode:  correction_mode,
-                joint_corr: joint_corr_factor,
-                rest_eps:   rest_eps
+                corr_mode:        correction_mode,
+                joint_corr:       joint_corr_factor,
+                rest_eps:         rest_eps,
+                contact_softness: None
             }
         }
     }
 
-    fn resize_buffers(&mut self, num_restitution_equations: uint, num_friction_equations: uint) {
-        resize_buffer(&mut self.restitution_constraints,
-                      num_restitution_equations,
-                      VelocityConstraint::new());
+    pub fn num_first_order_iter(&self) -> uint {
+        self.num_first_order_iter
+    }
+
+    pub fn set_num_first_order_iter(&mut self, num: uint) {
+        self.num_first_order_iter = num
+    }
+
+    pub fn num_second_order_iter(&self) -> uint {
+        self.num_second_order_iter
+    }
+
+    pub fn set_num_second_order_iter(&mut self, num: uint) {
+        self.num_second_order_iter = num
+    }
+
+    /// The number of non-linear Gauss-Seidel sweeps projecting the joints positions.
+    pub fn num_joint_pos_iter(&self) -> uint {
+        self.num_joint_pos_iter
+    }
+
+    /// Sets the number of non-linear Gauss-Seidel sweeps projecting the joints positions.
+    ///
+    /// After the velocity resolution, each sweep moves the bodies attached to each joint to
+    /// reduce its positional error, without modifying their velocities. The default, 0, disables
+    /// the projection: the joints positional errors are then only corrected by the velocity
+    /// resolution.
+    pub fn set_num_joint_pos_iter(&mut self, num: uint) {
+        self.num_joint_pos_iter = num
+    }
+
+    /// The fraction of each joint positional error removed by one projection.
+    pub fn joint_pos_corr(&self) -> N {
+        self.joint_pos_corr.clone()
+    }
+
+    /// Sets the fraction of each joint positional error removed by one projection.
+    pub fn set_joint_pos_corr(&mut self, factor: N) {
+        assert!(factor > na::zero() && factor <= na::one(),
+                "The joint position correction factor must be in ]0, 1].");
+
+        self.joint_pos_corr = factor
+    }
+
+    /// The softness of the contacts between bodies without softness of their own, `None` if they
+    /// are rigid.
+    pub fn contact_softness<'r>(&'r self) -> &'r Option<Softness<N>> {
+        &self.correction.contact_softness
+    }
+
+    /// Makes every contact behave like a damped spring, or rigid again with `None`.
+    ///
+    /// This applies to the contacts between bodies without softness of their own (see
+    /// `RigidBody::set_contact_softness`).
+    ///
+    /// The penetration of soft contacts is corrected by the velocity constraints with the
+    /// softness error reduction parameter, instead of the velocity correction factor of the
+    /// correction mode. The position correction is not affected.
+    pub fn set_contact_softness(&mut self, softness: Option<Softness<N>>) {
+        self.correction.contact_softness = softness
+    }
+
+    /// The impulse change below which the velocity resolution of an island stops early.
+    pub fn tolerance(&self) -> N {
+        self.tolerance.clone()
+    }
+
+    /// Sets the impulse change below which the velocity resolution of an island stops early.
+    ///
+    /// A PGS sweep which changes no impulse by more than `tolerance` ends the resolution, even if
+    /// `num_second_order_iter` sweeps have not been performed yet. Set it to zero (the default) to
+    /// always perform every sweep.
+    pub fn set_tolerance(&mut self, tolerance: N) {
+        self.tolerance = tolerance
+    }
+
+    /// The algorithm used to solve the velocity constraints of each island.
+    pub fn lcp_solver(&self) -> LCPSolver {
+        self.lcp_solver.clone()
+    }
+
+    /// Sets the algorithm used to solve the velocity constraints of each island.
+    ///
+    /// The default is `ProjectedGaussSeidel`. With `Dantzig`, the islands with more than
+    /// `self.max_direct_size()` equations are still solved with `ProjectedGaussSeidel`.
+    pub fn set_lcp_solver(&mut self, solver: LCPSolver) {
+        self.lcp_solver = solver
+    }
+
+    /// The largest number of equations of an island solved with `Dantzig`.
+    pub fn max_direct_size(&self) -> uint {
+        self.max_direct_size
+    }
+
+    /// Sets the largest number of equations of an island solved with `Dantzig`.
+    ///
+    /// The cost of the direct resolution grows with the cube of the number of equations, so the
+    /// larger islands are solved with `ProjectedGaussSeidel`. The default is 64.
+    pub fn set_max_direct_size(&mut self, size: uint) {
+        self.max_direct_size = size
+    }
+
+    /// The relaxation factor applied to each impulse change.
+    pub fn omega(&self) -> N {
+        self.omega.clone()
+    }
+
+    /// Sets the relaxation factor applied to each impulse change.
+    ///
+    /// The default, 1, is plain Gauss-Seidel. Values between 1 and 2 (successive
+    /// over-relaxation) may converge faster, values smaller than 1 are more stable.
+    pub fn set_omega(&mut self, omega: N) {
+        assert!(omega > na::zero() && omega < na::cast(2.0), "omega must be in ]0, 2[.");
+
+        self.omega = omega
+    }
+
+    /// The order in which the constraints are visited at each sweep.
+    pub fn ordering<'a>(&'a self) -> &'a ConstraintOrdering<LV> {
+        &'a self.ordering
+    }
+
+    /// Sets the order in which the constraints are visited at each sweep.
+    ///
+    /// With `BottomUp`, the contacts of each island are sorted by height before the resolution.
+    pub fn set_ordering(&mut self, ordering: ConstraintOrdering<LV>) {
+        self.ordering = ordering
+    }
+
+    /// Whether `ProjectedGaussSeidel` solves the contact manifolds exactly.
+    pub fn solve_manifolds(&self) -> bool {
+        self.solve_manifolds
+    }
+
+    /// Sets whether `ProjectedGaussSeidel` solves the contact manifolds exactly.
+    ///
+    /// If `true`, the normal constraints of each contact manifold of 2 to 4 points are solved
+    /// together, with Dantzig's algorithm, at each sweep. This removes most of the rocking of the
+    /// boxes resting on a face, but each sweep is more expensive. The default is `false`: every
+    /// constraint is solved one by one.
+    pub fn set_solve_manifolds(&mut self, solve_manifolds: bool) {
+        self.solve_manifolds = solve_manifolds
+    }
+
+    /// The number of tasks the islands are distributed over.
+    pub fn num_threads(&self) -> uint {
+        self.num_threads
+    }
+
+    /// Sets the number of tasks the islands are distributed over.
+    ///
+    /// The calling task solves its share of the islands, and `num - 1` worker tasks are spawned
+    /// once to solve the others at each step. Each island is always solved the same way and its
+    /// results are applied in the same order, so the simulation output does not depend on the
+    /// number of threads.
+    pub fn set_num_threads(&mut self, num: uint) {
+        assert!(num != 0, "The number of threads must not be zero.");
+
+        if num != self.num_threads {
+            self.num_threads = num;
+            self.workers     = WorkerPool::new(num - 1, solve_system);
+        }
+    }
+
+    /// The total cost below which the islands are all solved by the calling task.
+    pub fn min_parallel_cost(&self) -> uint {
+        self.min_parallel_cost
+    }
+
+    /// Sets the total cost below which the islands are all solved by the calling task.
+    ///
+    /// The cost of an island is its number of equations times its number of iterations. Below
+    /// this threshold, sending the islands to the worker tasks costs more than solving them.
+    pub fn set_min_parallel_cost(&mut self, cost: uint) {
+        self.min_parallel_cost = cost
+    }
 
-        resize_buffer(&mut self.friction_constraints,
-                      num_friction_equations,
-                      VelocityConstraint::new());
+    /// The activation manager whose islands are reused to split the constraints.
+    pub fn activation_manager(&self) -> Option<@mut IslandActivationManager<N, LV, AV, M, II>> {
+        self.activation_manager
     }
 
-    fn do_solve(&mut self,
-                dt:          N,
-                constraints: &[Constraint<N, LV, AV, M, II>],
-                joints:      &[uint],
-                bodies:      &[@mut Body<N, LV, AV, M, II>]) {
-        let num_friction_equations    = (na::dim::<LV>() - 1) * self.cache.len();
-        let num_restitution_equations = self.cache.len();
+    /// Sets the activation manager whose islands are reused to split the constraints.
+    ///
+    /// Its islands are used only when it was the last detector to output constraints before the
+    /// resolution. Otherwise, or without activation manager, the islands are computed from
+    /// scratch.
+    pub fn set_activation_manager(&mut self,
+                                  manager: Option<@mut IslandActivationManager<N, LV, AV, M, II>>) {
+        self.activation_manager = manager
+    }
+
+    /// The number of contacts solved during the last step.
+    pub fn num_contacts(&self) -> uint {
+        self.num_contacts
+    }
+
+    /// The number of joints solved during the last step.
+    pub fn num_joints(&self) -> uint {
+        self.num_joints
+    }
+
+    /// The number of independent islands solved during the last step.
+    pub fn num_islands(&self) -> uint {
+        self.num_islands
+    }
+
+    /// The total number of PGS iterations performed during the last step, summed over all
+    /// islands.
+    pub fn num_iterations(&self) -> uint {
+        self.num_iterations
+    }
+
+    /// The number of islands for which `Dantzig` failed during the last step, and which were solved
+    /// with `ProjectedGaussSeidel` instead.
+    pub fn num_fallbacks(&self) -> uint {
+        self.num_fallbacks
+    }
+
+    /// The largest impulse change of the last velocity resolution sweep, over all islands.
+    pub fn residual(&self) -> N {
+        self.residual.clone()
+    }
+
+    /// Assigns a global index to each movable body and collects them on `self.bodies`.
+    fn assign_body_indices(&mut self, constraints: &[Constraint<N, LV, AV, M, II>]) {
+        // This is a two-passes assignation of index to the rigid bodies.
+        // This is not very good, but is the only way to do that without having a separate list
+        // of all rigid bodies.
+        for c in constraints.iter() {
+            let (b1, b2) = c.anchors();
+
+            match b1 {
+                Some(b) => b.set_index(-2),
+                None    => { }
+            };
+
+            match b2 {
+                Some(b) => b.set_index(-2),
+                None    => { }
+            }
+        }
+
+        let mut id = 0;
+
+        fn set_body_index<N:  Clone + NPhysicsScalar,
+                          LV: Clone + NPhysicsDirection<N, AV>,
+                          AV: Clone + NPhysicsOrientation<N>,
+                          M:  NPhysicsTransform<LV, AV>,
+                          II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                          a:      @mut Body<N, LV, AV, M, II>,
+                          bodies: &mut ~[@mut Body<N, LV, AV, M, II>],
+                          id:     &mut int) {
+            if a.index() == -2 {
+                if a.can_move() {
+                    a.set_index(*id);
+                    bodies.push(a);
+                    *id = *id + 1;
+                }
+                else {
+                    a.set_index(-1)
+                }
+            }
+        }
+
+        self.bodies.clear();
+
+        for c in constraints.iter() {
+            let (b1, b2) = c.anchors();
+
+            match b1 {
+                Some(b) => set_body_index(b, &mut self.bodies, &mut id),
+                None    => { }
+            }
+
+            match b2 {
+                Some(b) => set_body_index(b, &mut self.bodies, &mut id),
+                None    => { }
+            }
+        }
+    }
+
+    /// Splits the constraints into islands and assigns to each body its index inside of its
+    /// island.
+    ///
+    /// The bodies must have been given a global index by `assign_body_indices` first.
+    fn build_islands(&mut self, constraints: &[Constraint<N, LV, AV, M, II>]) {
+        let num_bodies = self.bodies.len();
+
+        /*
+         * Run the union find on movable bodies.
+         */
+        resize_buffer(&mut self.ufind, num_bodies, UFindSet::new(0));
+
+        for (i, u) in self.ufind.mut_iter().enumerate() {
+            u.reinit(i)
+        }
+
+        let manager = self.activation_manager;
+
+        match manager {
+            Some(am) if am.partitions(constraints) => {
+                /*
+                 * Reuse the islands of the activation manager: only the constraints involving
+                 * bodies it has woken up are missing from its partition.
+                 */
+                resize_buffer(&mut self.partitioned, num_bodies, false);
+                resize_buffer(&mut self.island_roots, am.num_island_ids(), -1);
+
+                for r in self.island_roots.mut_iter() {
+                    *r = -1
+                }
+
+                for (i, b) in self.bodies.iter().enumerate() {
+                    match am.island_id(*b) {
+                        Some(id) => {
+                            if self.island_roots[id] < 0 {
+                                self.island_roots[id] = i as int
+                            }
+                            else {
+                                union(i, self.island_roots[id] as uint, self.ufind)
+                            }
+
+                            self.partitioned[i] = true
+                        },
+                        None => self.partitioned[i] = false
+                    }
+                }
+
+                for c in constraints.iter() {
+                    let (id1, id2) = body_indices(c);
+
+                    if id1 >= 0 && id2 >= 0 &&
+                       !(self.partitioned[id1 as uint] && self.partitioned[id2 as uint]) {
+                        union(id1 as uint, id2 as uint, self.ufind)
+                    }
+                }
+
+                // the next resolution must not reuse this partition if the manager does not run
+                am.invalidate_partition();
+            },
+            _ => {
+                for c in constraints.iter() {
+                    let (id1, id2) = body_indices(c);
+
+                    if id1 >= 0 && id2 >= 0 {
+                        union(id1 as uint, id2 as uint, self.ufind)
+                    }
+                }
+            }
+        }
+
+        /*
+         * Create one island per root.
+         */
+        resize_buffer(&mut self.island_ids, num_bodies, 0u);
+
+        self.num_islands = 0;
+
+        for i in range(0u, num_bodies) {
+            if find(i, self.ufind) == i {
+                self.island_ids[i] = self.num_islands;
+                self.num_islands   = self.num_islands + 1;
+            }
+        }
+
+        while self.islands.len() < self.num_islands {
+            self.islands.push(Island::new())
+        }
+
+        for island in self.islands.mut_iter() {
+            island.clear()
+        }
+
+        /*
+         * Dispatch the constraints (this needs the global body indices).
+         */
+        for (i, c) in constraints.iter().enumerate() {
+            let (id1, id2) = body_indices(c);
+            let id         = if id1 >= 0 { id1 } else { id2 };
+
+            if id < 0 {
+                // nothing can move: nothing to solve
+                continue;
+            }
+
+            let root   = find(id as uint, self.ufind);
+            let island = &mut self.islands[self.island_ids[root]];
+
+            match *c {
+                RBRB(_, _, _) => island.contacts.push(i),
+                _             => island.joints.push(i)
+            }
+        }
+
+        /*
+         * Replace the global body indices by indices local to each island.
+         */
+        for (i, b) in self.bodies.iter().enumerate() {
+            let root   = find(i, self.ufind);
+            let island = &mut self.islands[self.island_ids[root]];
+
+            b.set_index(island.bodies.len() as int);
+            island.bodies.push(*b);
+        }
+    }
+
+    /// Fills the velocity constraints of an island with the contact and joint equations.
+    fn fill_velocity_equations(&mut self,
+                               dt:          N,
+                               constraints: &[Constraint<N, LV, AV, M, II>],
+                               island:      &mut Island<N, LV, AV, M, II>) {
+        match self.ordering {
+            BottomUp(ref up) => sort_contacts_by_height(constraints, island.contacts, up),
+            _                => { }
+        }
+
+        let num_friction_equations    = (na::dim::<LV>() - 1) * island.contacts.len();
+        let num_restitution_equations = island.contacts.len();
         let mut num_joint_equations = 0;
 
-        for i in joints.iter() {
+        for i in island.joints.iter() {
             match constraints[*i] {
-                BallInSocket(_) => {
-                    num_joint_equations = num_joint_equations + na::dim::<LV>()
+                BallInSocket(bis) => {
+                    num_joint_equations = num_joint_equations + ball_in_socket_equation::num_equations(bis)
                 },
                 Fixed(_) => {
                     num_joint_equations = num_joint_equations + na::dim::<LV>() + na::dim::<AV>()
                 },
+                Distance(_) => {
+                    num_joint_equations = num_joint_equations + 1
+                },
+                Pulley(_) => {
+                    num_joint_equations = num_joint_equations + 1
+                },
+                Gear(_) => {
+                    num_joint_equations = num_joint_equations + 1
+                },
+                GenericJoint(_) => {
+                    num_joint_equations = num_joint_equations + generic_joint_equation::num_equations::<LV, AV>()
+                },
+                ConeTwist(_) => {
+                    num_joint_equations = num_joint_equations + cone_twist_equation::num_equations::<LV, AV>()
+                },
                 RBRB(_, _, _) => { }
             }
         }
 
-        self.resize_buffers(num_restitution_equations + num_joint_equations, num_friction_equations);
+        let system = &mut island.system;
+
+        system.resize(num_restitution_equations + num_joint_equations, num_friction_equations);
+        resize_buffer(&mut system.MJLambda, island.bodies.len(), Velocities::new());
+        system.lcp_solver     = self.island_lcp_solver(num_restitution_equations +
+                                                       num_joint_equations +
+                                                       num_friction_equations);
+        system.num_iter       = self.num_second_order_iter;
+        system.tolerance      = self.tolerance.clone();
+        system.omega          = self.omega.clone();
+        system.ordering       = self.ordering.clone();
+        system.manifolds      = self.solve_manifolds;
+        system.is_lambda_zero = false;
+
+        self.num_contacts = self.num_contacts + num_restitution_equations;
+        self.num_joints   = self.num_joints + island.joints.len();
 
         let mut friction_offset = 0;
 
-        for (i, (_, &(ci, imp))) in self.cache.hash().iter().enumerate() {
-            match constraints[ci] {
+        for (i, ci) in island.contacts.iter().enumerate() {
+            let (prev_imp, _) = self.cache_ids[*ci];
+
+            match constraints[*ci] {
                 RBRB(rb1, rb2, ref c) => {
                     contact_equation::fill_second_order_equation(
                         dt.clone(),
                         c,
                         rb1.to_rigid_body_or_fail(), rb2.to_rigid_body_or_fail(),
-                        &mut self.restitution_constraints[i],
+                        &mut system.restitution[i],
                         i,
-                        self.friction_constraints,
+                        system.friction,
                         friction_offset,
-                        self.cache.impulsions_at(imp),
+                        self.cache.impulsions_at(prev_imp),
                         &self.correction);
                 },
                 _ => { }
@@ -112,125 +741,278 @@ AccumulatedImpulseSolver<N, LV, AV, M, II, M2> {
             friction_offset = friction_offset + na::dim::<LV>() - 1;
         }
 
+        match self.correction.corr_mode.split_impulse() {
+            Some((factor, slop)) => {
+                resize_buffer(&mut system.pseudo, num_restitution_equations, VelocityConstraint::new());
+                resize_buffer(&mut system.pseudo_MJLambda, island.bodies.len(), Velocities::new());
+                system.num_pseudo_iter = self.num_first_order_iter;
+
+                for (i, ci) in island.contacts.iter().enumerate() {
+                    match constraints[*ci] {
+                        RBRB(_, _, ref c) => {
+                            system.pseudo[i] = system.restitution[i].clone();
+
+                            contact_equation::fill_pseudo_velocity_equation(
+                                dt.clone(),
+                                c,
+                                &mut system.pseudo[i],
+                                &factor,
+                                &slop);
+                        },
+                        _ => { }
+                    }
+                }
+            },
+            None => system.num_pseudo_iter = 0
+        }
+
         let mut joint_offset = num_restitution_equations;
-        for i in joints.iter() {
+        for i in island.joints.iter() {
             match constraints[*i] {
                 BallInSocket(bis) => {
                     ball_in_socket_equation::fill_second_order_equation(
                         dt.clone(),
                         bis,
-                        self.restitution_constraints.mut_slice_from(joint_offset), // XXX
+                        system.restitution.mut_slice_from(joint_offset), // XXX
                         &self.correction
                     );
 
-                    joint_offset = joint_offset + na::dim::<LV>();
+                    joint_offset = joint_offset + ball_in_socket_equation::num_equations(bis);
                 },
                 Fixed(f) => {
                     fixed_equation::fill_second_order_equation(
                         dt.clone(),
                         f,
-                        self.restitution_constraints.mut_slice_from(joint_offset), // XXX
+                        system.restitution.mut_slice_from(joint_offset), // XXX
                         &self.correction
                     );
 
                     joint_offset = joint_offset + na::dim::<LV>() + na::dim::<AV>();
                 },
+                Distance(d) => {
+                    distance_equation::fill_second_order_equation(
+                        dt.clone(),
+                        d,
+                        &mut system.restitution[joint_offset],
+                        &self.correction
+                    );
+
+                    joint_offset = joint_offset + 1;
+                },
+                Pulley(p) => {
+                    pulley_equation::fill_second_order_equation(
+                        dt.clone(),
+                        p,
+                        system.restitution.mut_slice_from(joint_offset), // XXX
+                        &self.correction
+                    );
+
+                    joint_offset = joint_offset + 1;
+                },
+                Gear(gr) => {
+                    gear_equation::fill_second_order_equation(
+                        dt.clone(),
+                        gr,
+                        system.restitution.mut_slice_from(joint_offset), // XXX
+                        &self.correction
+                    );
+
+                    joint_offset = joint_offset + 1;
+                },
+                GenericJoint(g) => {
+                    generic_joint_equation::fill_second_order_equation(
+                        dt.clone(),
+                        g,
+                        system.restitution.mut_slice_from(joint_offset), // XXX
+                        &self.correction
+                    );
+
+                    joint_offset = joint_offset + generic_joint_equation::num_equations::<LV, AV>();
+                },
+                ConeTwist(ct) => {
+                    cone_twist_equation::fill_second_order_equation(
+                        dt.clone(),
+                        ct,
+                        system.restitution.mut_slice_from(joint_offset), // XXX
+                        &self.correction
+                    );
+
+                    joint_offset = joint_offset + cone_twist_equation::num_equations::<LV, AV>();
+                },
                 RBRB(_, _, _) => { }
             }
         }
+    }
 
-        resize_buffer(&mut self.MJLambda, bodies.len(), Velocities::new());
-
-        // FIXME: parametrize by the resolution algorithm?
-        pgs::projected_gauss_seidel_solve(
-            self.restitution_constraints,
-            self.friction_constraints,
-            self.MJLambda,
-            bodies.len(),
-            self.num_second_order_iter,
-            false);
-
-        // FIXME: this is _so_ ugly!
-        self.resize_buffers(num_restitution_equations, num_friction_equations);
+    /// Applies the velocity changes of a solved island and caches its impulses for the next step
+    /// warm-starting. The bodies are then moved by the pseudo-velocities of the split impulse
+    /// correction, if any.
+    fn apply_velocities(&mut self, dt: N, island: &Island<N, LV, AV, M, II>) {
+        let system = &island.system;
 
-        for b in bodies.iter() {
+        for b in island.bodies.iter() {
             let rb = b.to_mut_rigid_body_or_fail();
             let i  = rb.index();
 
             let curr_lin_vel = rb.lin_vel();
             let curr_ang_vel = rb.ang_vel();
 
-            rb.set_lin_vel(curr_lin_vel + self.MJLambda[i].lv);
-            rb.set_ang_vel(curr_ang_vel + self.MJLambda[i].av);
+            rb.set_lin_vel(curr_lin_vel + system.MJLambda[i].lv);
+            rb.set_ang_vel(curr_ang_vel + system.MJLambda[i].av);
         }
 
-        for (i, dv) in self.restitution_constraints.iter().enumerate() {
-            let imps = self.cache.push_impulsions();
-            imps[0]  = dv.impulse * na::cast(0.85);
+        for (i, ci) in island.contacts.iter().enumerate() {
+            let (_, next_imp) = self.cache_ids[*ci];
+            let imps          = self.cache.next_impulsions_at_mut(next_imp);
+
+            imps[0] = system.restitution[i].impulse * na::cast(0.85);
 
             for j in range(0u, na::dim::<LV>() - 1) {
-                let fc = &self.friction_constraints[i * (na::dim::<LV>() - 1) + j];
+                let fc = &system.friction[i * (na::dim::<LV>() - 1) + j];
                 imps[1 + j] = fc.impulse * na::cast(0.85);
             }
         }
 
-        let offset = self.cache.reserved_impulse_offset();
-        for (i, (_, kv)) in self.cache.hash_mut().mut_iter().enumerate() {
-            *kv = (kv.first(), offset + i * na::dim::<LV>());
+        if system.num_pseudo_iter != 0 {
+            move_bodies(dt, island.bodies, system.pseudo_MJLambda);
         }
+    }
 
-        /*
-         * first order resolution
-         */
-        let needs_correction = !self.correction.corr_mode.pos_corr_factor().is_zero() &&
-            constraints.iter().any(|constraint| {
-            match *constraint {
-                RBRB(_, _, ref c) => c.depth >= self.correction.corr_mode.min_depth_for_pos_corr(),
-                _ => false // no first order resolution for joints
+    /// Reinitializes the constraints of an island for the first order resolution, if it needs
+    /// one.
+    fn fill_position_equations(&mut self,
+                               dt:          N,
+                               constraints: &[Constraint<N, LV, AV, M, II>],
+                               island:      &mut Island<N, LV, AV, M, II>) {
+        let correction = &self.correction;
+
+        island.needs_correction = !correction.corr_mode.pos_corr_factor().is_zero() &&
+            island.contacts.iter().any(|ci| {
+            match constraints[*ci] {
+                RBRB(_, _, ref c) => c.depth >= correction.corr_mode.min_depth_for_pos_corr(),
+                _ => false // joints are corrected by `project_joint_positions`
             }
         });
 
-        if needs_correction {
-            self.resize_buffers(num_restitution_equations, num_friction_equations);
+        let system = &mut island.system;
+
+        if island.needs_correction {
+            system.resize(island.contacts.len(), 0);
+            system.lcp_solver      = self.island_lcp_solver(island.contacts.len());
+            system.num_iter        = self.num_first_order_iter;
+            system.tolerance       = na::zero();
+            system.omega           = self.omega.clone();
+            system.ordering        = self.ordering.clone();
+            system.manifolds       = self.solve_manifolds;
+            system.is_lambda_zero  = true;
+            system.num_pseudo_iter = 0;
 
-            for (i, (_, &(ci, _))) in self.cache.hash().iter().enumerate() {
-                match constraints[ci] {
+            for (i, ci) in island.contacts.iter().enumerate() {
+                match constraints[*ci] {
                     RBRB(_, _, ref c) => {
                         contact_equation::reinit_to_first_order_equation(
                             dt.clone(),
                             c,
-                            &mut self.restitution_constraints[i],
-                            &self.correction);
+                            &mut system.restitution[i],
+                            correction);
                     },
                     _ => { }
                 }
             }
+        }
+        else {
+            system.num_iter        = 0;
+            system.num_pseudo_iter = 0;
+        }
+    }
 
-            // FIXME: parametrize by the resolution algorithm?
-            pgs::projected_gauss_seidel_solve(
-                self.restitution_constraints,
-                [],
-                self.MJLambda,
-                bodies.len(),
-                self.num_first_order_iter,
-                true);
+    /// Moves the bodies of an island with the pseudo-velocities computed by its first order
+    /// resolution. The bodies velocities are left untouched.
+    fn apply_position_corrections(&self, dt: N, island: &Island<N, LV, AV, M, II>) {
+        if island.needs_correction {
+            move_bodies(dt, island.bodies, island.system.MJLambda);
+        }
+    }
 
-            for b in bodies.iter() {
-                let rb = b.to_mut_rigid_body_or_fail();
-                let i  = rb.index();
+    /// Moves the bodies attached to the joints of an island to reduce their positional errors,
+    /// with non-linear Gauss-Seidel sweeps. The bodies velocities are left untouched.
+    fn project_joint_positions(&self,
+                               constraints: &[Constraint<N, LV, AV, M, II>],
+                               island:      &Island<N, LV, AV, M, II>) {
+        for _ in range(0u, self.num_joint_pos_iter) {
+            for i in island.joints.iter() {
+                match constraints[*i] {
+                    // soft joints are allowed to stretch
+                    BallInSocket(bis) => if bis.softness().is_none() {
+                        ball_in_socket_equation::project_position(bis, &self.joint_pos_corr)
+                    },
+                    Fixed(f) => if f.softness().is_none() {
+                        fixed_equation::project_position(f, &self.joint_pos_corr)
+                    },
+                    Distance(d) => if d.softness().is_none() {
+                        distance_equation::project_position(d, &self.joint_pos_corr)
+                    },
+                    GenericJoint(g) => generic_joint_equation::project_position(g, &self.joint_pos_corr),
+                    ConeTwist(ct) => cone_twist_equation::project_position(ct, &self.joint_pos_corr),
+                    // gears and pulleys only couple velocities
+                    Gear(_) | Pulley(_) => { },
+                    RBRB(_, _, _) => { }
+                }
+            }
+        }
+    }
 
-                let translation = self.MJLambda[i].lv * dt;
-                let rotation    = self.MJLambda[i].av * dt;
+    /// The algorithm used to solve a system of `num_equations` equations.
+    fn island_lcp_solver(&self, num_equations: uint) -> LCPSolver {
+        match self.lcp_solver {
+            Dantzig if num_equations > self.max_direct_size => ProjectedGaussSeidel,
+            ref s => s.clone()
+        }
+    }
 
-                let center = &rb.center_of_mass().clone();
+    /// Solves the systems of every island, distributing them over `self.num_threads` tasks.
+    ///
+    /// Returns the largest residual of all the islands.
+    fn solve_systems(&mut self) -> N {
+        let num_threads = self.num_threads;
+        let num_islands = self.num_islands;
+        let mut cost    = 0;
 
-                let mut delta: M = na::one();
-                delta.append_rotation_wrt_point(&rotation, center);
-                delta.append_translation(&translation);
+        for island in self.islands.slice_to(num_islands).iter() {
+            cost = cost + system_cost(&island.system);
+        }
 
-                rb.append_transformation(&delta);
+        if num_threads == 1 || num_islands <= 1 || cost < self.min_parallel_cost {
+            for island in self.islands.mut_slice_to(num_islands).mut_iter() {
+                island.system.solve()
             }
         }
+        else {
+            let mut systems = ~[];
+
+            for island in self.islands.mut_slice_to(num_islands).mut_iter() {
+                systems.push(util::replace(&mut island.system, IslandSystem::new()));
+            }
+
+            let systems = solve_in_parallel(&self.workers, systems, cost);
+
+            for (island, system) in self.islands.mut_iter().zip(systems.move_iter()) {
+                island.system = system
+            }
+        }
+
+        let mut residual: N = na::zero();
+
+        for island in self.islands.slice_to(num_islands).iter() {
+            self.num_iterations = self.num_iterations + island.system.num_iter_used;
+            residual            = residual.max(&island.system.residual);
+
+            if island.system.fell_back {
+                self.num_fallbacks = self.num_fallbacks + 1;
+            }
+        }
+
+        residual
     }
 }
 
@@ -243,125 +1025,95 @@ impl<N:  'static + Clone + NPhysicsScalar,
 Solver<N, Constraint<N, LV, AV, M, II>> for
 AccumulatedImpulseSolver<N, LV, AV, M, II, M2> {
     fn solve(&mut self, dt: N, constraints: &[Constraint<N, LV, AV, M, II>]) {
-        // FIXME: bodies index assignment is very ugly
-        let mut bodies = ~[];
+        self.num_contacts   = 0;
+        self.num_joints     = 0;
+        self.num_islands    = 0;
+        self.num_iterations = 0;
+        self.num_fallbacks  = 0;
+        self.residual       = na::zero();
 
         if constraints.len() != 0 {
             /*
              * Associate the constraints with the cached impulse.
              */
-            for (i, cstr) in constraints.iter().enumerate() {
+            self.cache_ids.clear();
+
+            for cstr in constraints.iter() {
                 match *cstr {
                     RBRB(a, b, ref c) => {
-                        self.cache.insert(i,
-                                          ptr::to_mut_unsafe_ptr(a) as uint,
-                                          ptr::to_mut_unsafe_ptr(b) as uint,
-                                          (c.world1 + c.world2) / na::cast(2.0));
+                        let ids = self.cache.insert(ptr::to_mut_unsafe_ptr(a) as uint,
+                                                    ptr::to_mut_unsafe_ptr(b) as uint,
+                                                    (c.world1 + c.world2) / na::cast(2.0));
+
+                        self.cache_ids.push(ids);
                     },
                     BallInSocket(_) => {
                         // XXX: cache for ball in socket?
+                        self.cache_ids.push((0, 0));
                     },
                     Fixed(_) => {
                         // XXX: cache for fixed?
+                        self.cache_ids.push((0, 0));
+                    },
+                    Distance(_) => {
+                        // XXX: cache for distance?
+                        self.cache_ids.push((0, 0));
+                    },
+                    Pulley(_) => {
+                        // XXX: cache for pulley?
+                        self.cache_ids.push((0, 0));
+                    },
+                    Gear(_) => {
+                        // XXX: cache for gear?
+                        self.cache_ids.push((0, 0));
+                    },
+                    GenericJoint(_) => {
+                        // XXX: cache for generic joint?
+                        self.cache_ids.push((0, 0));
+                    },
+                    ConeTwist(_) => {
+                        // XXX: cache for cone twist?
+                        self.cache_ids.push((0, 0));
                     }
                 }
             }
 
             /*
-             * Assign an index to each body.
+             * Split the system into independent islands.
              */
-            // This is a two-passes assignation of index to the rigid bodies.
-            // This is not very good, but is the only way to do that without having a separate list
-            // of all rigid bodies.
-            for c in constraints.iter() {
-                match *c {
-                    RBRB(a, b, _) => {
-                        a.set_index(-2);
-                        b.set_index(-2)
-                    },
-                    BallInSocket(bis) => {
-                        match bis.anchor1().body {
-                            Some(b) => b.set_index(-2),
-                            None    => { }
-                        };
-
-                        match bis.anchor2().body {
-                            Some(b) => b.set_index(-2),
-                            None    => { }
-                        }
-                    }
-                    Fixed(f) => { // FIXME: code duplication from BallInSocket
-                        match f.anchor1().body {
-                            Some(b) => b.set_index(-2),
-                            None    => { }
-                        };
-
-                        match f.anchor2().body {
-                            Some(b) => b.set_index(-2),
-                            None    => { }
-                        }
-                    }
-                }
+            self.assign_body_indices(constraints);
+            self.build_islands(constraints);
+
+            /*
+             * Solve each island separately.
+             * The islands are moved out to avoid borrowing `self` twice.
+             */
+            for i in range(0u, self.num_islands) {
+                let mut island = util::replace(&mut self.islands[i], Island::new());
+
+                self.fill_velocity_equations(dt.clone(), constraints, &mut island);
+
+                self.islands[i] = island;
             }
 
-            let mut id = 0;
-
-            fn set_body_index<N:  Clone + NPhysicsScalar,
-                              LV: Clone + NPhysicsDirection<N, AV>,
-                              AV: Clone + NPhysicsOrientation<N>,
-                              M:  NPhysicsTransform<LV, AV>,
-                              II: Clone + NPhysicsInertia<N, LV, AV, M>>(
-                              a:      @mut Body<N, LV, AV, M, II>,
-                              bodies: &mut ~[@mut Body<N, LV, AV, M, II>],
-                              id:     &mut int) {
-                if a.index() == -2 {
-                    if a.can_move() {
-                        a.set_index(*id);
-                        bodies.push(a);
-                        *id = *id + 1;
-                    }
-                    else {
-                        a.set_index(-1)
-                    }
-                }
+            self.residual = self.solve_systems();
+
+            for i in range(0u, self.num_islands) {
+                let mut island = util::replace(&mut self.islands[i], Island::new());
+
+                self.apply_velocities(dt.clone(), &island);
+                self.fill_position_equations(dt.clone(), constraints, &mut island);
+
+                self.islands[i] = island;
             }
 
-            // FIXME: avoid allocation
-            let mut joints = ~[];
-            for (i, c) in constraints.iter().enumerate() {
-                match *c {
-                    RBRB(a, b, _) => {
-                        set_body_index(a, &mut bodies, &mut id);
-                        set_body_index(b, &mut bodies, &mut id);
-                    },
-                    BallInSocket(bis) => {
-                        joints.push(i);
-                        match bis.anchor1().body {
-                            Some(b) => set_body_index(b, &mut bodies, &mut id),
-                            None => { }
-                        }
-
-                        match bis.anchor2().body {
-                            Some(b) => set_body_index(b, &mut bodies, &mut id),
-                            None => { }
-                        }
-                    },
-                    Fixed(f) => { // FIXME: code duplication from BallInSocket
-                        joints.push(i);
-                        match f.anchor1().body {
-                            Some(b) => set_body_index(b, &mut bodies, &mut id),
-                            None => { }
-                        }
-
-                        match f.anchor2().body {
-                            Some(b) => set_body_index(b, &mut bodies, &mut id),
-                            None => { }
-                        }
-                    }
-                }
+            self.solve_systems();
+
+            for island in self.islands.slice_to(self.num_islands).iter() {
+                self.apply_position_corrections(dt.clone(), island);
+                self.project_joint_positions(constraints, island);
             }
 
-            self.do_solve(dt.clone(), constraints, joints, bodies);
             self.cache.swap();
         }
     }
@@ -370,6 +1122,98 @@ AccumulatedImpulseSolver<N, LV, AV, M, II, M2> {
     fn priority(&self) -> f64 { 0.0 }
 }
 
+/// The global indices of the bodies affected by a constraint (-1 for bodies which cannot move).
+fn body_indices<N:  Clone + NPhysicsScalar,
+                LV: Clone + NPhysicsDirection<N, AV>,
+                AV: Clone + NPhysicsOrientation<N>,
+                M:  Clone + NPhysicsTransform<LV, AV>,
+                II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                c: &Constraint<N, LV, AV, M, II>)
+                -> (int, int) {
+    fn anchor_index<N:  Clone + NPhysicsScalar,
+                    LV: Clone + NPhysicsDirection<N, AV>,
+                    AV: Clone + NPhysicsOrientation<N>,
+                    M:  NPhysicsTransform<LV, AV>,
+                    II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                    b: Option<@mut Body<N, LV, AV, M, II>>)
+                    -> int {
+        match b {
+            Some(b) => b.index(),
+            None    => -1
+        }
+    }
+
+    let (b1, b2) = c.anchors();
+
+    (anchor_index(b1), anchor_index(b2))
+}
+
+/// Sorts contacts from the lowest to the highest along the `up` direction.
+fn sort_contacts_by_height<N:  Clone + NPhysicsScalar,
+                           LV: Clone + NPhysicsDirection<N, AV>,
+                           AV: Clone + NPhysicsOrientation<N>,
+                           M:  NPhysicsTransform<LV, AV>,
+                           II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                           constraints: &[Constraint<N, LV, AV, M, II>],
+                           contacts:    &mut [uint],
+                           up:          &LV) {
+    fn height<N:  Clone + NPhysicsScalar,
+              LV: Clone + NPhysicsDirection<N, AV>,
+              AV: Clone + NPhysicsOrientation<N>,
+              M:  NPhysicsTransform<LV, AV>,
+              II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+              c:  &Constraint<N, LV, AV, M, II>,
+              up: &LV)
+              -> N {
+        match *c {
+            RBRB(_, _, ref c) => na::dot(&(c.world1 + c.world2), up),
+            _                 => na::zero()
+        }
+    }
+
+    contacts.sort_by(|a, b| {
+        let ha = height(&constraints[*a], up);
+        let hb = height(&constraints[*b], up);
+
+        if ha < hb {
+            Less
+        }
+        else if ha > hb {
+            Greater
+        }
+        else {
+            Equal
+        }
+    })
+}
+
+/// Moves the bodies of an island with velocities applied during `dt`, without modifying their
+/// velocities.
+fn move_bodies<N:  Clone + NPhysicsScalar,
+               LV: Clone + NPhysicsDirection<N, AV>,
+               AV: Clone + NPhysicsOrientation<N>,
+               M:  Clone + NPhysicsTransform<LV, AV>,
+               II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+               dt:         N,
+               bodies:     &[@mut Body<N, LV, AV, M, II>],
+               velocities: &[Velocities<LV, AV>]) {
+    for b in bodies.iter() {
+        let rb = b.to_mut_rigid_body_or_fail();
+        let i  = rb.index();
+
+        let translation = velocities[i].lv * dt;
+        let rotation    = velocities[i].av * dt;
+
+        let center = &rb.center_of_mass().clone();
+
+        let mut delta: M = na::one();
+        delta.append_rotation_wrt_point(&rotation, center);
+        delta.append_translation(&translation);
+
+        rb.append_transformation(&delta);
+    }
+}
+
 fn resize_buffer<A: Clone>(buff: &mut ~[A], size: uint, val: A) {
     if buff.len() < size {
         buff.grow_set(size - 1, &val, val.clone());
@@ -378,3 +1222,184 @@ fn resize_buffer<A: Clone>(buff: &mut ~[A], size: uint, val: A) {
         buff.truncate(size)
     }
 }
+
+/// Solves a set of systems on the calling task and the workers, and returns them in the same
+/// order.
+///
+/// The systems are split into contiguous groups with roughly the same cost, `total_cost` being the
+/// sum of the costs of all the systems.
+fn solve_in_parallel<LV: 'static + Send + Vec<N> + Zero + Clone,
+                     AV: 'static + Send + Vec<N> + Zero + Clone,
+                     N:  'static + Send + Num + Orderable + Signed + Clone>(
+                     workers:    &WorkerPool<IslandSystem<LV, AV, N>>,
+                     systems:    ~[IslandSystem<LV, AV, N>],
+                     total_cost: uint)
+                     -> ~[IslandSystem<LV, AV, N>] {
+    let num_groups = workers.num_workers() + 1;
+    let group_cost = total_cost / num_groups + 1;
+    let mut groups = ~[];
+    let mut group  = ~[];
+    let mut cost   = 0;
+
+    for s in systems.move_iter() {
+        cost = cost + system_cost(&s);
+        group.push(s);
+
+        // the last group takes whatever remains
+        if cost >= group_cost && groups.len() + 1 < num_groups {
+            groups.push(util::replace(&mut group, ~[]));
+            cost = 0;
+        }
+    }
+
+    if !group.is_empty() {
+        groups.push(group);
+    }
+
+    workers.run(groups)
+}
+
+fn solve_system<LV: Vec<N> + Zero + Clone,
+                AV: Vec<N> + Zero + Clone,
+                N:  Num + Orderable + Signed + Clone>(
+                system: &mut IslandSystem<LV, AV, N>) {
+    system.solve()
+}
+
+#[inline]
+fn system_cost<LV, AV, N>(s: &IslandSystem<LV, AV, N>) -> uint {
+    (s.restitution.len() + s.friction.len() + 1) * s.num_iter + s.pseudo.len() * s.num_pseudo_iter
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Translation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, Body3d, ContactSolver3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Static, Dynamic, RB};
+    use resolution::constraint::contact_equation::{CorrectionMode, Velocity, SplitImpulse};
+    use super::AccumulatedImpulseSolver;
+
+    fn new_world() -> BodyWorld3d<f64> {
+        let mut world = BodyWorld::new();
+
+        world.set_gravity(Vec3::new(0.0f64, -9.81, 0.0));
+
+        let ground = RigidBody::new(Geom::new_plane(Vec3::new(0.0f64, 1.0, 0.0)), 0.0, Static,
+                                    0.3, 0.6);
+
+        world.add_body(@mut RB(ground));
+
+        world
+    }
+
+    // adds a ball of radius 0.5 slightly penetrating whatever is below
+    fn add_ball(world: &mut BodyWorld3d<f64>, x: f64, y: f64) {
+        let mut rb = RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6);
+
+        rb.append_translation(&Vec3::new(x, y, 0.0));
+
+        world.add_body(@mut RB(rb))
+    }
+
+    #[test]
+    fn test_bodies_touching_only_static_bodies_are_separate_islands() {
+        let mut world = new_world();
+
+        add_ball(&mut world, -5.0, 0.45);
+        add_ball(&mut world, 5.0, 0.45);
+        // stacked on the first ball
+        add_ball(&mut world, -5.0, 1.4);
+
+        world.step(0.016);
+
+        let solver = world.constraints_solver();
+
+        assert!(solver.num_contacts() == 3);
+        assert!(solver.num_islands() == 2);
+    }
+
+    // the positions of the rigid bodies of `world`
+    fn positions(world: &BodyWorld3d<f64>) -> ~[Vec3<f64>] {
+        world.world().objects().iter().map(|o| {
+            match **o {
+                RB(ref rb) => rb.transform_ref().translation(),
+                _          => fail!("Unexpected body.")
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn test_parallel_resolution_is_deterministic() {
+        let mut sequential = new_world();
+        let mut parallel   = new_world();
+
+        parallel.constraints_solver().set_num_threads(3);
+        parallel.constraints_solver().set_min_parallel_cost(0);
+
+        // six stacks of two balls
+        for i in range(0u, 6) {
+            add_ball(&mut sequential, i as f64 * 3.0, 0.45);
+            add_ball(&mut sequential, i as f64 * 3.0, 1.4);
+            add_ball(&mut parallel, i as f64 * 3.0, 0.45);
+            add_ball(&mut parallel, i as f64 * 3.0, 1.4);
+        }
+
+        for _ in range(0u, 20) {
+            sequential.step(0.016);
+            parallel.step(0.016);
+        }
+
+        assert!(parallel.constraints_solver().num_islands() == 6);
+        assert!(positions(&sequential) == positions(&parallel));
+    }
+
+    // a ball penetrating the ground by 0.2, without gravity, whose contact is solved with `mode`
+    fn penetrating_ball(mode: CorrectionMode<f64>) -> (BodyWorld3d<f64>, @mut Body3d<f64>) {
+        let mut world = new_world();
+        let solver: @mut ContactSolver3d<f64> =
+            @mut AccumulatedImpulseSolver::new(0.1, mode, 0.4, 1.0, 10, 10);
+
+        world.set_gravity(na::zero());
+        world.world_mut().replace_solver("solver", solver);
+
+        let mut rb = RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6);
+
+        rb.append_translation(&Vec3::new(0.0, 0.3, 0.0));
+
+        let ball = @mut RB(rb);
+
+        world.add_body(ball);
+
+        (world, ball)
+    }
+
+    fn height_and_speed(ball: @mut Body3d<f64>) -> (f64, f64) {
+        let rb = ball.to_rigid_body_or_fail();
+
+        (rb.transform_ref().translation().y, rb.lin_vel().y)
+    }
+
+    #[test]
+    fn test_split_impulse_does_not_add_kinetic_energy() {
+        let (mut world, ball) = penetrating_ball(SplitImpulse(0.5, 0.01));
+
+        world.step(0.016);
+
+        let (y, vy) = height_and_speed(ball);
+
+        assert!(y > 0.3);
+        assert!(vy.abs() < 1.0e-10);
+
+        // by contrast, the velocity correction makes the ball pop out
+        let (mut world, ball) = penetrating_ball(Velocity(0.5));
+
+        world.step(0.016);
+
+        let (_, vy) = height_and_speed(ball);
+
+        assert!(vy > 1.0);
+    }
+}
@@ -1,14 +1,24 @@
-use nalgebra::na::{CrossMatrix, Row};
+use std::num::Zero;
+use nalgebra::na::{CrossMatrix, Row, Transformation, RotationWithTranslation, Rotate, Dim};
 use nalgebra::na;
-use object::Body;
+use object::{Body, RigidBody, RB, SB, MB};
 use detection::joint::anchor::Anchor;
 use detection::joint::ball_in_socket::BallInSocket;
 use resolution::constraint::velocity_constraint::VelocityConstraint;
 use resolution::constraint::contact_equation::CorrectionParameters;
 use resolution::constraint::contact_equation;
+use resolution::constraint::cone_twist_equation;
+use resolution::constraint::softness::Softness;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
+/// The number of velocity constraints of a ball-in-socket joint: one per translation axis, plus
+/// one if its rotation is limited.
+#[inline]
+pub fn num_equations<N, LV: Dim, AV, M, II>(joint: &BallInSocket<N, LV, AV, M, II>) -> uint {
+    na::dim::<LV>() + if joint.angular_limit().is_some() { 1 } else { 0 }
+}
+
 pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
                                   LV: Clone + NPhysicsDirection<N, AV> + CrossMatrix<CM>,
                                   AV: Clone + NPhysicsOrientation<N>,
@@ -26,7 +36,113 @@ pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
         joint.anchor1(),
         joint.anchor2(),
         constraints,
-        correction);
+        correction,
+        joint.softness());
+
+    match *joint.angular_limit() {
+        Some(ref limit) => {
+            let constraint = &mut constraints[na::dim::<LV>()];
+
+            match limit_axis_and_angle(joint.anchor1(), joint.anchor2(), &limit.axis1, &limit.axis2) {
+                // the angle is never negative, so only its upper limit can be the nearest one
+                Some((axis, angle)) => cone_twist_equation::fill_angular_limit(
+                    dt, axis, angle, -limit.max_angle, limit.max_angle.clone(), joint.anchor1(),
+                    joint.anchor2(), constraint, correction),
+                None => *constraint = VelocityConstraint::new() // same axes: nothing to limit
+            }
+        },
+        None => { }
+    }
+}
+
+/// Moves the bodies attached to a ball-in-socket joint to reduce its positional error.
+pub fn project_position<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV> + CrossMatrix<CM>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  Clone + NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                        CM: Row<AV>>(
+                        joint:  &BallInSocket<N, LV, AV, M, II>,
+                        factor: &N) {
+    project_relative_linear_position(
+        || (joint.anchor1_pos(), joint.anchor2_pos()),
+        joint.anchor1(),
+        joint.anchor2(),
+        factor);
+
+    match *joint.angular_limit() {
+        Some(ref limit) => {
+            match limit_axis_and_angle(joint.anchor1(), joint.anchor2(), &limit.axis1, &limit.axis2) {
+                Some((axis, angle)) => if angle > limit.max_angle {
+                    apply_position_impulse(na::zero(), axis.clone(), -axis, joint.anchor1(),
+                                           joint.anchor2(), limit.max_angle - angle, factor)
+                },
+                None => { }
+            }
+        },
+        None => { }
+    }
+}
+
+/// The rotation axis bringing the world-space image of `local1` to the one of `local2`, and the
+/// angle between them.
+///
+/// Returns `None` if the two axes point toward the same direction. If they point toward opposite
+/// directions, any axis orthogonal to them is returned.
+fn limit_axis_and_angle<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                        anchor1: &Anchor<N, LV, AV, M, II, LV>,
+                        anchor2: &Anchor<N, LV, AV, M, II, LV>,
+                        local1:  &LV,
+                        local2:  &LV)
+                        -> Option<(AV, N)> {
+    let axis1 = na::normalize(&world_direction(anchor1, local1));
+    let axis2 = na::normalize(&world_direction(anchor2, local2));
+    let rot   = na::cross(&axis1, &axis2);
+    let sin   = na::norm(&rot);
+    let _1: N = na::one();
+    let cos   = na::dot(&axis1, &axis2).clamp(&-_1, &_1);
+
+    if !sin.is_zero() {
+        Some((rot / sin, cos.acos()))
+    }
+    else if cos < na::zero() {
+        // opposite axes: a half-turn around any axis orthogonal to `axis1` aligns them
+        let mut res = None;
+
+        na::orthonormal_subspace_basis(&axis1, |ortho| {
+            res = Some((na::cross(&axis1, &ortho), cos.acos()));
+            false
+        });
+
+        res
+    }
+    else {
+        None
+    }
+}
+
+fn world_direction<N:  Clone + NPhysicsScalar,
+                   LV: Clone + NPhysicsDirection<N, AV>,
+                   AV: Clone + NPhysicsOrientation<N>,
+                   M:  NPhysicsTransform<LV, AV>,
+                   II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                   anchor: &Anchor<N, LV, AV, M, II, LV>,
+                   local:  &LV)
+                   -> LV {
+    match anchor.body {
+        Some(b) => {
+            match *b {
+                RB(ref rb) => rb.transform_ref().rotate(local),
+                SB(_)      => fail!("Not yet implemented."),
+                MB(ref mb) => mb.rigid_body().transform_ref().rotate(local)
+            }
+        },
+        None => local.clone()
+    }
 }
 
 // FIXME: move this on another file. Something like "joint_equation_helper.rs"
@@ -43,8 +159,14 @@ pub fn cancel_relative_linear_motion<N:  Clone + NPhysicsScalar,
                                      anchor1:     &Anchor<N, LV, AV, M, II, P>,
                                      anchor2:     &Anchor<N, LV, AV, M, II, P>,
                                      constraints: &mut [VelocityConstraint<LV, AV, N>],
-                                     correction:  &CorrectionParameters<N>) {
-    let error      = (global2 - *global1) * correction.joint_corr;
+                                     correction:  &CorrectionParameters<N>,
+                                     softness:    &Option<Softness<N>>) {
+    let erp = match *softness {
+        Some(ref s) => s.erp(&dt),
+        None        => correction.joint_corr.clone()
+    };
+
+    let error      = (global2 - *global1) * erp;
     let rot_axis1  = na::cross_matrix(&(global1 - anchor1.center_of_mass()));
     let rot_axis2  = na::cross_matrix(&(global2 - anchor2.center_of_mass()));
 
@@ -79,6 +201,11 @@ pub fn cancel_relative_linear_motion<N:  Clone + NPhysicsScalar,
             constraint
         );
 
+        match *softness {
+            Some(ref s) => s.soften(&dt, constraint),
+            None        => constraint.cfm = na::zero()
+        }
+
         let _M: N = Bounded::max_value();
         constraint.lobound   = -_M;
         constraint.hibound   = _M;
@@ -87,6 +214,108 @@ pub fn cancel_relative_linear_motion<N:  Clone + NPhysicsScalar,
     }
 }
 
+/// Position-level counterpart of `cancel_relative_linear_motion`.
+///
+/// Performs one non-linear Gauss-Seidel step per axis: the anchors world positions are
+/// recomputed by `anchors` before each step, and the bodies are directly moved to remove the
+/// fraction `factor` of the distance between them.
+pub fn project_relative_linear_position<N:  Clone + NPhysicsScalar,
+                                        LV: Clone + NPhysicsDirection<N, AV> + CrossMatrix<CM>,
+                                        AV: Clone + NPhysicsOrientation<N>,
+                                        M:  Clone + NPhysicsTransform<LV, AV>,
+                                        II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                                        CM: Row<AV>,
+                                        P>(
+                                        anchors: || -> (LV, LV),
+                                        anchor1: &Anchor<N, LV, AV, M, II, P>,
+                                        anchor2: &Anchor<N, LV, AV, M, II, P>,
+                                        factor:  &N) {
+    for i in range(0u, na::dim::<LV>()) {
+        let (global1, global2) = anchors();
+        let mut lin_axis: LV   = na::zero();
+
+        lin_axis.set(i, na::one());
+
+        let rot_axis1 = na::cross_matrix(&(global1 - anchor1.center_of_mass())).row(i);
+        let rot_axis2 = -na::cross_matrix(&(global2 - anchor2.center_of_mass())).row(i);
+        let error     = (global2 - global1).at(i);
+
+        apply_position_impulse(lin_axis, rot_axis1, rot_axis2, anchor1, anchor2, error, factor);
+    }
+}
+
+/// Moves the bodies attached to two anchors along a constraint direction, to remove the fraction
+/// `factor` of the positional `error` along it.
+pub fn apply_position_impulse<N:  Clone + NPhysicsScalar,
+                              LV: Clone + NPhysicsDirection<N, AV>,
+                              AV: Clone + NPhysicsOrientation<N>,
+                              M:  Clone + NPhysicsTransform<LV, AV>,
+                              II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                              P>(
+                              normal:    LV,
+                              rot_axis1: AV,
+                              rot_axis2: AV,
+                              anchor1:   &Anchor<N, LV, AV, M, II, P>,
+                              anchor2:   &Anchor<N, LV, AV, M, II, P>,
+                              error:     N,
+                              factor:    &N) {
+    let mut constraint = VelocityConstraint::new();
+
+    let opt_b1 = write_anchor_id(anchor1, &mut constraint.id1);
+    let opt_b2 = write_anchor_id(anchor2, &mut constraint.id2);
+
+    if opt_b1.is_none() && opt_b2.is_none() {
+        return
+    }
+
+    {
+        let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+        let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+        contact_equation::fill_constraint_geometry(
+            normal,
+            rot_axis1,
+            rot_axis2,
+            opt_rb1,
+            opt_rb2,
+            &mut constraint
+        );
+    }
+
+    let impulse = -error * *factor * constraint.inv_projected_mass;
+
+    match opt_b1 {
+        Some(b) => displace(b.to_mut_rigid_body_or_fail(),
+                            -constraint.weighted_normal1 * impulse,
+                            constraint.weighted_rot_axis1 * impulse),
+        None    => { }
+    }
+
+    match opt_b2 {
+        Some(b) => displace(b.to_mut_rigid_body_or_fail(),
+                            constraint.weighted_normal2 * impulse,
+                            constraint.weighted_rot_axis2 * impulse),
+        None    => { }
+    }
+}
+
+fn displace<N:  Clone + NPhysicsScalar,
+            LV: Clone + NPhysicsDirection<N, AV>,
+            AV: Clone + NPhysicsOrientation<N>,
+            M:  Clone + NPhysicsTransform<LV, AV>,
+            II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+            rb:          &mut RigidBody<N, LV, AV, M, II>,
+            translation: LV,
+            rotation:    AV) {
+    let center = rb.center_of_mass().clone();
+
+    let mut delta: M = na::one();
+    delta.append_rotation_wrt_point(&rotation, &center);
+    delta.append_translation(&translation);
+
+    rb.append_transformation(&delta);
+}
+
 #[inline]
 pub fn write_anchor_id<'r,
                        N:  Clone + NPhysicsScalar,
@@ -114,3 +343,89 @@ pub fn write_anchor_id<'r,
         None => { *id = -1; None }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Rotate};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::BodyWorld3d;
+    use world::BodyWorld;
+    use object::{RigidBody, Dynamic, RB};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::ball_in_socket::{BallInSocket, AngularLimit};
+
+    // steps a ball attached, one unit away, to a fixed point; returns the distance between the
+    // anchors and the velocity of the ball
+    fn step_broken_joint(num_joint_pos_iter: uint) -> (f64, Vec3<f64>) {
+        let mut world: BodyWorld3d<f64> = BodyWorld::new();
+
+        world.constraints_solver().set_num_joint_pos_iter(num_joint_pos_iter);
+
+        let ball = @mut RB(RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6));
+
+        world.add_body(ball);
+
+        let joint = @mut BallInSocket::new(Anchor::new(Some(ball), Vec3::new(0.0f64, 0.0, 0.0)),
+                                           Anchor::new(None, Vec3::new(0.0f64, 1.0, 0.0)));
+
+        world.add_ball_in_socket(joint);
+        world.step(0.016);
+
+        let error = na::norm(&(joint.anchor1_pos() - joint.anchor2_pos()));
+
+        (error, ball.to_rigid_body_or_fail().lin_vel())
+    }
+
+    #[test]
+    fn test_joint_position_projection_reduces_the_error_only() {
+        let (error, vel)          = step_broken_joint(0);
+        let (projected, proj_vel)  = step_broken_joint(10);
+
+        // the velocity resolution alone does not move the ball during this step
+        assert!((error - 1.0).abs() < 1.0e-10);
+        assert!(projected < 0.5);
+        assert!(na::norm(&(vel - proj_vel)) < 1.0e-10);
+    }
+
+    // spins a ball attached at its center to the world, with the angle between the world `y` axis
+    // and its own `axis` limited to 0.3 radians; returns the final angle
+    fn spin_limited_ball(axis: Vec3<f64>, ang_vel: Vec3<f64>) -> f64 {
+        let mut world: BodyWorld3d<f64> = BodyWorld::new();
+
+        let ball = @mut RB(RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6));
+
+        ball.to_mut_rigid_body_or_fail().set_ang_vel(ang_vel);
+        world.add_body(ball);
+
+        let joint = @mut BallInSocket::new(Anchor::new(None, na::zero()),
+                                           Anchor::new(Some(ball), na::zero()));
+
+        joint.set_angular_limit(Some(AngularLimit::new(Vec3::new(0.0f64, 1.0, 0.0), axis.clone(),
+                                                       0.3)));
+        world.add_ball_in_socket(joint);
+
+        for _ in range(0u, 300) {
+            world.step(0.016)
+        }
+
+        let world_axis = ball.to_rigid_body_or_fail().transform_ref().rotate(&axis);
+
+        na::dot(&world_axis, &Vec3::new(0.0, 1.0, 0.0)).acos()
+    }
+
+    #[test]
+    fn test_angular_limit_stops_the_rotation() {
+        // without limit, the ball would turn by 4.8 radians
+        let angle = spin_limited_ball(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+
+        assert!(angle < 0.35);
+    }
+
+    #[test]
+    fn test_angular_limit_with_opposite_axes() {
+        let angle = spin_limited_ball(Vec3::new(0.0, -1.0, 0.0), na::zero());
+
+        assert!(angle < 0.35);
+    }
+}
@@ -0,0 +1,58 @@
+use std::num::{Zero, Orderable, Signed};
+use nalgebra::na::Vec;
+use nalgebra::na;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::projected_gauss_seidel_solver::{Velocities, BlockWorkspace};
+use pgs = resolution::constraint::projected_gauss_seidel_solver;
+
+/// Solves the velocity constraints with a block Gauss-Seidel algorithm.
+///
+/// The consecutive restitution constraints which act on the same pair of bodies (typically, the
+/// normals of a contact manifold, or the rows of a joint) are solved together, exactly, as a
+/// small LCP with `direct_solver::solve_boxed_lcp` (see `BlockWorkspace::solve_block`). The other
+/// constraints, the friction constraints, and the blocks for which Dantzig's algorithm fails are
+/// solved one by one as with the projected Gauss-Seidel algorithm. The blocks and the buffers of
+/// the resolution are kept in `workspace`.
+///
+/// Returns the number of sweeps performed and the largest impulse change of the last one.
+pub fn block_gauss_seidel_solve<LV: Vec<N> + Zero + Clone,
+                                AV: Vec<N> + Zero + Clone,
+                                N:  Num + Orderable + Signed + Clone>(
+                                restitution:    &mut [VelocityConstraint<LV, AV, N>],
+                                friction:       &mut [VelocityConstraint<LV, AV, N>],
+                                result:         &mut [Velocities<LV, AV>],
+                                num_bodies:     uint,
+                                num_iterations: uint,
+                                tolerance:      &N,
+                                workspace:      &mut BlockWorkspace<LV, AV, N>,
+                                is_lambda_zero: bool)
+                                -> (uint, N) {
+    pgs::setup_warmstart(restitution, friction, result, num_bodies, is_lambda_zero);
+
+    workspace.find_blocks(restitution, num_bodies, false);
+
+    let mut residual: N = na::zero();
+    let mut num_iter    = 0;
+
+    while num_iter < num_iterations {
+        residual = na::zero();
+
+        for i in range(0u, workspace.num_blocks()) {
+            residual = residual.max(&workspace.solve_block(i, restitution, result, &na::one(), false));
+        }
+
+        for c in friction.mut_iter() {
+            if pgs::update_friction_bounds(c, restitution) {
+                residual = residual.max(&pgs::solve_velocity_constraint(c, result, &na::one()));
+            }
+        }
+
+        num_iter = num_iter + 1;
+
+        if residual < *tolerance {
+            break;
+        }
+    }
+
+    (num_iter, residual)
+}
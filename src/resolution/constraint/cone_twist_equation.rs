@@ -0,0 +1,330 @@
+use std::num::{Zero, Bounded};
+use nalgebra::na::{CrossMatrix, Row, Dim};
+use nalgebra::na;
+use detection::joint::anchor::Anchor;
+use detection::joint::cone_twist::ConeTwist;
+use resolution::constraint::ball_in_socket_equation;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::contact_equation::CorrectionParameters;
+use resolution::constraint::contact_equation;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// The number of velocity constraints of a cone-twist joint: one per translation axis, one for
+/// the twist, and one for the swing if the space has more than one rotation axis.
+#[inline]
+pub fn num_equations<LV: Dim, AV: Dim>() -> uint {
+    na::dim::<LV>() + if na::dim::<AV>() > 1 { 2 } else { 1 }
+}
+
+pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
+                                  LV: Clone + NPhysicsDirection<N, AV> + CrossMatrix<CM>,
+                                  AV: Clone + NPhysicsOrientation<N>,
+                                  M:  Clone + NPhysicsTransform<LV, AV>,
+                                  II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                                  CM: Row<AV>>(
+                                  dt:          N,
+                                  joint:       &ConeTwist<N, LV, AV, M, II>,
+                                  constraints: &mut [VelocityConstraint<LV, AV, N>],
+                                  correction:  &CorrectionParameters<N>) {
+    let ref1 = joint.anchor1_pos();
+    let ref2 = joint.anchor2_pos();
+
+    ball_in_socket_equation::cancel_relative_linear_motion(
+        dt.clone(),
+        &ref1.translation(),
+        &ref2.translation(),
+        joint.anchor1(),
+        joint.anchor2(),
+        constraints,
+        correction,
+        &None);
+
+    let (twist, twist_axis, swing, swing_axis) = swing_twist(&ref1, &ref2);
+    let angular = constraints.mut_slice_from(na::dim::<LV>());
+
+    fill_angular_limit(
+        dt.clone(),
+        twist_axis,
+        twist,
+        joint.min_twist(),
+        joint.max_twist(),
+        joint.anchor1(),
+        joint.anchor2(),
+        &mut angular[0],
+        correction);
+
+    if na::dim::<AV>() > 1 {
+        match swing_axis {
+            Some(axis) => {
+                // the swing is never negative, so only its upper limit can be the nearest one
+                fill_angular_limit(
+                    dt,
+                    axis,
+                    swing,
+                    -joint.swing_limit(),
+                    joint.swing_limit(),
+                    joint.anchor1(),
+                    joint.anchor2(),
+                    &mut angular[1],
+                    correction)
+            },
+            None => angular[1] = VelocityConstraint::new() // no swing: nothing to limit
+        }
+    }
+}
+
+/// Moves the bodies attached to a cone-twist joint to reduce its positional error, and to bring
+/// its swing and twist back within their limits.
+pub fn project_position<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV> + CrossMatrix<CM>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  Clone + NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                        CM: Row<AV>>(
+                        joint:  &ConeTwist<N, LV, AV, M, II>,
+                        factor: &N) {
+    ball_in_socket_equation::project_relative_linear_position(
+        || (joint.anchor1_pos().translation(), joint.anchor2_pos().translation()),
+        joint.anchor1(),
+        joint.anchor2(),
+        factor);
+
+    let (twist, twist_axis, _, _) = swing_twist(&joint.anchor1_pos(), &joint.anchor2_pos());
+
+    let twist_error =
+        if twist > joint.max_twist() {
+            Some(joint.max_twist() - twist)
+        }
+        else if twist < joint.min_twist() {
+            Some(joint.min_twist() - twist)
+        }
+        else {
+            None
+        };
+
+    match twist_error {
+        Some(error) => ball_in_socket_equation::apply_position_impulse(
+            na::zero(), twist_axis.clone(), -twist_axis, joint.anchor1(), joint.anchor2(),
+            error, factor),
+        None => { }
+    }
+
+    let (_, _, swing, swing_axis) = swing_twist(&joint.anchor1_pos(), &joint.anchor2_pos());
+
+    match swing_axis {
+        Some(axis) => if swing > joint.swing_limit() {
+            ball_in_socket_equation::apply_position_impulse(
+                na::zero(), axis.clone(), -axis, joint.anchor1(), joint.anchor2(),
+                joint.swing_limit() - swing, factor)
+        },
+        None => { }
+    }
+}
+
+/// Decomposes the rotation of `ref2` relative to `ref1` into a twist around the first axis of
+/// `ref1`, and a swing around an axis orthogonal to it.
+///
+/// Returns the twist angle, the world-space twist axis, the swing angle, and the world-space
+/// swing axis (`None` if there is no swing). The decomposition is done on the rotation vector
+/// of the relative rotation, which is exact for a pure twist or a pure swing and a good
+/// approximation otherwise.
+pub fn swing_twist<N:  Clone + NPhysicsScalar,
+                   LV: Clone + NPhysicsDirection<N, AV>,
+                   AV: Clone + NPhysicsOrientation<N>,
+                   M:  Clone + NPhysicsTransform<LV, AV>>(
+                   ref1: &M,
+                   ref2: &M)
+                   -> (N, AV, N, Option<AV>) {
+    let delta  = na::inv(ref1).expect("ref1 must be inversible.") * *ref2;
+    let rotvec = delta.rotation(); // expressed in the local frame of `ref1`
+
+    let mut twist_axis: AV = na::zero();
+    twist_axis.set(0, na::one());
+
+    let twist           = rotvec.at(0);
+    let mut swing_local = rotvec;
+    swing_local.set(0, na::zero());
+
+    let swing      = na::norm(&swing_local);
+    let swing_axis =
+        if swing.is_zero() {
+            None
+        }
+        else {
+            Some(local_to_world_axis(ref1, &(swing_local / swing)))
+        };
+
+    (twist, local_to_world_axis(ref1, &twist_axis), swing, swing_axis)
+}
+
+/// Expresses in world-space a unit rotation axis given in the local frame `frame`.
+pub fn local_to_world_axis<N:  Clone + NPhysicsScalar,
+                           LV: Clone + NPhysicsDirection<N, AV>,
+                           AV: Clone + NPhysicsOrientation<N>,
+                           M:  Clone + NPhysicsTransform<LV, AV>>(
+                           frame: &M,
+                           local: &AV)
+                           -> AV {
+    // the rotation of one radian around `local`, seen from the world
+    let mut rot: M = na::one();
+    rot.append_rotation(local);
+
+    let world = *frame * rot * na::inv(frame).expect("frame must be inversible.");
+
+    world.rotation()
+}
+
+/// Fills an angular velocity constraint keeping `angle` (measured around `axis`) between `min`
+/// and `max`.
+///
+/// If the angle is within its limits, the constraint only prevents the nearest one to be
+/// exceeded during the step.
+pub fn fill_angular_limit<N:  Clone + NPhysicsScalar,
+                          LV: Clone + NPhysicsDirection<N, AV>,
+                          AV: Clone + NPhysicsOrientation<N>,
+                          M:  Clone + NPhysicsTransform<LV, AV>,
+                          II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                          P>(
+                          dt:         N,
+                          axis:       AV,
+                          angle:      N,
+                          min:        N,
+                          max:        N,
+                          anchor1:    &Anchor<N, LV, AV, M, II, P>,
+                          anchor2:    &Anchor<N, LV, AV, M, II, P>,
+                          constraint: &mut VelocityConstraint<LV, AV, N>,
+                          correction: &CorrectionParameters<N>) {
+    let _M: N = Bounded::max_value();
+
+    // `violated` is false for the limits which are only enforced if they would be exceeded
+    // during the step.
+    let (error, lobound, hibound, violated) =
+        if min == max {
+            (angle - max, -_M, _M.clone(), true)
+        }
+        else if angle > max {
+            (angle - max, na::zero(), _M.clone(), true)
+        }
+        else if angle < min {
+            (angle - min, -_M, na::zero(), true)
+        }
+        else if angle - min < max - angle {
+            (angle - min, -_M, na::zero(), false)
+        }
+        else {
+            (angle - max, na::zero(), _M.clone(), false)
+        };
+
+    let opt_b1  = ball_in_socket_equation::write_anchor_id(anchor1, &mut constraint.id1);
+    let opt_b2  = ball_in_socket_equation::write_anchor_id(anchor2, &mut constraint.id2);
+    let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+    let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+    contact_equation::fill_constraint_geometry(
+        na::zero(),
+        axis.clone(),
+        -axis,
+        opt_rb1,
+        opt_rb2,
+        constraint
+    );
+
+    let ang_vel1 = match opt_rb1 { Some(rb) => rb.ang_vel(), None => na::zero() };
+    let ang_vel2 = match opt_rb2 { Some(rb) => rb.ang_vel(), None => na::zero() };
+
+    let erp = if violated { correction.joint_corr.clone() } else { na::one() };
+
+    constraint.cfm       = na::zero();
+    constraint.lobound   = lobound;
+    constraint.hibound   = hibound;
+    constraint.objective = na::dot(&(ang_vel2 - ang_vel1), &axis) + error * erp / dt;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Iso3, Rotation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, ConeTwist3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Dynamic, RB};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::cone_twist::ConeTwist;
+    use super::swing_twist;
+
+    fn rotated(frame: &Iso3<f64>, local_rotation: Vec3<f64>) -> Iso3<f64> {
+        let mut rot: Iso3<f64> = na::one();
+
+        rot.append_rotation(&local_rotation);
+
+        *frame * rot
+    }
+
+    // spins a ball attached at its center to the world, and returns the final twist and swing
+    fn spin(ang_vel: Vec3<f64>, swing_limit: f64, min_twist: f64, max_twist: f64) -> (f64, f64) {
+        let mut world: BodyWorld3d<f64> = BodyWorld::new();
+
+        let ball = @mut RB(RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6));
+
+        ball.to_mut_rigid_body_or_fail().set_ang_vel(ang_vel);
+        world.add_body(ball);
+
+        let joint: @mut ConeTwist3d<f64> =
+            @mut ConeTwist::new(Anchor::new(None, na::one()), Anchor::new(Some(ball), na::one()),
+                                swing_limit, min_twist, max_twist);
+
+        world.add_cone_twist(joint);
+
+        for _ in range(0u, 100) {
+            world.step(0.016)
+        }
+
+        let (twist, _, swing, _) =
+            swing_twist::<f64, Vec3<f64>, Vec3<f64>, Iso3<f64>>(&joint.anchor1_pos(),
+                                                                &joint.anchor2_pos());
+
+        (twist, swing)
+    }
+
+    #[test]
+    fn test_swing_twist_decomposition() {
+        // the twist axis of this frame is the world `y` axis
+        let ref1 = rotated(&na::one(), Vec3::new(0.0, 0.0, 1.5707963267948966));
+
+        let (twist, twist_axis, swing, swing_axis) =
+            swing_twist::<f64, Vec3<f64>, Vec3<f64>, Iso3<f64>>(
+                &ref1, &rotated(&ref1, Vec3::new(0.3, 0.0, 0.0)));
+
+        assert!((twist - 0.3).abs() < 1.0e-10);
+        assert!(na::norm(&(twist_axis - Vec3::new(0.0, 1.0, 0.0))) < 1.0e-10);
+        assert!(swing.abs() < 1.0e-10);
+        assert!(swing_axis.is_none());
+
+        let (twist, _, swing, swing_axis) =
+            swing_twist::<f64, Vec3<f64>, Vec3<f64>, Iso3<f64>>(
+                &ref1, &rotated(&ref1, Vec3::new(0.0, 0.0, 0.4)));
+
+        assert!(twist.abs() < 1.0e-10);
+        assert!((swing - 0.4).abs() < 1.0e-10);
+        assert!(na::norm(&(swing_axis.unwrap() - Vec3::new(0.0, 0.0, 1.0))) < 1.0e-10);
+    }
+
+    #[test]
+    fn test_cone_twist_limits_the_swing() {
+        // without limit, the ball would swing by 1.6 radians
+        let (twist, swing) = spin(Vec3::new(0.0, 0.0, 1.0), 0.2, -1.0, 1.0);
+
+        assert!(swing < 0.25);
+        assert!(twist.abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn test_cone_twist_limits_the_twist() {
+        let (twist, swing) = spin(Vec3::new(-1.0, 0.0, 0.0), 1.0, -0.1, 0.3);
+
+        assert!(twist > -0.15 && twist < 0.3);
+        assert!(swing < 1.0e-3);
+    }
+}
@@ -2,15 +2,44 @@ use std::num::{Zero, Bounded};
 use nalgebra::na;
 use ncollide::contact::Contact;
 use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::softness::Softness;
 use object::RigidBody;
 use object::volumetric::InertiaTensor;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
+/// The way the penetration between two bodies is corrected.
+///
+/// The velocity correction biases the velocity constraints so that the bodies separate: it
+/// is cheap but adds kinetic energy, making deeply penetrating objects pop out. The position
+/// correction is a second resolution whose result moves the bodies without modifying their
+/// velocities. The split impulse correction solves pseudo-velocities along with the velocities,
+/// and moves the bodies with them.
 pub enum CorrectionMode<N> {
+    /// `Velocity(vel_factor)`: velocity correction only. A factor of 1 removes the whole
+    /// penetration in one step.
     Velocity(N),
+    /// `VelocityAndPosition(vel_factor, pos_factor, min_depth)`: velocity correction, plus
+    /// position correction for contacts deeper than `min_depth`.
     VelocityAndPosition(N, N, N),
-    VelocityAndPositionThresold(N, N, N)
+    /// `VelocityAndPositionThresold(vel_factor, pos_factor, depth)`: velocity correction for the
+    /// contacts shallower than `depth`, position correction for the deeper ones.
+    VelocityAndPositionThresold(N, N, N),
+    /// `SplitImpulse(pos_factor, slop)`: split impulse correction only.
+    ///
+    /// The velocity constraints are not biased at all, so the correction never feeds energy
+    /// back into the bodies velocities. Instead, each contact gets a pseudo-velocity constraint
+    /// with the same geometry, solved right after the velocity constraints of its island with
+    /// its own impulses and its own velocity changes. The resulting pseudo-velocities move the
+    /// bodies during the step, and are then discarded: they never reach `lin_vel` or `ang_vel`.
+    ///
+    /// `pos_factor` is the fraction of the penetration removed in one step, like the
+    /// `pos_factor` of `VelocityAndPosition`. `slop` is subtracted from the depth of every
+    /// contact, where the `min_depth` of `VelocityAndPosition` only selects the contacts to
+    /// correct: bodies are allowed to penetrate by `slop`, which keeps resting contacts alive
+    /// from one step to the next. The number of sweeps is the number of first order iterations
+    /// of the solver.
+    SplitImpulse(N, N)
 }
 
 impl<N: Zero + Bounded + Clone> CorrectionMode<N> {
@@ -19,7 +48,8 @@ impl<N: Zero + Bounded + Clone> CorrectionMode<N> {
         match *self {
             Velocity(ref v)                          => v.clone(),
             VelocityAndPosition(ref v, _, _)         => v.clone(),
-            VelocityAndPositionThresold(ref v, _, _) => v.clone()
+            VelocityAndPositionThresold(ref v, _, _) => v.clone(),
+            SplitImpulse(_, _)                       => na::zero()
         }
     }
 
@@ -28,7 +58,8 @@ impl<N: Zero + Bounded + Clone> CorrectionMode<N> {
         match *self {
             VelocityAndPosition(_, ref p, _)         => p.clone(),
             VelocityAndPositionThresold(_, ref p, _) => p.clone(),
-            Velocity(_)                              => na::zero()
+            Velocity(_)                              => na::zero(),
+            SplitImpulse(_, _)                       => na::zero()
         }
     }
 
@@ -37,7 +68,17 @@ impl<N: Zero + Bounded + Clone> CorrectionMode<N> {
         match *self {
             VelocityAndPosition(_, _, ref t)         => t.clone(),
             VelocityAndPositionThresold(_, _, ref t) => t.clone(),
-            Velocity(_)                              => Bounded::max_value()
+            Velocity(_)                              => Bounded::max_value(),
+            SplitImpulse(_, _)                       => Bounded::max_value()
+        }
+    }
+
+    /// The factor and the slop of the split impulse correction, or `None` if it is not used.
+    #[inline]
+    pub fn split_impulse(&self) -> Option<(N, N)> {
+        match *self {
+            SplitImpulse(ref p, ref s) => Some((p.clone(), s.clone())),
+            _                          => None
         }
     }
 
@@ -46,15 +87,17 @@ impl<N: Zero + Bounded + Clone> CorrectionMode<N> {
         match *self {
             VelocityAndPosition(_, _, _)             => Bounded::max_value(),
             VelocityAndPositionThresold(_, _, ref t) => t.clone(),
-            Velocity(_)                              => Bounded::max_value()
+            Velocity(_)                              => Bounded::max_value(),
+            SplitImpulse(_, _)                       => Bounded::max_value()
         }
     }
 }
 
 pub struct CorrectionParameters<N> {
-    corr_mode:       CorrectionMode<N>,
-    joint_corr:      N,
-    rest_eps:        N
+    corr_mode:        CorrectionMode<N>,
+    joint_corr:       N,
+    rest_eps:         N,
+    contact_softness: Option<Softness<N>>
 }
 
 pub fn reinit_to_first_order_equation<N:  Clone + NPhysicsScalar,
@@ -78,6 +121,28 @@ pub fn reinit_to_first_order_equation<N:  Clone + NPhysicsScalar,
      * Reset forces
      */
     constraint.impulse = na::zero();
+    constraint.cfm     = na::zero();
+}
+
+/// Turns a copy of the velocity constraint of a contact into its pseudo-velocity constraint, for
+/// the split impulse correction.
+///
+/// Only the objective, the bounds and the impulse are replaced: the geometry is the one of the
+/// velocity constraint. The pseudo-velocities remove the fraction `factor` of the penetration
+/// deeper than `slop` in one step.
+pub fn fill_pseudo_velocity_equation<N:  Clone + NPhysicsScalar,
+                                     LV: Clone + NPhysicsDirection<N, AV>,
+                                     AV: NPhysicsOrientation<N>>(
+                                     dt:         N,
+                                     coll:       &Contact<N, LV>,
+                                     constraint: &mut VelocityConstraint<LV, AV, N>,
+                                     factor:     &N,
+                                     slop:       &N) {
+    constraint.objective = *factor * (coll.depth - *slop).max(&na::zero()) / dt;
+    constraint.impulse   = na::zero();
+    constraint.cfm       = na::zero();
+    constraint.lobound   = na::zero();
+    constraint.hibound   = Bounded::max_value();
 }
 
 pub fn fill_second_order_equation<N:  NPhysicsScalar,
@@ -110,7 +175,8 @@ pub fn fill_second_order_equation<N:  NPhysicsScalar,
                              rb1,
                              rb2,
                              rconstraint,
-                             correction);
+                             correction,
+                             &contact_softness(rb1, rb2, &correction.contact_softness));
 
 
     let friction  = rb1.friction() * rb2.friction();
@@ -134,7 +200,8 @@ pub fn fill_second_order_equation<N:  NPhysicsScalar,
                                  rb1,
                                  rb2,
                                  constraint,
-                                 correction);
+                                 correction,
+                                 &None);
 
         constraint.friction_coeff    = friction.clone();
         constraint.friction_limit_id = idr;
@@ -144,6 +211,31 @@ pub fn fill_second_order_equation<N:  NPhysicsScalar,
     })
 }
 
+/// The softness of a contact: the softest of the ones of its bodies, or `default` if none is set.
+fn contact_softness<N:  Clone + NPhysicsScalar,
+                    LV: Clone + NPhysicsDirection<N, AV>,
+                    AV: Clone + NPhysicsOrientation<N>,
+                    M:  NPhysicsTransform<LV, AV>,
+                    II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                    rb1:     &RigidBody<N, LV, AV, M, II>,
+                    rb2:     &RigidBody<N, LV, AV, M, II>,
+                    default: &Option<Softness<N>>)
+                    -> Option<Softness<N>> {
+    match (rb1.contact_softness(), rb2.contact_softness()) {
+        (&Some(ref s1), &Some(ref s2)) => {
+            if s1.frequency <= s2.frequency {
+                Some(s1.clone())
+            }
+            else {
+                Some(s2.clone())
+            }
+        },
+        (&Some(ref s), &None) => Some(s.clone()),
+        (&None, &Some(ref s)) => Some(s.clone()),
+        (&None, &None)        => default.clone()
+    }
+}
+
 pub fn fill_constraint_geometry<N:  Clone + NPhysicsScalar,
                                 LV: Clone + NPhysicsDirection<N, AV>,
                                 AV: Clone + NPhysicsOrientation<N>,
@@ -155,7 +247,29 @@ pub fn fill_constraint_geometry<N:  Clone + NPhysicsScalar,
                                 rb1:        Option<&RigidBody<N, LV, AV, M, II>>,
                                 rb2:        Option<&RigidBody<N, LV, AV, M, II>>,
                                 constraint: &mut VelocityConstraint<LV, AV, N>) {
-    constraint.normal             = normal;
+    fill_coupling_constraint_geometry(normal.clone(), normal, rot_axis1, rot_axis2, rb1, rb2,
+                                      constraint)
+}
+
+/// Fills the geometry of a constraint which acts along `normal1` on the first body and along
+/// `normal2` on the second one.
+///
+/// The relative velocity of such a constraint is `dot(v2, normal2) - dot(v1, normal1)` plus the
+/// angular terms.
+pub fn fill_coupling_constraint_geometry<N:  Clone + NPhysicsScalar,
+                                         LV: Clone + NPhysicsDirection<N, AV>,
+                                         AV: Clone + NPhysicsOrientation<N>,
+                                         M:  NPhysicsTransform<LV, AV>,
+                                         II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                                         normal1:    LV,
+                                         normal2:    LV,
+                                         rot_axis1:  AV,
+                                         rot_axis2:  AV,
+                                         rb1:        Option<&RigidBody<N, LV, AV, M, II>>,
+                                         rb2:        Option<&RigidBody<N, LV, AV, M, II>>,
+                                         constraint: &mut VelocityConstraint<LV, AV, N>) {
+    constraint.normal             = normal1;
+    constraint.normal2            = normal2;
     constraint.inv_projected_mass = na::zero();
 
     match rb1 {
@@ -176,13 +290,13 @@ pub fn fill_constraint_geometry<N:  Clone + NPhysicsScalar,
     match rb2 {
         Some(rb) => {
             // rotation axis
-            constraint.weighted_normal2   = constraint.normal * rb.inv_mass();
+            constraint.weighted_normal2   = constraint.normal2 * rb.inv_mass();
             constraint.rot_axis2          = rot_axis2;
 
             constraint.weighted_rot_axis2 = rb.inv_inertia().apply(&constraint.rot_axis2);
 
             constraint.inv_projected_mass = constraint.inv_projected_mass +
-                na::dot(&constraint.normal, &constraint.weighted_normal2) +
+                na::dot(&constraint.normal2, &constraint.weighted_normal2) +
                 na::dot(&constraint.rot_axis2, &constraint.weighted_rot_axis2);
         },
         None => { }
@@ -208,7 +322,8 @@ fn fill_velocity_constraint<N:  Clone + NPhysicsScalar,
                             rb1:             &RigidBody<N, LV, AV, M, II>,
                             rb2:             &RigidBody<N, LV, AV, M, II>,
                             constraint:      &mut VelocityConstraint<LV, AV, N>,
-                            correction:      &CorrectionParameters<N>) {
+                            correction:      &CorrectionParameters<N>,
+                            softness:        &Option<Softness<N>>) {
     let rot_axis1 = na::cross(&(center - *rb1.center_of_mass()), &-normal);
     let rot_axis2 = na::cross(&(center - *rb2.center_of_mass()), &normal);
 
@@ -216,6 +331,11 @@ fn fill_velocity_constraint<N:  Clone + NPhysicsScalar,
     let opt_rb2 = if rb2.can_move() { Some(rb2) } else { None };
     fill_constraint_geometry(normal, rot_axis1, rot_axis2, opt_rb1, opt_rb2, constraint);
 
+    match *softness {
+        Some(ref s) => s.soften(&dt, constraint),
+        None        => constraint.cfm = na::zero()
+    }
+
     /*
      * Fill indice
      */
@@ -243,7 +363,12 @@ fn fill_velocity_constraint<N:  Clone + NPhysicsScalar,
         constraint.objective = constraint.objective + depth / dt
     }
     else if depth < correction.corr_mode.max_depth_for_vel_corr() {
-        constraint.objective = constraint.objective + depth * correction.corr_mode.vel_corr_factor() / dt
+        let factor = match *softness {
+            Some(ref s) => s.erp(&dt),
+            None        => correction.corr_mode.vel_corr_factor()
+        };
+
+        constraint.objective = constraint.objective + depth * factor / dt
     }
 
     // for warm-starting
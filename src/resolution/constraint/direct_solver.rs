@@ -0,0 +1,549 @@
+use std::num::{Zero, Orderable, Signed};
+use std::vec;
+use nalgebra::na::Vec;
+use nalgebra::na;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::projected_gauss_seidel_solver::{Velocities, Sequential};
+use pgs = resolution::constraint::projected_gauss_seidel_solver;
+
+#[deriving(Eq, Clone)]
+enum VarState {
+    Free,
+    AtLo,
+    AtHi
+}
+
+/// The buffers used by `solve_boxed_lcp`.
+///
+/// They are kept by the callers solving many small LCPs so that they are not reallocated at each
+/// resolution.
+pub struct LCPWorkspace<N> {
+    priv states: ~[VarState],
+    priv w:      ~[N],
+    priv dx:     ~[N],
+    priv dw:     ~[N],
+    priv free:   ~[uint],
+    priv M:      ~[N],
+    priv r:      ~[N]
+}
+
+impl<N> LCPWorkspace<N> {
+    /// Creates an empty workspace. Its buffers grow with the LCPs it is used for.
+    pub fn new() -> LCPWorkspace<N> {
+        LCPWorkspace {
+            states: ~[],
+            w:      ~[],
+            dx:     ~[],
+            dw:     ~[],
+            free:   ~[],
+            M:      ~[],
+            r:      ~[]
+        }
+    }
+}
+
+/// Solves the velocity constraints directly, as a dense boxed LCP, with Dantzig's algorithm.
+///
+/// The friction bounds are deduced from the normal impulses while the LCP is solved (see
+/// `solve_boxed_lcp`), so the solution is exact up to round-off errors and to the changes of the
+/// normal impulses after their friction rows are processed. Its cost grows with the cube of the
+/// number of constraints, so this is meant for small systems which need high accuracy. The system
+/// is solved from scratch: the initial impulses are ignored.
+///
+/// Dantzig's algorithm fails if it meets a singular system or cycles (which may happen with
+/// redundant constraints). The impulses are then clamped to their bounds and refined by at most
+/// `num_iterations` sweeps of projected Gauss-Seidel.
+///
+/// Returns the number of pivots and sweeps performed, the largest impulse change a Gauss-Seidel
+/// sweep would still apply to the solution, and whether Dantzig's algorithm succeeded.
+pub fn direct_solve<LV: Vec<N> + Zero + Clone,
+                    AV: Vec<N> + Zero + Clone,
+                    N:  Num + Orderable + Signed + Clone>(
+                    restitution:    &mut [VelocityConstraint<LV, AV, N>],
+                    friction:       &mut [VelocityConstraint<LV, AV, N>],
+                    result:         &mut [Velocities<LV, AV>],
+                    num_bodies:     uint,
+                    num_iterations: uint,
+                    tolerance:      &N,
+                    _:              bool)
+                    -> (uint, N, bool) {
+    let num_restitution = restitution.len();
+    let mut rows        = ~[];
+
+    rows.push_all(restitution);
+    rows.push_all(friction);
+
+    let n           = rows.len();
+    let mut scratch = vec::from_elem(num_bodies, Velocities::new());
+    let mut A       = ~[];
+
+    fill_delassus_matrix(rows, scratch, &mut A);
+
+    let b      = rows.map(|r| r.objective.clone());
+    let mut lo = rows.map(|r| r.lobound.clone());
+    let mut hi = rows.map(|r| r.hibound.clone());
+    let mut x  = vec::from_elem(n, na::zero());
+
+    // each friction row is bounded by the impulse of its normal row
+    let limits = vec::from_fn(n, |i| {
+        if i < num_restitution {
+            None
+        }
+        else {
+            Some((rows[i].friction_limit_id, rows[i].friction_coeff.clone()))
+        }
+    });
+
+    let (num_pivots, converged) = solve_boxed_lcp(A, b, lo, hi, limits, x, 4 * n + 10,
+                                                  &mut LCPWorkspace::new());
+
+    /*
+     * Write the result back.
+     */
+    for (i, c) in restitution.mut_iter().chain(friction.mut_iter()).enumerate() {
+        c.impulse = x[i].clamp(&lo[i], &hi[i]);
+        c.lobound = lo[i].clone();
+        c.hibound = hi[i].clone();
+    }
+
+    if converged {
+        let residual = boxed_lcp_residual(A, b, lo, hi, x);
+
+        pgs::setup_warmstart(restitution, friction, result, num_bodies, false);
+
+        (num_pivots, residual, true)
+    }
+    else {
+        let (num_iter, residual) =
+            pgs::projected_gauss_seidel_solve(restitution, friction, result, num_bodies,
+                                              num_iterations, tolerance, &na::one(), &Sequential,
+                                              None, false);
+
+        (num_pivots + num_iter, residual, false)
+    }
+}
+
+/// Fills `out` with the row-major matrix `J M^-1 J^t` of a set of constraints, plus their
+/// constraint force mixing on the diagonal.
+///
+/// `scratch` must contain one zero velocity per body. It is left unchanged.
+pub fn fill_delassus_matrix<LV: Vec<N> + Clone,
+                            AV: Vec<N> + Clone,
+                            N:  Num + Clone>(
+                            rows:    &[VelocityConstraint<LV, AV, N>],
+                            scratch: &mut [Velocities<LV, AV>],
+                            out:     &mut ~[N]) {
+    let n = rows.len();
+
+    out.clear();
+    out.grow(n * n, &na::zero());
+
+    for j in range(0u, n) {
+        pgs::apply_impulse(&rows[j], scratch, &na::one());
+
+        for i in range(0u, n) {
+            out[i * n + j] = -pgs::constraint_velocity(&rows[i], scratch);
+        }
+
+        pgs::apply_impulse(&rows[j], scratch, &-na::one::<N>());
+
+        out[j * n + j] = out[j * n + j] + rows[j].cfm;
+    }
+}
+
+/// Solves a boxed LCP with Dantzig's algorithm.
+///
+/// Finds `x` such that `lo <= x <= hi` and `w = b - A x` is zero where `x` is strictly inside its
+/// bounds, non-positive where `x = lo`, and non-negative where `x = hi`. `A` is a row-major
+/// square matrix, and the bounds must contain zero.
+///
+/// The variables are added one at a time, starting from zero: each one is moved until its `w`
+/// vanishes or it reaches a bound, while the variables already added keep satisfying the
+/// conditions above (those reaching a bound, or whose `w` vanishes, change of state on the way).
+/// If `limits[i]` is `Some((j, mu))`, the bounds of the variable `i` are replaced by
+/// `[-mu * x[j], mu * x[j]]` (with `x[j]` clamped to zero if negative) when it is added, so `j`
+/// must be smaller than `i`. The final bounds are written to `lo` and `hi`.
+///
+/// The buffers of `ws` are reused: the LCP does not allocate once they are large enough.
+///
+/// Returns the number of pivoting steps performed, at most `max_pivots`, and whether a solution
+/// was found. If not, `x` is the last iterate, which may violate the conditions above.
+pub fn solve_boxed_lcp<N: Num + Orderable + Signed + Clone>(A:          &[N],
+                                                              b:          &[N],
+                                                              lo:         &mut [N],
+                                                              hi:         &mut [N],
+                                                              limits:     &[Option<(uint, N)>],
+                                                              x:          &mut [N],
+                                                              max_pivots: uint,
+                                                              ws:         &mut LCPWorkspace<N>)
+                                                              -> (uint, bool) {
+    let n      = b.len();
+    let _0: N  = na::zero();
+    let _1: N  = na::one();
+
+    let LCPWorkspace {
+        states: ref mut states,
+        w:      ref mut w,
+        dx:     ref mut dx,
+        dw:     ref mut dw,
+        free:   ref mut free,
+        M:      ref mut M,
+        r:      ref mut r
+    } = *ws;
+
+    states.clear();
+    states.grow(n, &Free);
+    w.clear();
+    w.push_all(b);
+    dx.clear();
+    dx.grow(n, &_0);
+    dw.clear();
+    dw.grow(n, &_0);
+
+    let mut num_pivots = 0;
+
+    for xi in x.mut_iter() {
+        *xi = _0.clone()
+    }
+
+    for i in range(0u, n) {
+        match limits[i] {
+            Some((j, ref mu)) => {
+                let bound = *mu * x[j].max(&_0);
+
+                lo[i] = -bound;
+                hi[i] = bound;
+            },
+            None => { }
+        }
+
+        loop {
+            /*
+             * Stop if the new variable satisfies the conditions.
+             */
+            if w[i].is_zero() {
+                states[i] = Free;
+                break;
+            }
+
+            let dir = if w[i] > _0 { _1.clone() } else { -_1 };
+
+            if dir > _0 && x[i] >= hi[i] {
+                states[i] = AtHi;
+                break;
+            }
+
+            if dir < _0 && x[i] <= lo[i] {
+                states[i] = AtLo;
+                break;
+            }
+
+            if num_pivots == max_pivots {
+                return (num_pivots, false)
+            }
+
+            num_pivots = num_pivots + 1;
+
+            /*
+             * Direction moving the new variable while the `w` of the free ones stay zero.
+             */
+            free.clear();
+
+            for j in range(0u, i) {
+                if states[j] == Free {
+                    free.push(j)
+                }
+            }
+
+            M.clear();
+            r.clear();
+
+            for fj in free.iter() {
+                r.push(-A[*fj * n + i] * dir);
+
+                for fk in free.iter() {
+                    M.push(A[*fj * n + *fk].clone());
+                }
+            }
+
+            if !solve_dense_system(*M, *r) {
+                return (num_pivots, false)
+            }
+
+            for d in dx.mut_iter() {
+                *d = _0.clone()
+            }
+
+            dx[i] = dir.clone();
+
+            for (k, fj) in free.iter().enumerate() {
+                dx[*fj] = r[k].clone();
+            }
+
+            for j in range(0u, n) {
+                let mut dwj = -A[j * n + i] * dx[i];
+
+                for fk in free.iter() {
+                    dwj = dwj - A[j * n + *fk] * dx[*fk];
+                }
+
+                dw[j] = dwj;
+            }
+
+            /*
+             * Find the largest step keeping every condition satisfied.
+             */
+            let mut step: Option<(N, uint, VarState)> = None;
+
+            // the new variable reaches zero `w`, or its bound
+            if dw[i] * dir < _0 {
+                keep_smallest(&mut step, -w[i] / dw[i], i, Free);
+            }
+
+            if dir > _0 {
+                keep_smallest(&mut step, hi[i] - x[i], i, AtHi);
+            }
+            else {
+                keep_smallest(&mut step, x[i] - lo[i], i, AtLo);
+            }
+
+            for j in range(0u, i) {
+                match states[j] {
+                    Free => {
+                        if dx[j] < _0 {
+                            keep_smallest(&mut step, (lo[j] - x[j]) / dx[j], j, AtLo);
+                        }
+                        else if dx[j] > _0 {
+                            keep_smallest(&mut step, (hi[j] - x[j]) / dx[j], j, AtHi);
+                        }
+                    },
+                    AtLo => if dw[j] > _0 {
+                        keep_smallest(&mut step, -w[j] / dw[j], j, Free);
+                    },
+                    AtHi => if dw[j] < _0 {
+                        keep_smallest(&mut step, -w[j] / dw[j], j, Free);
+                    }
+                }
+            }
+
+            let (t, blocking, state) = match step {
+                Some(s) => s,
+                None    => return (num_pivots, false) // unbounded direction
+            };
+
+            let t = t.max(&_0);
+
+            for j in range(0u, n) {
+                x[j] = x[j] + dx[j] * t;
+                w[j] = w[j] + dw[j] * t;
+            }
+
+            /*
+             * Update the state of the blocking variable, removing the round-off errors.
+             */
+            match state {
+                Free => w[blocking] = _0.clone(),
+                AtLo => x[blocking] = lo[blocking].clone(),
+                AtHi => x[blocking] = hi[blocking].clone()
+            }
+
+            states[blocking] = state;
+
+            if blocking == i {
+                break;
+            }
+        }
+    }
+
+    (num_pivots, true)
+}
+
+#[inline]
+fn keep_smallest<N: Ord>(step: &mut Option<(N, uint, VarState)>, t: N, i: uint, state: VarState) {
+    let smaller = match *step {
+        Some((ref best, _, _)) => t < *best,
+        None                   => true
+    };
+
+    if smaller {
+        *step = Some((t, i, state))
+    }
+}
+
+/// The largest impulse change a projected Gauss-Seidel sweep would apply to `x`.
+pub fn boxed_lcp_residual<N: Num + Orderable + Signed + Clone>(A:  &[N],
+                                                                 b:  &[N],
+                                                                 lo: &[N],
+                                                                 hi: &[N],
+                                                                 x:  &[N])
+                                                                 -> N {
+    let n          = b.len();
+    let mut res: N = na::zero();
+
+    for i in range(0u, n) {
+        let diag = A[i * n + i].clone();
+
+        if !diag.is_zero() {
+            let new_x = (x[i] + w(A, b, x, i) / diag).clamp(&lo[i], &hi[i]);
+
+            res = res.max(&(new_x - x[i]).abs());
+        }
+    }
+
+    res
+}
+
+#[inline]
+fn w<N: Num + Clone>(A: &[N], b: &[N], x: &[N], i: uint) -> N {
+    let n     = b.len();
+    let mut w = b[i].clone();
+
+    for j in range(0u, n) {
+        w = w - A[i * n + j] * x[j];
+    }
+
+    w
+}
+
+/// Solves the row-major square system `M x = r` with Gaussian elimination and partial pivoting.
+///
+/// `r` is overwritten by the solution. The unknowns which cannot be determined (singular `M`) are
+/// set to zero. Returns `false` if `M` is singular.
+pub fn solve_dense_system<N: Num + Orderable + Signed + Clone>(M: &mut [N], r: &mut [N]) -> bool {
+    let n           = r.len();
+    let mut regular = true;
+
+    for k in range(0u, n) {
+        let mut p = k;
+
+        for i in range(k + 1, n) {
+            if M[i * n + k].abs() > M[p * n + k].abs() {
+                p = i
+            }
+        }
+
+        if M[p * n + k].is_zero() {
+            regular = false;
+            continue;
+        }
+
+        if p != k {
+            for j in range(0u, n) {
+                M.swap(p * n + j, k * n + j);
+            }
+
+            r.swap(p, k);
+        }
+
+        for i in range(k + 1, n) {
+            let f = M[i * n + k] / M[k * n + k];
+
+            for j in range(k, n) {
+                M[i * n + j] = M[i * n + j] - f * M[k * n + j];
+            }
+
+            r[i] = r[i] - f * r[k];
+        }
+    }
+
+    let mut k = n;
+
+    while k > 0 {
+        k = k - 1;
+
+        if M[k * n + k].is_zero() {
+            r[k] = na::zero();
+        }
+        else {
+            let mut s = r[k].clone();
+
+            for j in range(k + 1, n) {
+                s = s - M[k * n + j] * r[j];
+            }
+
+            r[k] = s / M[k * n + k];
+        }
+    }
+
+    regular
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::Bounded;
+    use super::{solve_boxed_lcp, solve_dense_system, LCPWorkspace};
+
+    fn solve(A:      &[f64],
+             b:      &[f64],
+             lo:     &mut [f64],
+             hi:     &mut [f64],
+             limits: &[Option<(uint, f64)>])
+             -> ~[f64] {
+        let mut x = ~[0.0, 0.0];
+        let (_, converged) = solve_boxed_lcp(A, b, lo, hi, limits, x, 10, &mut LCPWorkspace::new());
+
+        assert!(converged);
+
+        x
+    }
+
+    fn assert_approx_eq(a: &[f64], b: &[f64]) {
+        for (ai, bi) in a.iter().zip(b.iter()) {
+            assert!((*ai - *bi).abs() < 1.0e-10);
+        }
+    }
+
+    #[test]
+    fn test_boxed_lcp_with_free_variables() {
+        let max: f64 = Bounded::max_value();
+        let x = solve(&[2.0, 1.0, 1.0, 2.0], &[3.0, 3.0], &mut [0.0, 0.0], &mut [max, max],
+                      &[None, None]);
+
+        assert_approx_eq(x, &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_boxed_lcp_with_active_bounds() {
+        let max: f64 = Bounded::max_value();
+
+        // the second variable would be negative
+        let x = solve(&[2.0, 1.0, 1.0, 2.0], &[3.0, -3.0], &mut [0.0, 0.0], &mut [max, max],
+                      &[None, None]);
+
+        assert_approx_eq(x, &[1.5, 0.0]);
+
+        // the first variable is capped
+        let x = solve(&[2.0, 1.0, 1.0, 2.0], &[3.0, 3.0], &mut [0.0, 0.0], &mut [1.0, max],
+                      &[None, None]);
+
+        assert_approx_eq(x, &[1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_boxed_lcp_with_friction_limits() {
+        let max: f64 = Bounded::max_value();
+        let mut lo   = ~[0.0, 0.0];
+        let mut hi   = ~[max, 0.0];
+
+        // the second variable is bounded by half of the first one
+        let x = solve(&[1.0, 0.0, 0.0, 1.0], &[2.0, 5.0], lo, hi, &[None, Some((0, 0.5))]);
+
+        assert_approx_eq(x, &[2.0, 1.0]);
+        assert_approx_eq(lo, &[0.0, -1.0]);
+        assert_approx_eq(hi, &[max, 1.0]);
+    }
+
+    #[test]
+    fn test_dense_system() {
+        let mut M = ~[4.0, 1.0, 2.0, 3.0];
+        let mut r = ~[6.0, 8.0];
+
+        assert!(solve_dense_system(M, r));
+        assert_approx_eq(r, &[1.0, 2.0]);
+
+        let mut M = ~[1.0, 2.0, 2.0, 4.0];
+        let mut r = ~[1.0, 2.0];
+
+        assert!(!solve_dense_system(M, r));
+    }
+}
@@ -0,0 +1,231 @@
+use std::num::{Zero, Bounded};
+use nalgebra::na;
+use detection::joint::distance::Distance;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::contact_equation::CorrectionParameters;
+use resolution::constraint::contact_equation;
+use resolution::constraint::ball_in_socket_equation;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// Fills the single velocity constraint of a distance joint.
+///
+/// The constraint acts along the line joining the two anchors. When the distance between the
+/// anchors is within its limits, the constraint is bounded so that it only prevents the limit
+/// nearest to be exceeded during the step (and a rope never pushes its anchors away).
+pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
+                                  LV: Clone + NPhysicsDirection<N, AV>,
+                                  AV: Clone + NPhysicsOrientation<N>,
+                                  M:  Clone + NPhysicsTransform<LV, AV>,
+                                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                                  dt:         N,
+                                  joint:      &Distance<N, LV, AV, M, II>,
+                                  constraint: &mut VelocityConstraint<LV, AV, N>,
+                                  correction: &CorrectionParameters<N>) {
+    let global1        = joint.anchor1_pos();
+    let global2        = joint.anchor2_pos();
+    let (axis, length) = axis_and_length(&global1, &global2);
+    let min            = joint.min_length();
+    let max            = joint.max_length();
+    let _M: N          = Bounded::max_value();
+
+    // `violated` is false for the limits which are only enforced if they would be exceeded
+    // during the step.
+    let (error, lobound, hibound, violated) =
+        if min == max {
+            (length - max, -_M, _M.clone(), true)
+        }
+        else if length > max {
+            (length - max, -_M, na::zero(), true)
+        }
+        else if length < min {
+            (length - min, na::zero(), _M.clone(), true)
+        }
+        else if !min.is_zero() && length - min < max - length {
+            (length - min, na::zero(), _M.clone(), false)
+        }
+        else {
+            (length - max, -_M, na::zero(), false)
+        };
+
+    let anchor1   = joint.anchor1();
+    let anchor2   = joint.anchor2();
+    let rot_axis1 = na::cross(&(global1 - anchor1.center_of_mass()), &-axis);
+    let rot_axis2 = na::cross(&(global2 - anchor2.center_of_mass()), &axis);
+
+    let opt_b1  = ball_in_socket_equation::write_anchor_id(anchor1, &mut constraint.id1);
+    let opt_b2  = ball_in_socket_equation::write_anchor_id(anchor2, &mut constraint.id2);
+    let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+    let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+    let dvel = contact_equation::relative_velocity(
+        opt_rb1,
+        opt_rb2,
+        &axis,
+        &rot_axis1,
+        &rot_axis2,
+        &dt);
+
+    contact_equation::fill_constraint_geometry(
+        axis,
+        rot_axis1,
+        rot_axis2,
+        opt_rb1,
+        opt_rb2,
+        constraint
+    );
+
+    constraint.cfm = na::zero();
+
+    let erp =
+        if violated {
+            match *joint.softness() {
+                Some(ref s) => {
+                    s.soften(&dt, constraint);
+                    s.erp(&dt)
+                },
+                None => correction.joint_corr.clone()
+            }
+        }
+        else {
+            na::one()
+        };
+
+    constraint.lobound   = lobound;
+    constraint.hibound   = hibound;
+    constraint.objective = -dvel - error * erp / dt;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+/// Moves the bodies attached to a distance joint to bring the distance between its anchors back
+/// within its limits.
+pub fn project_position<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  Clone + NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                        joint:  &Distance<N, LV, AV, M, II>,
+                        factor: &N) {
+    let global1        = joint.anchor1_pos();
+    let global2        = joint.anchor2_pos();
+    let (axis, length) = axis_and_length(&global1, &global2);
+
+    let error =
+        if length > joint.max_length() {
+            length - joint.max_length()
+        }
+        else if length < joint.min_length() {
+            length - joint.min_length()
+        }
+        else {
+            return
+        };
+
+    let anchor1   = joint.anchor1();
+    let anchor2   = joint.anchor2();
+    let rot_axis1 = na::cross(&(global1 - anchor1.center_of_mass()), &-axis);
+    let rot_axis2 = na::cross(&(global2 - anchor2.center_of_mass()), &axis);
+
+    ball_in_socket_equation::apply_position_impulse(axis, rot_axis1, rot_axis2, anchor1, anchor2,
+                                                    error, factor);
+}
+
+/// The unit vector from `global1` to `global2`, and the distance between them.
+///
+/// An arbitrary axis is returned if the two points coincide.
+pub fn axis_and_length<N:  Clone + NPhysicsScalar,
+                   LV: Clone + NPhysicsDirection<N, AV>,
+                   AV>(
+                   global1: &LV,
+                   global2: &LV)
+                   -> (LV, N) {
+    let delta  = *global2 - *global1;
+    let length = na::norm(&delta);
+
+    if length.is_zero() {
+        let mut axis: LV = na::zero();
+
+        axis.set(0, na::one());
+
+        (axis, length)
+    }
+    else {
+        (delta / length, length)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Mat3, Iso3, Translation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, Body3d, Distance3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Dynamic, RB};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::distance::Distance;
+
+    // a ball falling from the origin
+    fn falling_ball() -> (BodyWorld3d<f64>, @mut Body3d<f64>) {
+        let mut world = BodyWorld::new();
+
+        world.set_gravity(Vec3::new(0.0f64, -9.81, 0.0));
+
+        let ball = @mut RB(RigidBody::new(Geom::new_ball(0.1f64), 1.0, Dynamic, 0.3, 0.6));
+
+        world.add_body(ball);
+
+        (world, ball)
+    }
+
+    // the point `(0, y, 0)` of the world
+    fn fixed_anchor(y: f64) -> Anchor<f64, Vec3<f64>, Vec3<f64>, Iso3<f64>, Mat3<f64>, Vec3<f64>> {
+        Anchor::new(None, Vec3::new(0.0, y, 0.0))
+    }
+
+    fn run(world: &mut BodyWorld3d<f64>, num_steps: uint) {
+        for _ in range(0u, num_steps) {
+            world.step(0.016)
+        }
+    }
+
+    #[test]
+    fn test_rope_is_slack_until_taut() {
+        let (mut world, ball) = falling_ball();
+        let rope: @mut Distance3d<f64> =
+            @mut Distance::new_rope(Anchor::new(Some(ball), na::zero()), fixed_anchor(1.0), 2.0);
+
+        world.add_distance(rope);
+
+        // free fall while the ball is closer than the rope length
+        run(&mut world, 10);
+
+        let vel = ball.to_rigid_body_or_fail().lin_vel();
+
+        assert!((vel.y + 9.81 * 0.16).abs() < 1.0e-6);
+
+        // the ball then hangs at the end of the rope
+        run(&mut world, 300);
+
+        let length = na::norm(&(rope.anchor1_pos() - rope.anchor2_pos()));
+
+        assert!((length - 2.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_distance_joint_keeps_its_minimum_length() {
+        let (mut world, ball) = falling_ball();
+        let joint: @mut Distance3d<f64> =
+            @mut Distance::new(Anchor::new(Some(ball), na::zero()), fixed_anchor(-2.0), 1.5, 10.0);
+
+        world.add_distance(joint);
+
+        // the ball falls toward the anchor and stops at the minimum length
+        run(&mut world, 300);
+
+        let length = na::norm(&(joint.anchor1_pos() - joint.anchor2_pos()));
+
+        assert!((length - 1.5).abs() < 0.05);
+        assert!(ball.to_rigid_body_or_fail().transform_ref().translation().y > -0.6);
+    }
+}
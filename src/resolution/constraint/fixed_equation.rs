@@ -6,6 +6,7 @@ use resolution::constraint::ball_in_socket_equation;
 use resolution::constraint::velocity_constraint::VelocityConstraint;
 use resolution::constraint::contact_equation::CorrectionParameters;
 use resolution::constraint::contact_equation;
+use resolution::constraint::softness::Softness;
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
                       NPhysicsInertia};
 
@@ -29,7 +30,8 @@ pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
         joint.anchor1(),
         joint.anchor2(),
         constraints,
-        correction);
+        correction,
+        joint.softness());
 
     cancel_relative_angular_motion(
         dt,
@@ -38,7 +40,30 @@ pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
         joint.anchor1(),
         joint.anchor2(),
         constraints.mut_slice_from(na::dim::<LV>()),
-        correction);
+        correction,
+        joint.softness());
+}
+
+/// Moves the bodies attached to a fixed joint to reduce its positional and angular errors.
+pub fn project_position<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV> + CrossMatrix<CM>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  Clone + NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                        CM: Row<AV>>(
+                        joint:  &Fixed<N, LV, AV, M, II>,
+                        factor: &N) {
+    ball_in_socket_equation::project_relative_linear_position(
+        || (joint.anchor1_pos().translation(), joint.anchor2_pos().translation()),
+        joint.anchor1(),
+        joint.anchor2(),
+        factor);
+
+    project_relative_angular_position(
+        || (joint.anchor1_pos(), joint.anchor2_pos()),
+        joint.anchor1(),
+        joint.anchor2(),
+        factor);
 }
 
 pub fn cancel_relative_angular_motion<N:  Clone + NPhysicsScalar,
@@ -54,7 +79,13 @@ pub fn cancel_relative_angular_motion<N:  Clone + NPhysicsScalar,
                                       anchor1:     &Anchor<N, LV, AV, M, II, P>,
                                       anchor2:     &Anchor<N, LV, AV, M, II, P>,
                                       constraints: &mut [VelocityConstraint<LV, AV, N>],
-                                      correction:  &CorrectionParameters<N>) {
+                                      correction:  &CorrectionParameters<N>,
+                                      softness:    &Option<Softness<N>>) {
+    let erp = match *softness {
+        Some(ref s) => s.erp(&dt),
+        None        => correction.joint_corr.clone()
+    };
+
     let delta     = na::inv(ref2).expect("ref2 must be inversible.") * *ref1;
     let delta_rot = delta.rotation();
 
@@ -76,6 +107,11 @@ pub fn cancel_relative_angular_motion<N:  Clone + NPhysicsScalar,
             constraint
         );
 
+        match *softness {
+            Some(ref s) => s.soften(&dt, constraint),
+            None        => constraint.cfm = na::zero()
+        }
+
         let ang_vel1 = match opt_rb1 { Some(rb) => rb.ang_vel(), None => na::zero() };
         let ang_vel2 = match opt_rb2 { Some(rb) => rb.ang_vel(), None => na::zero() };
 
@@ -83,7 +119,7 @@ pub fn cancel_relative_angular_motion<N:  Clone + NPhysicsScalar,
         constraint.lobound   = -_M;
         constraint.hibound   = _M;
         // FIXME: dont compute the difference at each iteration
-        let error = na::dot(&delta_rot, &rot_axis) * correction.joint_corr / dt;
+        let error = na::dot(&delta_rot, &rot_axis) * erp / dt;
         constraint.objective = na::dot(&(ang_vel2 - ang_vel1), &rot_axis) - error;
         constraint.impulse   = na::zero(); // FIXME: cache
 
@@ -92,3 +128,42 @@ pub fn cancel_relative_angular_motion<N:  Clone + NPhysicsScalar,
         true
     })
 }
+
+/// Position-level counterpart of `cancel_relative_angular_motion`.
+///
+/// Performs one non-linear Gauss-Seidel step per rotation axis: the anchors world frames are
+/// recomputed by `refs` before each step, and the bodies are directly rotated to remove the
+/// fraction `factor` of the angular error.
+pub fn project_relative_angular_position<N:  Clone + NPhysicsScalar,
+                                         LV: Clone + NPhysicsDirection<N, AV>,
+                                         AV: Clone + NPhysicsOrientation<N>,
+                                         M:  Clone + NPhysicsTransform<LV, AV>,
+                                         II: Clone + NPhysicsInertia<N, LV, AV, M>,
+                                         P>(
+                                         refs:    || -> (M, M),
+                                         anchor1: &Anchor<N, LV, AV, M, II, P>,
+                                         anchor2: &Anchor<N, LV, AV, M, II, P>,
+                                         factor:  &N) {
+    let mut axes = ~[];
+
+    na::canonical_basis(|rot_axis: AV| {
+        axes.push(rot_axis);
+
+        true
+    });
+
+    for rot_axis in axes.iter() {
+        let (ref1, ref2) = refs();
+        let delta        = na::inv(&ref2).expect("ref2 must be inversible.") * ref1;
+        let error        = na::dot(&delta.rotation(), rot_axis);
+
+        ball_in_socket_equation::apply_position_impulse(
+            na::zero(),
+            rot_axis.clone(),
+            -rot_axis,
+            anchor1,
+            anchor2,
+            error,
+            factor);
+    }
+}
@@ -0,0 +1,103 @@
+use std::num::Bounded;
+use nalgebra::na;
+use detection::joint::gear::Gear;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::contact_equation::CorrectionParameters;
+use resolution::constraint::contact_equation;
+use resolution::constraint::ball_in_socket_equation;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// Fills the single velocity constraint of a gear.
+///
+/// The constraint is purely angular. There is no position error to correct, so `correction` is
+/// unused.
+pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
+                                  LV: Clone + NPhysicsDirection<N, AV>,
+                                  AV: Clone + NPhysicsOrientation<N>,
+                                  M:  Clone + NPhysicsTransform<LV, AV>,
+                                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                                  dt:          N,
+                                  joint:       &Gear<N, LV, AV, M, II>,
+                                  constraints: &mut [VelocityConstraint<LV, AV, N>],
+                                  _:           &CorrectionParameters<N>) {
+    let constraint = &mut constraints[0];
+
+    // the constraint velocity is `dot(w2, axis2) - ratio * dot(w1, axis1)`
+    let rot_axis1 = joint.axis1() * -joint.ratio();
+    let rot_axis2 = joint.axis2();
+
+    let opt_b1  = ball_in_socket_equation::write_anchor_id(joint.anchor1(), &mut constraint.id1);
+    let opt_b2  = ball_in_socket_equation::write_anchor_id(joint.anchor2(), &mut constraint.id2);
+    let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+    let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+    let dvel = contact_equation::relative_velocity(
+        opt_rb1,
+        opt_rb2,
+        &na::zero(),
+        &rot_axis1,
+        &rot_axis2,
+        &dt);
+
+    contact_equation::fill_constraint_geometry(
+        na::zero(),
+        rot_axis1,
+        rot_axis2,
+        opt_rb1,
+        opt_rb2,
+        constraint
+    );
+
+    let _M: N = Bounded::max_value();
+
+    constraint.cfm       = na::zero();
+    constraint.lobound   = -_M;
+    constraint.hibound   = _M;
+    constraint.objective = -dvel;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Translation};
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, Gear3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Dynamic, RB};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::gear::Gear;
+
+    #[test]
+    fn test_gear_couples_the_angular_velocities() {
+        let mut world: BodyWorld3d<f64> = BodyWorld::new();
+
+        let mut rb1 = RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6);
+        let mut rb2 = RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6);
+
+        rb1.set_ang_vel(Vec3::new(0.0, 0.0, 1.0));
+        rb2.append_translation(&Vec3::new(3.0, 0.0, 0.0));
+
+        let b1 = @mut RB(rb1);
+        let b2 = @mut RB(rb2);
+
+        world.add_body(b1);
+        world.add_body(b2);
+
+        let axis = Vec3::new(0.0f64, 0.0, 1.0);
+        let gear: @mut Gear3d<f64> = @mut Gear::new(Anchor::new(Some(b1), axis.clone()),
+                                                    Anchor::new(Some(b2), axis),
+                                                    2.0);
+
+        world.add_gear(gear);
+        world.step(0.016);
+
+        let w1 = b1.to_rigid_body_or_fail().ang_vel();
+        let w2 = b2.to_rigid_body_or_fail().ang_vel();
+
+        // both balls have the same inertia: the angular momentum 1 is shared as `w + 2 * (2 * w)`
+        assert!((w1.z - 0.2).abs() < 1.0e-6);
+        assert!((w2.z - 2.0 * w1.z).abs() < 1.0e-6);
+        assert!(w1.x.abs() < 1.0e-10 && w1.y.abs() < 1.0e-10);
+    }
+}
@@ -0,0 +1,433 @@
+use std::num::Bounded;
+use nalgebra::na::{Dim, Rotate, Transformation};
+use nalgebra::na;
+use detection::joint::anchor::Anchor;
+use detection::joint::generic_joint::{GenericJoint, AxisMotion, Locked, Free, Limited, Motor};
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::contact_equation::CorrectionParameters;
+use resolution::constraint::contact_equation;
+use resolution::constraint::ball_in_socket_equation;
+use resolution::constraint::cone_twist_equation;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// The number of velocity constraints of a generic joint: one limit and one motor per
+/// translation and rotation axis.
+///
+/// Free axes and axes without motor get an inactive constraint so that this number does not
+/// depend on the joint configuration.
+#[inline]
+pub fn num_equations<LV: Dim, AV: Dim>() -> uint {
+    2 * (na::dim::<LV>() + na::dim::<AV>())
+}
+
+pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
+                                  LV: Clone + NPhysicsDirection<N, AV>,
+                                  AV: Clone + NPhysicsOrientation<N>,
+                                  M:  Clone + NPhysicsTransform<LV, AV>,
+                                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                                  dt:          N,
+                                  joint:       &GenericJoint<N, LV, AV, M, II>,
+                                  constraints: &mut [VelocityConstraint<LV, AV, N>],
+                                  correction:  &CorrectionParameters<N>) {
+    let ref1     = joint.anchor1_pos();
+    let ref2     = joint.anchor2_pos();
+    let global1  = ref1.translation();
+    let global2  = ref2.translation();
+    let rotvec   = relative_rotation(&ref1, &ref2);
+    let num_lin  = na::dim::<LV>();
+    let num_ang  = na::dim::<AV>();
+    let num_dofs = num_lin + num_ang;
+
+    for i in range(0u, num_lin) {
+        let axis  = linear_axis(&ref1, i);
+        let value = na::dot(&(global2 - global1), &axis);
+
+        match limits(joint.linear_axis(i)) {
+            Some((min, max)) =>
+                fill_linear_limit(dt.clone(), axis.clone(), value, min, max, &global1, &global2,
+                                  joint.anchor1(), joint.anchor2(), &mut constraints[i],
+                                  correction),
+            None => constraints[i] = VelocityConstraint::new()
+        }
+
+        match *joint.linear_motor(i) {
+            Some(ref motor) =>
+                fill_linear_motor(dt.clone(), axis, motor, &global1, &global2, joint.anchor1(),
+                                  joint.anchor2(), &mut constraints[num_dofs + i]),
+            None => constraints[num_dofs + i] = VelocityConstraint::new()
+        }
+    }
+
+    for i in range(0u, num_ang) {
+        let axis = angular_axis(&ref1, i);
+
+        match limits(joint.angular_axis(i)) {
+            Some((min, max)) =>
+                cone_twist_equation::fill_angular_limit(
+                    dt.clone(), axis.clone(), rotvec.at(i), min, max, joint.anchor1(),
+                    joint.anchor2(), &mut constraints[num_lin + i], correction),
+            None => constraints[num_lin + i] = VelocityConstraint::new()
+        }
+
+        match *joint.angular_motor(i) {
+            Some(ref motor) =>
+                fill_angular_motor(dt.clone(), axis, motor, joint.anchor1(), joint.anchor2(),
+                                   &mut constraints[num_dofs + num_lin + i]),
+            None => constraints[num_dofs + num_lin + i] = VelocityConstraint::new()
+        }
+    }
+}
+
+/// Moves the bodies attached to a generic joint to bring each of its locked or limited axes back
+/// within its limits.
+pub fn project_position<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  Clone + NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                        joint:  &GenericJoint<N, LV, AV, M, II>,
+                        factor: &N) {
+    for i in range(0u, na::dim::<LV>()) {
+        match limits(joint.linear_axis(i)) {
+            Some((min, max)) => {
+                let ref1    = joint.anchor1_pos();
+                let global1 = ref1.translation();
+                let global2 = joint.anchor2_pos().translation();
+                let axis    = linear_axis(&ref1, i);
+                let value   = na::dot(&(global2 - global1), &axis);
+
+                match limit_error(value, min, max) {
+                    Some(error) => {
+                        let anchor1   = joint.anchor1();
+                        let anchor2   = joint.anchor2();
+                        let rot_axis1 = na::cross(&(global1 - anchor1.center_of_mass()), &-axis);
+                        let rot_axis2 = na::cross(&(global2 - anchor2.center_of_mass()), &axis);
+
+                        ball_in_socket_equation::apply_position_impulse(
+                            axis, rot_axis1, rot_axis2, anchor1, anchor2, error, factor)
+                    },
+                    None => { }
+                }
+            },
+            None => { }
+        }
+    }
+
+    for i in range(0u, na::dim::<AV>()) {
+        match limits(joint.angular_axis(i)) {
+            Some((min, max)) => {
+                let ref1  = joint.anchor1_pos();
+                let angle = relative_rotation(&ref1, &joint.anchor2_pos()).at(i);
+
+                match limit_error(angle, min, max) {
+                    Some(error) => {
+                        let axis = angular_axis(&ref1, i);
+
+                        ball_in_socket_equation::apply_position_impulse(
+                            na::zero(), axis.clone(), -axis, joint.anchor1(), joint.anchor2(),
+                            -error, factor)
+                    },
+                    None => { }
+                }
+            },
+            None => { }
+        }
+    }
+}
+
+/// The limits of an axis, or `None` if it is free.
+fn limits<N: Clone + NPhysicsScalar>(motion: &AxisMotion<N>) -> Option<(N, N)> {
+    match *motion {
+        Locked                    => Some((na::zero(), na::zero())),
+        Free                      => None,
+        Limited(ref min, ref max) => Some((min.clone(), max.clone()))
+    }
+}
+
+/// The distance between `value` and the interval `[min, max]`, or `None` if it is inside.
+fn limit_error<N: Clone + NPhysicsScalar>(value: N, min: N, max: N) -> Option<N> {
+    if value > max {
+        Some(value - max)
+    }
+    else if value < min {
+        Some(value - min)
+    }
+    else {
+        None
+    }
+}
+
+/// The rotation vector of `ref2` relative to `ref1`, expressed in the local frame of `ref1`.
+fn relative_rotation<N:  Clone + NPhysicsScalar,
+                     LV: Clone + NPhysicsDirection<N, AV>,
+                     AV: Clone + NPhysicsOrientation<N>,
+                     M:  Clone + NPhysicsTransform<LV, AV>>(
+                     ref1: &M,
+                     ref2: &M)
+                     -> AV {
+    (na::inv(ref1).expect("ref1 must be inversible.") * *ref2).rotation()
+}
+
+/// The world-space direction of the `i`-th translation axis of the frame `ref1`.
+fn linear_axis<N:  Clone + NPhysicsScalar,
+               LV: Clone + NPhysicsDirection<N, AV>,
+               AV: Clone + NPhysicsOrientation<N>,
+               M:  Clone + NPhysicsTransform<LV, AV>>(
+               ref1: &M,
+               i:    uint)
+               -> LV {
+    let mut axis: LV = na::zero();
+
+    axis.set(i, na::one());
+
+    ref1.rotate(&axis)
+}
+
+/// The world-space direction of the `i`-th rotation axis of the frame `ref1`.
+fn angular_axis<N:  Clone + NPhysicsScalar,
+                LV: Clone + NPhysicsDirection<N, AV>,
+                AV: Clone + NPhysicsOrientation<N>,
+                M:  Clone + NPhysicsTransform<LV, AV>>(
+                ref1: &M,
+                i:    uint)
+                -> AV {
+    let mut axis: AV = na::zero();
+
+    axis.set(i, na::one());
+
+    cone_twist_equation::local_to_world_axis(ref1, &axis)
+}
+
+/// Fills a linear velocity constraint keeping `value`, the distance between the two anchors
+/// along `axis`, between `min` and `max`.
+///
+/// If the distance is within its limits, the constraint only prevents the nearest one to be
+/// exceeded during the step.
+fn fill_linear_limit<N:  Clone + NPhysicsScalar,
+                     LV: Clone + NPhysicsDirection<N, AV>,
+                     AV: Clone + NPhysicsOrientation<N>,
+                     M:  Clone + NPhysicsTransform<LV, AV>,
+                     II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                     dt:         N,
+                     axis:       LV,
+                     value:      N,
+                     min:        N,
+                     max:        N,
+                     global1:    &LV,
+                     global2:    &LV,
+                     anchor1:    &Anchor<N, LV, AV, M, II, M>,
+                     anchor2:    &Anchor<N, LV, AV, M, II, M>,
+                     constraint: &mut VelocityConstraint<LV, AV, N>,
+                     correction: &CorrectionParameters<N>) {
+    let _M: N = Bounded::max_value();
+
+    // `violated` is false for the limits which are only enforced if they would be exceeded
+    // during the step.
+    let (error, lobound, hibound, violated) =
+        if min == max {
+            (value - max, -_M, _M.clone(), true)
+        }
+        else if value > max {
+            (value - max, -_M, na::zero(), true)
+        }
+        else if value < min {
+            (value - min, na::zero(), _M.clone(), true)
+        }
+        else if value - min < max - value {
+            (value - min, na::zero(), _M.clone(), false)
+        }
+        else {
+            (value - max, -_M, na::zero(), false)
+        };
+
+    let dvel = fill_linear_geometry(dt.clone(), axis, global1, global2, anchor1, anchor2,
+                                    constraint);
+
+    let erp = if violated { correction.joint_corr.clone() } else { na::one() };
+
+    constraint.cfm       = na::zero();
+    constraint.lobound   = lobound;
+    constraint.hibound   = hibound;
+    constraint.objective = -dvel - error * erp / dt;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+/// Fills a linear velocity constraint driving the relative velocity of the anchors along `axis`
+/// toward the motor target velocity.
+fn fill_linear_motor<N:  Clone + NPhysicsScalar,
+                     LV: Clone + NPhysicsDirection<N, AV>,
+                     AV: Clone + NPhysicsOrientation<N>,
+                     M:  Clone + NPhysicsTransform<LV, AV>,
+                     II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                     dt:         N,
+                     axis:       LV,
+                     motor:      &Motor<N>,
+                     global1:    &LV,
+                     global2:    &LV,
+                     anchor1:    &Anchor<N, LV, AV, M, II, M>,
+                     anchor2:    &Anchor<N, LV, AV, M, II, M>,
+                     constraint: &mut VelocityConstraint<LV, AV, N>) {
+    let dvel        = fill_linear_geometry(dt.clone(), axis, global1, global2, anchor1, anchor2,
+                                           constraint);
+    let max_impulse = motor.max_force * dt;
+
+    constraint.cfm       = na::zero();
+    constraint.lobound   = -max_impulse;
+    constraint.hibound   = max_impulse;
+    constraint.objective = -dvel + motor.target_vel;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+/// Fills an angular velocity constraint driving the relative angular velocity of the anchors
+/// around `axis` toward the motor target velocity.
+fn fill_angular_motor<N:  Clone + NPhysicsScalar,
+                      LV: Clone + NPhysicsDirection<N, AV>,
+                      AV: Clone + NPhysicsOrientation<N>,
+                      M:  Clone + NPhysicsTransform<LV, AV>,
+                      II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                      dt:         N,
+                      axis:       AV,
+                      motor:      &Motor<N>,
+                      anchor1:    &Anchor<N, LV, AV, M, II, M>,
+                      anchor2:    &Anchor<N, LV, AV, M, II, M>,
+                      constraint: &mut VelocityConstraint<LV, AV, N>) {
+    let opt_b1  = ball_in_socket_equation::write_anchor_id(anchor1, &mut constraint.id1);
+    let opt_b2  = ball_in_socket_equation::write_anchor_id(anchor2, &mut constraint.id2);
+    let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+    let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+    contact_equation::fill_constraint_geometry(
+        na::zero(),
+        axis.clone(),
+        -axis,
+        opt_rb1,
+        opt_rb2,
+        constraint
+    );
+
+    let ang_vel1    = match opt_rb1 { Some(rb) => rb.ang_vel(), None => na::zero() };
+    let ang_vel2    = match opt_rb2 { Some(rb) => rb.ang_vel(), None => na::zero() };
+    let max_impulse = motor.max_force * dt;
+
+    constraint.cfm       = na::zero();
+    constraint.lobound   = -max_impulse;
+    constraint.hibound   = max_impulse;
+    constraint.objective = na::dot(&(ang_vel2 - ang_vel1), &axis) - motor.target_vel;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+/// Fills the geometry of a linear constraint acting along `axis` between two anchors, and returns
+/// the relative velocity of the anchors along it.
+fn fill_linear_geometry<N:  Clone + NPhysicsScalar,
+                        LV: Clone + NPhysicsDirection<N, AV>,
+                        AV: Clone + NPhysicsOrientation<N>,
+                        M:  Clone + NPhysicsTransform<LV, AV>,
+                        II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                        dt:         N,
+                        axis:       LV,
+                        global1:    &LV,
+                        global2:    &LV,
+                        anchor1:    &Anchor<N, LV, AV, M, II, M>,
+                        anchor2:    &Anchor<N, LV, AV, M, II, M>,
+                        constraint: &mut VelocityConstraint<LV, AV, N>)
+                        -> N {
+    let rot_axis1 = na::cross(&(global1 - anchor1.center_of_mass()), &-axis);
+    let rot_axis2 = na::cross(&(global2 - anchor2.center_of_mass()), &axis);
+
+    let opt_b1  = ball_in_socket_equation::write_anchor_id(anchor1, &mut constraint.id1);
+    let opt_b2  = ball_in_socket_equation::write_anchor_id(anchor2, &mut constraint.id2);
+    let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+    let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+    let dvel = contact_equation::relative_velocity(
+        opt_rb1,
+        opt_rb2,
+        &axis,
+        &rot_axis1,
+        &rot_axis2,
+        &dt);
+
+    contact_equation::fill_constraint_geometry(
+        axis,
+        rot_axis1,
+        rot_axis2,
+        opt_rb1,
+        opt_rb2,
+        constraint
+    );
+
+    dvel
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Translation, Rotation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, Body3d, GenericJoint3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Dynamic, RB};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::generic_joint::{GenericJoint, Free, Limited, Motor};
+
+    // a ball attached at its center to the world by a generic joint with every axis locked
+    fn locked_ball() -> (BodyWorld3d<f64>, @mut Body3d<f64>, @mut GenericJoint3d<f64>) {
+        let mut world = BodyWorld::new();
+
+        let ball = @mut RB(RigidBody::new(Geom::new_ball(0.5f64), 1.0, Dynamic, 0.3, 0.6));
+
+        world.add_body(ball);
+
+        let joint: @mut GenericJoint3d<f64> =
+            @mut GenericJoint::new(Anchor::new(None, na::one()),
+                                   Anchor::new(Some(ball), na::one()));
+
+        world.add_generic_joint(joint);
+
+        (world, ball, joint)
+    }
+
+    fn run(world: &mut BodyWorld3d<f64>, num_steps: uint) {
+        for _ in range(0u, num_steps) {
+            world.step(0.016)
+        }
+    }
+
+    #[test]
+    fn test_generic_joint_slider_with_motor() {
+        let (mut world, ball, joint) = locked_ball();
+
+        joint.set_linear_axis(0, Free);
+        joint.set_linear_motor(0, Some(Motor::new(2.0, 100.0)));
+        ball.to_mut_rigid_body_or_fail().set_lin_vel(Vec3::new(0.0, 1.0, 0.0));
+
+        run(&mut world, 50);
+
+        let rb  = ball.to_rigid_body_or_fail();
+        let pos = rb.transform_ref().translation();
+
+        // the motor drives the free axis, the locked ones do not move
+        assert!(na::norm(&(rb.lin_vel() - Vec3::new(2.0, 0.0, 0.0))) < 1.0e-3);
+        assert!(pos.x > 1.0);
+        assert!(pos.y.abs() < 0.01 && pos.z.abs() < 0.01);
+    }
+
+    #[test]
+    fn test_generic_joint_angular_limit_stops_its_motor() {
+        let (mut world, ball, joint) = locked_ball();
+
+        joint.set_angular_axis(2, Limited(-0.5, 0.5));
+        joint.set_angular_motor(2, Some(Motor::new(1.0, 100.0)));
+
+        // the motor alone would turn the ball by 1.6 radians
+        run(&mut world, 100);
+
+        let angle = joint.anchor2_pos().rotation();
+        let pos   = ball.to_rigid_body_or_fail().transform_ref().translation();
+
+        assert!(angle.z > 0.4 && angle.z < 0.55);
+        assert!(angle.x.abs() < 1.0e-3 && angle.y.abs() < 1.0e-3);
+        assert!(na::norm(&pos) < 1.0e-3);
+    }
+}
@@ -32,11 +32,10 @@ impl<V: Round + Vec<N>, N> ContactIdentifier<V> {
     }
 }
 
-// FIXME: make the fields priv
 pub struct ImpulseCache<N, V> {
-    priv hash_prev:           HashMap<ContactIdentifier<V>, (uint, uint)>,
+    priv hash_prev:           HashMap<ContactIdentifier<V>, uint>,
     priv cache_prev:          ~[N],
-    priv hash_next:           HashMap<ContactIdentifier<V>, (uint, uint)>,
+    priv hash_next:           HashMap<ContactIdentifier<V>, uint>,
     priv cache_next:          ~[N],
     priv step:                N,
     priv impulse_per_contact: uint
@@ -58,45 +57,36 @@ ImpulseCache<N, V> {
         }
     }
 
-    pub fn insert<'a>(&'a mut self, cid: uint, obj1: uint, obj2: uint, center: V) {
-        let id = ContactIdentifier::new(obj1, obj2, center, &self.step);
-        let imp =
+    /// Registers a contact for the current step.
+    ///
+    /// Returns the offset of the impulses this contact had at the previous step (a set of zero
+    /// impulses if it did not exist), and the offset where its new impulses have to be written.
+    pub fn insert(&mut self, obj1: uint, obj2: uint, center: V) -> (uint, uint) {
+        let id   = ContactIdentifier::new(obj1, obj2, center, &self.step);
+        let prev =
             match self.hash_prev.find_copy(&id) {
-                Some((_, i)) => i,
-                None         => 0
+                Some(i) => i,
+                None    => 0
             };
+        let next = self.cache_next.len();
 
-        self.hash_next.insert(id, (cid, imp));
-    }
-
-    pub fn hash<'a>(&'a self) -> &'a HashMap<ContactIdentifier<V>, (uint, uint)> {
-        &'a self.hash_next
-    }
-
-    pub fn hash_mut<'a>(&'a mut self) -> &'a mut HashMap<ContactIdentifier<V>, (uint, uint)> {
-        &'a mut self.hash_next
-    }
-
-    pub fn push_impulsions<'a>(&'a mut self) -> &'a mut [N] {
-        let begin = self.cache_next.len();
-
-        self.impulse_per_contact.times(|| {
+        for _ in range(0u, self.impulse_per_contact) {
             self.cache_next.push(na::zero());
-        });
-
-        let end = self.cache_next.len();
+        }
 
-        self.cache_next.mut_slice(begin, end)
-    }
+        self.hash_next.insert(id, next);
 
-    pub fn reserved_impulse_offset(&self) -> uint {
-        self.impulse_per_contact
+        (prev, next)
     }
 
     pub fn impulsions_at<'a>(&'a self, at: uint) -> &'a [N] {
         self.cache_prev.slice(at, at + self.impulse_per_contact)
     }
 
+    pub fn next_impulsions_at_mut<'a>(&'a mut self, at: uint) -> &'a mut [N] {
+        self.cache_next.mut_slice(at, at + self.impulse_per_contact)
+    }
+
     pub fn len(&self) -> uint {
         self.hash_next.len()
     }
@@ -107,8 +97,8 @@ ImpulseCache<N, V> {
         self.cache_next.clear();
         self.hash_next.clear();
 
-        self.cache_prev.grow_set(self.impulse_per_contact, &na::zero(), na::zero());
-        self.cache_next.grow_set(self.impulse_per_contact, &na::zero(), na::zero());
+        self.cache_prev.grow(self.impulse_per_contact, &na::zero());
+        self.cache_next.grow(self.impulse_per_contact, &na::zero());
     }
 
     pub fn swap(&mut self) {
@@ -0,0 +1,85 @@
+use std::num::{Orderable, Signed};
+use nalgebra::na::Vec;
+use nalgebra::na;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::projected_gauss_seidel_solver::Velocities;
+use pgs = resolution::constraint::projected_gauss_seidel_solver;
+
+/// Solves the velocity constraints with the projected Jacobi algorithm.
+///
+/// Unlike Gauss-Seidel, every constraint of a sweep is solved with the velocities of the previous
+/// sweep so that they could be processed independently (in parallel or with SIMD). This
+/// implementation still processes them serially. It converges more slowly than Gauss-Seidel, and
+/// needs under-relaxation (an `omega` around 0.5 to 0.9) to be stable when many constraints act
+/// on the same body.
+///
+/// Returns the number of sweeps performed and the largest impulse change of the last one.
+pub fn projected_jacobi_solve<LV: Vec<N> + Clone,
+                              AV: Vec<N> + Clone,
+                              N:  Num + Orderable + Signed + Clone>(
+                              restitution:    &mut [VelocityConstraint<LV, AV, N>],
+                              friction:       &mut [VelocityConstraint<LV, AV, N>],
+                              result:         &mut [Velocities<LV, AV>],
+                              num_bodies:     uint,
+                              num_iterations: uint,
+                              tolerance:      &N,
+                              omega:          &N,
+                              is_lambda_zero: bool)
+                              -> (uint, N) {
+    pgs::setup_warmstart(restitution, friction, result, num_bodies, is_lambda_zero);
+
+    let mut prev        = ~[];
+    let mut residual: N = na::zero();
+    let mut num_iter    = 0;
+
+    prev.push_all(result);
+
+    while num_iter < num_iterations {
+        residual = na::zero();
+
+        for (p, r) in prev.mut_iter().zip(result.iter()) {
+            *p = r.clone();
+        }
+
+        for c in restitution.mut_iter() {
+            residual = residual.max(&solve_velocity_constraint(c, prev, result, omega));
+        }
+
+        for c in friction.mut_iter() {
+            if pgs::update_friction_bounds(c, restitution) {
+                residual = residual.max(&solve_velocity_constraint(c, prev, result, omega));
+            }
+        }
+
+        num_iter = num_iter + 1;
+
+        if residual < *tolerance {
+            break;
+        }
+    }
+
+    (num_iter, residual)
+}
+
+/// Solves one constraint using the velocities `prev`, and accumulates its impulse change on
+/// `MJLambda`.
+#[inline(always)]
+fn solve_velocity_constraint<LV: Vec<N> + Clone,
+                             AV: Vec<N> + Clone,
+                             N:  Num + Orderable + Signed + Clone>(
+                             c:        &mut VelocityConstraint<LV, AV, N>,
+                             prev:     &[Velocities<LV, AV>],
+                             MJLambda: &mut [Velocities<LV, AV>],
+                             omega:    &N)
+                             -> N {
+    let d_lambda_i = pgs::velocity_error(c, prev) * c.inv_projected_mass * *omega;
+    let lambda_i_0 = c.impulse.clone();
+
+    c.impulse = (lambda_i_0 + d_lambda_i).clamp(&c.lobound, &c.hibound);
+
+    let d_lambda_i = c.impulse - lambda_i_0;
+
+    pgs::apply_impulse(c, MJLambda, &d_lambda_i);
+
+    d_lambda_i.abs()
+}
@@ -0,0 +1,156 @@
+use std::num::{Zero, Orderable, Signed};
+use nalgebra::na::Vec;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::projected_gauss_seidel_solver::{Velocities, ConstraintOrdering,
+                                                            BlockWorkspace};
+use resolution::constraint::block_gauss_seidel_solver;
+use resolution::constraint::jacobi_solver;
+use resolution::constraint::direct_solver;
+use pgs = resolution::constraint::projected_gauss_seidel_solver;
+
+/// The algorithm used to solve the velocity constraints.
+#[deriving(Eq, ToStr, Clone)]
+pub enum LCPSolver {
+    /// Projected Gauss-Seidel: the constraints are solved one by one.
+    ProjectedGaussSeidel,
+    /// Block Gauss-Seidel: the consecutive constraints acting on the same pair of bodies (e.g.
+    /// the normals of a contact manifold) are solved together, exactly.
+    BlockGaussSeidel,
+    /// Projected Jacobi: every constraint of a sweep is solved independently of the others (but
+    /// still serially). Needs an `omega` smaller than 1.
+    ProjectedJacobi,
+    /// Direct resolution of the whole system with Dantzig's algorithm, the friction bounds being
+    /// deduced from the normal impulses as they are computed. Falls back to projected
+    /// Gauss-Seidel, and reports it, if the algorithm fails. Only suitable for small systems.
+    Dantzig
+}
+
+/// Solves a set of velocity constraints with the given algorithm.
+///
+/// `num_iterations`, `tolerance`, `omega` and `ordering` are the parameters of the iterative
+/// algorithms; they are ignored by those which do not need them (`omega` and `ordering` are only
+/// used by `ProjectedGaussSeidel`, and `omega` by `ProjectedJacobi`). `Dantzig` uses
+/// `num_iterations` and `tolerance` only if it fails.
+///
+/// `workspace` keeps the blocks of `BlockGaussSeidel` between resolutions. `ProjectedGaussSeidel`
+/// uses it to solve the contact manifolds exactly only if `solve_manifolds` is `true`.
+///
+/// Returns the number of iterations (or pivots for `Dantzig`) performed, the final residual, and
+/// `false` if `Dantzig` failed and the system was solved with projected Gauss-Seidel instead.
+pub fn solve<LV: Vec<N> + Zero + Clone,
+             AV: Vec<N> + Zero + Clone,
+             N:  Num + Orderable + Signed + Clone>(
+             solver:          &LCPSolver,
+             restitution:     &mut [VelocityConstraint<LV, AV, N>],
+             friction:        &mut [VelocityConstraint<LV, AV, N>],
+             result:          &mut [Velocities<LV, AV>],
+             num_bodies:      uint,
+             num_iterations:  uint,
+             tolerance:       &N,
+             omega:           &N,
+             ordering:        &ConstraintOrdering<LV>,
+             workspace:       &mut BlockWorkspace<LV, AV, N>,
+             solve_manifolds: bool,
+             is_lambda_zero:  bool)
+             -> (uint, N, bool) {
+    match *solver {
+        ProjectedGaussSeidel => {
+            let manifolds = if solve_manifolds { Some(workspace) } else { None };
+
+            let (num_iter, residual) =
+                pgs::projected_gauss_seidel_solve(restitution, friction, result, num_bodies,
+                                                  num_iterations, tolerance, omega, ordering,
+                                                  manifolds, is_lambda_zero);
+
+            (num_iter, residual, true)
+        },
+        BlockGaussSeidel => {
+            let (num_iter, residual) =
+                block_gauss_seidel_solver::block_gauss_seidel_solve(restitution, friction, result,
+                                                                    num_bodies, num_iterations,
+                                                                    tolerance, workspace,
+                                                                    is_lambda_zero);
+
+            (num_iter, residual, true)
+        },
+        ProjectedJacobi => {
+            let (num_iter, residual) =
+                jacobi_solver::projected_jacobi_solve(restitution, friction, result, num_bodies,
+                                                      num_iterations, tolerance, omega,
+                                                      is_lambda_zero);
+
+            (num_iter, residual, true)
+        },
+        Dantzig => {
+            direct_solver::direct_solve(restitution, friction, result, num_bodies, num_iterations,
+                                        tolerance, is_lambda_zero)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::num::Bounded;
+    use nalgebra::na::Vec3;
+    use resolution::constraint::velocity_constraint::VelocityConstraint;
+    use resolution::constraint::projected_gauss_seidel_solver::{Velocities, Sequential,
+                                                                BlockWorkspace};
+    use super::{solve, LCPSolver, ProjectedGaussSeidel, BlockGaussSeidel, ProjectedJacobi,
+                Dantzig};
+
+    type Constraint = VelocityConstraint<Vec3<f64>, Vec3<f64>, f64>;
+
+    // a constraint between the body 0, of unit mass, and the ground
+    fn ground_constraint(normal: Vec3<f64>, objective: f64, lobound: f64) -> Constraint {
+        let mut c = VelocityConstraint::new();
+
+        c.normal             = normal.clone();
+        c.normal2            = normal.clone();
+        c.weighted_normal1   = normal;
+        c.inv_projected_mass = 1.0;
+        c.lobound            = lobound;
+        c.hibound            = Bounded::max_value();
+        c.objective          = objective;
+        c.id1                = 0;
+
+        c
+    }
+
+    // solves two coupled contacts and a friction row limited by half of the first contact impulse
+    fn solve_with(solver: LCPSolver) -> (~[Constraint], ~[Constraint], bool) {
+        let cos             = 0.9f64;
+        let mut restitution = ~[
+            ground_constraint(Vec3::new(1.0, 0.0, 0.0), 1.0, 0.0),
+            ground_constraint(Vec3::new(cos, (1.0 - cos * cos).sqrt(), 0.0), 1.0, 0.0)
+        ];
+        let mut friction = ~[ground_constraint(Vec3::new(0.0, 0.0, 1.0), 5.0, 0.0)];
+
+        friction[0].friction_limit_id = 0;
+        friction[0].friction_coeff    = 0.5;
+
+        let mut result    = ~[Velocities::new()];
+        let mut workspace = BlockWorkspace::new();
+        let omega         = if solver == ProjectedJacobi { 0.5 } else { 1.0 };
+
+        let (_, _, direct) = solve(&solver, restitution, friction, result, 1, 1000, &1.0e-12,
+                                   &omega, &Sequential, &mut workspace, false, true);
+
+        (restitution, friction, direct)
+    }
+
+    #[test]
+    fn test_every_backend_gives_the_same_solution() {
+        for solver in [ProjectedGaussSeidel, BlockGaussSeidel, ProjectedJacobi, Dantzig].iter() {
+            let (restitution, friction, direct) = solve_with(solver.clone());
+
+            assert!(direct);
+
+            for c in restitution.iter() {
+                assert!((c.impulse - 1.0 / 1.9).abs() < 1.0e-6);
+            }
+
+            // the friction row is orthogonal to the contacts, and saturated
+            assert!((friction[0].impulse - 0.5 / 1.9).abs() < 1.0e-6);
+        }
+    }
+}
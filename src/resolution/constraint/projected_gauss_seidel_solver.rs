@@ -1,7 +1,11 @@
-use std::num::{Zero, Orderable};
+use std::num::{Zero, Orderable, Signed};
+use std::rand::{Rng, SeedableRng, IsaacRng};
+use std::vec;
 use nalgebra::na::Vec;
 use nalgebra::na;
 use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::direct_solver;
+use resolution::constraint::direct_solver::LCPWorkspace;
 
 #[deriving(Eq, ToStr, Clone)]
 pub struct Velocities<LV, AV> {
@@ -23,15 +27,330 @@ impl<LV: Zero, AV: Zero> Velocities<LV, AV> {
     }
 }
 
-pub fn projected_gauss_seidel_solve<LV: Vec<N> + Clone,
-                                    AV: Vec<N> + Clone,
-                                    N:  Num + Orderable + Clone>(
+/// The order in which the constraints are visited at each sweep.
+///
+/// The restitution constraints are always visited before the friction constraints.
+#[deriving(ToStr, Clone)]
+pub enum ConstraintOrdering<LV> {
+    /// The constraints are visited in the order they are given.
+    Sequential,
+    /// The constraints are shuffled before each sweep, using a random generator initialized with
+    /// the given seed at each resolution.
+    Randomized(u32),
+    /// The constraints are visited in the order they are given, and in reverse order on every
+    /// other sweep.
+    Alternating,
+    /// The contacts are visited from the lowest to the highest along the given up direction
+    /// (usually the opposite of the gravity).
+    ///
+    /// The solver does not know where the constraints are: it is up to the caller to give them
+    /// sorted. `projected_gauss_seidel_solve` visits them in sequential order.
+    BottomUp(LV)
+}
+
+/// A set of consecutive restitution constraints acting on the same pair of bodies.
+pub struct Block<N> {
+    begin: uint,
+    end:   uint,
+    A:     ~[N]
+}
+
+/// Solves the velocity constraints with the projected Gauss-Seidel algorithm.
+///
+/// The resolution stops after `num_iterations` sweeps, or as soon as the largest impulse change
+/// of a sweep is smaller than `tolerance` (use zero to always perform every sweep). Each impulse
+/// change is scaled by the relaxation factor `omega` (1 is plain Gauss-Seidel, between 1 and 2
+/// is over-relaxation).
+///
+/// If a workspace is given as `manifolds`, the consecutive restitution constraints forming a
+/// contact manifold of 2 to 4 points are solved together and exactly (see
+/// `BlockWorkspace::solve_block`). This removes most of the rocking of boxes resting on a face.
+/// The sweeps then visit the manifolds, instead of the single restitution constraints, in the
+/// given `ordering`.
+///
+/// Returns the number of sweeps performed and the largest impulse change of the last one.
+pub fn projected_gauss_seidel_solve<LV: Vec<N> + Zero + Clone,
+                                    AV: Vec<N> + Zero + Clone,
+                                    N:  Num + Orderable + Signed + Clone>(
                                     restitution:    &mut [VelocityConstraint<LV, AV, N>],
                                     friction:       &mut [VelocityConstraint<LV, AV, N>],
                                     result:         &mut [Velocities<LV, AV>],
                                     num_bodies:     uint,
                                     num_iterations: uint,
-                                    is_lambda_zero: bool) {
+                                    tolerance:      &N,
+                                    omega:          &N,
+                                    ordering:       &ConstraintOrdering<LV>,
+                                    manifolds:      Option<&mut BlockWorkspace<LV, AV, N>>,
+                                    is_lambda_zero: bool)
+                                    -> (uint, N) {
+    setup_warmstart(restitution, friction, result, num_bodies, is_lambda_zero);
+
+    let mut manifolds = manifolds;
+    let num_units =
+        match manifolds {
+            Some(ref mut ws) => {
+                ws.find_blocks(restitution, num_bodies, true);
+                ws.num_blocks()
+            },
+            None => restitution.len()
+        };
+
+    /*
+     * solve the system
+     */
+    let mut rorder = vec::from_fn(num_units, |i| i);
+    let mut forder = vec::from_fn(friction.len(), |i| i);
+    let mut rng: Option<IsaacRng> =
+        match *ordering {
+            Randomized(seed) => Some(SeedableRng::from_seed(&[seed])),
+            _                => None
+        };
+
+    let mut reversed    = false;
+    let mut residual: N = na::zero();
+    let mut num_iter    = 0;
+
+    while num_iter < num_iterations {
+        match *ordering {
+            Randomized(_) => {
+                let rng = rng.get_mut_ref();
+
+                rng.shuffle_mut(rorder);
+                rng.shuffle_mut(forder);
+            },
+            Alternating => {
+                if num_iter != 0 {
+                    rorder.reverse();
+                    forder.reverse();
+                    reversed = !reversed;
+                }
+            },
+            Sequential | BottomUp(_) => { }
+        }
+
+        residual = na::zero();
+
+        for i in rorder.iter() {
+            let d_lambda =
+                match manifolds {
+                    Some(ref mut ws) => ws.solve_block(*i, restitution, result, omega, reversed),
+                    None             => solve_velocity_constraint(&mut restitution[*i], result, omega)
+                };
+
+            residual = residual.max(&d_lambda);
+        }
+
+        for i in forder.iter() {
+            let c = &mut friction[*i];
+
+            if update_friction_bounds(c, restitution) {
+                residual = residual.max(&solve_velocity_constraint(c, result, omega));
+            }
+        }
+
+        num_iter = num_iter + 1;
+
+        if residual < *tolerance {
+            break;
+        }
+    }
+
+    (num_iter, residual)
+}
+
+
+/// The blocks of constraints of a system, and the buffers used to solve them.
+///
+/// It is kept between resolutions so that none of them is reallocated at each step.
+pub struct BlockWorkspace<LV, AV, N> {
+    priv blocks:     ~[Block<N>],
+    priv num_blocks: uint,
+    priv scratch:    ~[Velocities<LV, AV>],
+    priv b:          ~[N],
+    priv lo:         ~[N],
+    priv hi:         ~[N],
+    priv limits:     ~[Option<(uint, N)>],
+    priv dx:         ~[N],
+    priv lcp:        LCPWorkspace<N>
+}
+
+impl<LV, AV, N> BlockWorkspace<LV, AV, N> {
+    /// Creates an empty workspace. Nothing is allocated until blocks are found.
+    pub fn new() -> BlockWorkspace<LV, AV, N> {
+        BlockWorkspace {
+            blocks:     ~[],
+            num_blocks: 0,
+            scratch:    ~[],
+            b:          ~[],
+            lo:         ~[],
+            hi:         ~[],
+            limits:     ~[],
+            dx:         ~[],
+            lcp:        LCPWorkspace::new()
+        }
+    }
+
+    /// The number of blocks found by the last call to `find_blocks`.
+    pub fn num_blocks(&self) -> uint {
+        self.num_blocks
+    }
+}
+
+impl<LV: Vec<N> + Zero + Clone,
+     AV: Vec<N> + Zero + Clone,
+     N:  Num + Orderable + Signed + Clone>
+BlockWorkspace<LV, AV, N> {
+    /// Groups the consecutive restitution constraints acting on the same pair of bodies, and
+    /// computes the `J M^-1 J^t` matrix of each group with more than one constraint.
+    ///
+    /// If `manifolds_only` is `true`, only the groups which look like contact manifolds (2 to 4
+    /// constraints, all with a zero lower bound) are kept together: the other constraints get a
+    /// block each. The matrices of the previous blocks are reused.
+    pub fn find_blocks(&mut self,
+                       restitution:    &[VelocityConstraint<LV, AV, N>],
+                       num_bodies:     uint,
+                       manifolds_only: bool) {
+        self.num_blocks = 0;
+
+        let mut begin = 0;
+
+        while begin < restitution.len() {
+            let mut end = begin + 1;
+
+            while end < restitution.len() &&
+                  restitution[end].id1 == restitution[begin].id1 &&
+                  restitution[end].id2 == restitution[begin].id2 {
+                end = end + 1;
+            }
+
+            let is_manifold = end - begin >= 2 && end - begin <= 4 &&
+                              restitution.slice(begin, end).iter().all(|c| c.lobound.is_zero());
+
+            if manifolds_only && !is_manifold {
+                for i in range(begin, end) {
+                    self.push_block(restitution, i, i + 1);
+                }
+            }
+            else {
+                if end - begin > 1 && self.scratch.len() != num_bodies {
+                    self.scratch = vec::from_elem(num_bodies, Velocities::new());
+                }
+
+                self.push_block(restitution, begin, end);
+            }
+
+            begin = end;
+        }
+    }
+
+    fn push_block(&mut self, restitution: &[VelocityConstraint<LV, AV, N>], begin: uint, end: uint) {
+        if self.num_blocks == self.blocks.len() {
+            self.blocks.push(Block { begin: 0, end: 0, A: ~[] });
+        }
+
+        let block = &mut self.blocks[self.num_blocks];
+
+        block.begin = begin;
+        block.end   = end;
+
+        if end - begin > 1 {
+            direct_solver::fill_delassus_matrix(restitution.slice(begin, end), self.scratch,
+                                                &mut block.A);
+        }
+        else {
+            block.A.clear();
+        }
+
+        self.num_blocks = self.num_blocks + 1;
+    }
+
+    /// Solves the restitution constraints of the `i`-th block found by `find_blocks`.
+    ///
+    /// The constraints of a block are solved together, exactly, with Dantzig's algorithm (see
+    /// `direct_solver::solve_boxed_lcp`), whose cost is polynomial in the size of the block. The
+    /// exact impulse changes are then scaled by the relaxation factor `omega` and clamped to the
+    /// bounds, like those of the single constraints. The single constraints, and the blocks for
+    /// which Dantzig's algorithm fails, are solved one constraint at a time, in reverse order if
+    /// `reversed` is `true`.
+    ///
+    /// Returns the largest absolute impulse change.
+    pub fn solve_block(&mut self,
+                       i:           uint,
+                       restitution: &mut [VelocityConstraint<LV, AV, N>],
+                       MJLambda:    &mut [Velocities<LV, AV>],
+                       omega:       &N,
+                       reversed:    bool)
+                       -> N {
+        assert!(i < self.num_blocks, "Block index out of bounds.");
+
+        let block = &self.blocks[i];
+        let rows  = restitution.mut_slice(block.begin, block.end);
+        let mut res: N = na::zero();
+
+        if rows.len() > 1 {
+            self.b.clear();
+            self.lo.clear();
+            self.hi.clear();
+            self.limits.clear();
+            self.dx.clear();
+
+            for c in rows.iter() {
+                self.b.push(velocity_error(c, MJLambda));
+                self.lo.push(c.lobound - c.impulse);
+                self.hi.push(c.hibound - c.impulse);
+                self.limits.push(None);
+                self.dx.push(na::zero());
+            }
+
+            let max_pivots = 4 * rows.len() + 10;
+
+            let (_, converged) = direct_solver::solve_boxed_lcp(block.A, self.b, self.lo, self.hi,
+                                                                self.limits, self.dx, max_pivots,
+                                                                &mut self.lcp);
+
+            if converged {
+                for (c, d) in rows.mut_iter().zip(self.dx.iter()) {
+                    let lambda_i_0 = c.impulse.clone();
+
+                    c.impulse = (lambda_i_0 + *d * *omega).clamp(&c.lobound, &c.hibound);
+
+                    let d_lambda_i = c.impulse - lambda_i_0;
+
+                    apply_impulse(c, MJLambda, &d_lambda_i);
+
+                    res = res.max(&d_lambda_i.abs());
+                }
+
+                return res
+            }
+        }
+
+        // single constraint, or degenerate block: solve the constraints one by one
+        if reversed {
+            for c in rows.mut_rev_iter() {
+                res = res.max(&solve_velocity_constraint(c, MJLambda, omega));
+            }
+        }
+        else {
+            for c in rows.mut_iter() {
+                res = res.max(&solve_velocity_constraint(c, MJLambda, omega));
+            }
+        }
+
+        res
+    }
+}
+
+/// Initializes the solution with zeros, and applies the current impulses of the constraints if
+/// `is_lambda_zero` is `false`.
+pub fn setup_warmstart<LV: Vec<N> + Clone,
+                       AV: Vec<N> + Clone,
+                       N:  Num + Orderable + Clone>(
+                       restitution:    &[VelocityConstraint<LV, AV, N>],
+                       friction:       &[VelocityConstraint<LV, AV, N>],
+                       result:         &mut [Velocities<LV, AV>],
+                       num_bodies:     uint,
+                       is_lambda_zero: bool) {
     // initialize the solution with zeros...
     // MJLambda is result
     assert!(result.len() == num_bodies);
@@ -50,27 +369,6 @@ pub fn projected_gauss_seidel_solve<LV: Vec<N> + Clone,
             setup_warmstart_for_constraint(c, result);
         }
     }
-
-    /*
-     * solve the system
-     */
-    num_iterations.times(|| {
-        for c in restitution.mut_iter() {
-            solve_velocity_constraint(c, result);
-        }
-
-        for c in friction.mut_iter() {
-            let impulse = restitution[c.friction_limit_id].impulse.clone();
-
-            if impulse > na::zero() {
-                let bound = c.friction_coeff * impulse;
-                c.lobound = -bound;
-                c.hibound = bound;
-
-                solve_velocity_constraint(c, result);
-            }
-        }
-    });
 }
 
 #[inline(always)]
@@ -79,42 +377,108 @@ pub fn setup_warmstart_for_constraint<LV: Vec<N> + Clone,
                                       N:  Num + Orderable + Clone>(
                                       c:        &VelocityConstraint<LV, AV, N>,
                                       MJLambda: &mut [Velocities<LV, AV>]) {
+    apply_impulse(c, MJLambda, &c.impulse)
+}
+
+/// Sets the bounds of a friction constraint from the impulse of its restitution constraint.
+///
+/// Returns `false` if the friction constraint is inactive (the contact does not push).
+#[inline(always)]
+pub fn update_friction_bounds<LV, AV, N: Num + Orderable + Clone>(
+                              c:           &mut VelocityConstraint<LV, AV, N>,
+                              restitution: &[VelocityConstraint<LV, AV, N>])
+                              -> bool {
+    let impulse = restitution[c.friction_limit_id].impulse.clone();
+
+    if impulse > na::zero() {
+        let bound = c.friction_coeff * impulse;
+        c.lobound = -bound;
+        c.hibound = bound;
+
+        true
+    }
+    else {
+        false
+    }
+}
+
+/// The relative velocity of the bodies along a constraint.
+#[inline(always)]
+pub fn constraint_velocity<LV: Vec<N> + Clone,
+                           AV: Vec<N> + Clone,
+                           N:  Num + Clone>(
+                           c:        &VelocityConstraint<LV, AV, N>,
+                           MJLambda: &[Velocities<LV, AV>])
+                           -> N {
     let id1 = c.id1;
     let id2 = c.id2;
 
+    let mut res: N = na::zero();
+
     if id1 >= 0 {
-        MJLambda[id1 as uint].lv = MJLambda[id1 as uint].lv - c.weighted_normal1 * c.impulse;
-        MJLambda[id1 as uint].av = MJLambda[id1 as uint].av + c.weighted_rot_axis1 * c.impulse;
+        res = res + na::dot(&c.normal, &MJLambda[id1 as uint].lv)
+                  - na::dot(&c.rot_axis1, &MJLambda[id1 as uint].av);
     }
 
     if id2 >= 0 {
-        MJLambda[id2 as uint].lv = MJLambda[id2 as uint].lv + c.weighted_normal2 * c.impulse;
-        MJLambda[id2 as uint].av = MJLambda[id2 as uint].av + c.weighted_rot_axis2 * c.impulse;
+        res = res - na::dot(&c.normal2, &MJLambda[id2 as uint].lv)
+                  - na::dot(&c.rot_axis2, &MJLambda[id2 as uint].av);
     }
+
+    res
 }
 
+/// The velocity error of a constraint, i.e. its objective minus its current relative velocity,
+/// relaxed by its constraint force mixing.
+///
+/// Multiplied by the inverse projected mass, this is the impulse change which solves the
+/// constraint.
 #[inline(always)]
-pub fn solve_velocity_constraint<LV: Vec<N> + Clone,
-                                 AV: Vec<N> + Clone,
-                                 N:  Num + Orderable + Clone>(
-                                 c:        &mut VelocityConstraint<LV, AV, N>,
-                                 MJLambda: &mut [Velocities<LV, AV>]) {
+pub fn velocity_error<LV: Vec<N> + Clone,
+                      AV: Vec<N> + Clone,
+                      N:  Num + Clone>(
+                      c:        &VelocityConstraint<LV, AV, N>,
+                      MJLambda: &[Velocities<LV, AV>])
+                      -> N {
+    c.objective + constraint_velocity(c, MJLambda) - c.cfm * c.impulse
+}
+
+/// Applies an impulse along a constraint to the velocity changes of its bodies.
+#[inline(always)]
+pub fn apply_impulse<LV: Vec<N> + Clone,
+                     AV: Vec<N> + Clone,
+                     N:  Num + Clone>(
+                     c:        &VelocityConstraint<LV, AV, N>,
+                     MJLambda: &mut [Velocities<LV, AV>],
+                     impulse:  &N) {
     let id1 = c.id1;
     let id2 = c.id2;
 
-    let mut d_lambda_i = c.objective.clone();
-
     if id1 >= 0 {
-        d_lambda_i = d_lambda_i + na::dot(&c.normal, &MJLambda[id1 as uint].lv)
-                                - na::dot(&c.rot_axis1, &MJLambda[id1 as uint].av);
+        MJLambda[id1 as uint].lv = MJLambda[id1 as uint].lv - c.weighted_normal1 * *impulse;
+        MJLambda[id1 as uint].av = MJLambda[id1 as uint].av + c.weighted_rot_axis1 * *impulse;
     }
 
     if id2 >= 0 {
-        d_lambda_i = d_lambda_i - na::dot(&c.normal, &MJLambda[id2 as uint].lv)
-                                - na::dot(&c.rot_axis2, &MJLambda[id2 as uint].av);
+        MJLambda[id2 as uint].lv = MJLambda[id2 as uint].lv + c.weighted_normal2 * *impulse;
+        MJLambda[id2 as uint].av = MJLambda[id2 as uint].av + c.weighted_rot_axis2 * *impulse;
     }
+}
+
+/// Solves one velocity constraint and returns the absolute value of its impulse change.
+///
+/// The impulse change is scaled by the relaxation factor `omega` before being clamped.
+#[inline(always)]
+pub fn solve_velocity_constraint<LV: Vec<N> + Clone,
+                                 AV: Vec<N> + Clone,
+                                 N:  Num + Orderable + Signed + Clone>(
+                                 c:        &mut VelocityConstraint<LV, AV, N>,
+                                 MJLambda: &mut [Velocities<LV, AV>],
+                                 omega:    &N)
+                                 -> N {
+    let mut d_lambda_i = velocity_error(c, MJLambda);
 
-    d_lambda_i = d_lambda_i * c.inv_projected_mass;
+    d_lambda_i = d_lambda_i * c.inv_projected_mass * *omega;
 
     // clamp the value such that: lambda- <= lambda <= lambda+
     // (this is the ``projected'' flavour of Gauss-Seidel
@@ -124,25 +488,170 @@ pub fn solve_velocity_constraint<LV: Vec<N> + Clone,
 
     d_lambda_i = c.impulse - lambda_i_0;
 
+    apply_impulse(c, MJLambda, &d_lambda_i);
 
-    if id1 >= 0 {
-        MJLambda[id1 as uint].lv = MJLambda[id1 as uint].lv - c.weighted_normal1 * d_lambda_i;
-        MJLambda[id1 as uint].av = MJLambda[id1 as uint].av + c.weighted_rot_axis1 * d_lambda_i;
-    }
-
-    if id2 >= 0 {
-        MJLambda[id2 as uint].lv = MJLambda[id2 as uint].lv + c.weighted_normal2 * d_lambda_i;
-        MJLambda[id2 as uint].av = MJLambda[id2 as uint].av + c.weighted_rot_axis2 * d_lambda_i;
-    }
+    d_lambda_i.abs()
 }
 
 #[cfg(test)]
 mod test {
-    use super::projected_gauss_seidel_solve;
+    use std::num::Bounded;
+    use std::vec;
+    use super::{projected_gauss_seidel_solve, Velocities, ConstraintOrdering, Sequential,
+                Randomized, Alternating, BlockWorkspace};
     use extra::test::BenchHarness;
     use nalgebra::vec::{Vec3, AlgebraicVec};
     use resolution::constraint::velocity_constraint::VelocityConstraint;
 
+    type Constraint = VelocityConstraint<Vec3<f64>, Vec3<f64>, f64>;
+
+    // a non-penetration constraint between the body 0, of unit mass, and the ground
+    fn ground_contact(normal: Vec3<f64>, objective: f64) -> Constraint {
+        let mut c = VelocityConstraint::new();
+
+        c.normal             = normal.clone();
+        c.normal2            = normal.clone();
+        c.weighted_normal1   = normal;
+        c.inv_projected_mass = 1.0;
+        c.lobound            = 0.0;
+        c.hibound            = Bounded::max_value();
+        c.objective          = objective;
+        c.id1                = 0;
+
+        c
+    }
+
+    // two contacts whose normals are almost parallel, so that Gauss-Seidel converges slowly
+    fn coupled_contacts() -> ~[Constraint] {
+        let cos = 0.9f64;
+
+        ~[
+            ground_contact(Vec3::new(1.0, 0.0, 0.0), 1.0),
+            ground_contact(Vec3::new(cos, (1.0 - cos * cos).sqrt(), 0.0), 1.0)
+        ]
+    }
+
+    fn solve(restitution: &mut [Constraint], tolerance: f64, omega: f64) -> (uint, f64) {
+        solve_ordered(restitution, tolerance, omega, &Sequential)
+    }
+
+    fn solve_ordered(restitution: &mut [Constraint],
+                     tolerance:   f64,
+                     omega:       f64,
+                     ordering:    &ConstraintOrdering<Vec3<f64>>)
+                     -> (uint, f64) {
+        let mut friction = ~[];
+        let mut result   = ~[Velocities::new()];
+
+        projected_gauss_seidel_solve(restitution, friction, result, 1, 1000, &tolerance, &omega,
+                                     ordering, None, true)
+    }
+
+    fn assert_coupled_solution(constraints: &[Constraint]) {
+        for c in constraints.iter() {
+            assert!((c.impulse - 1.0 / 1.9).abs() < 1.0e-6);
+        }
+    }
+
+    #[test]
+    fn test_pgs_stops_when_the_impulses_stop_changing() {
+        let mut constraints = ~[ground_contact(Vec3::new(0.0, 1.0, 0.0), 2.0)];
+
+        // the first sweep solves the constraint, the second one changes nothing
+        assert!(solve(constraints, 1.0e-10, 1.0) == (2, 0.0));
+        assert!(constraints[0].impulse == 2.0);
+
+        // a zero tolerance performs every sweep
+        let mut constraints = ~[ground_contact(Vec3::new(0.0, 1.0, 0.0), 2.0)];
+        let (num_iter, _)   = solve(constraints, 0.0, 1.0);
+
+        assert!(num_iter == 1000);
+    }
+
+    #[test]
+    fn test_pgs_converges_on_coupled_constraints() {
+        let mut constraints = coupled_contacts();
+        let (num_iter, residual) = solve(constraints, 1.0e-8, 1.0);
+
+        assert!(num_iter < 1000);
+        assert!(residual < 1.0e-8);
+        assert_coupled_solution(constraints);
+    }
+
+    #[test]
+    fn test_over_relaxation_speeds_up_coupled_constraints() {
+        let mut gs  = coupled_contacts();
+        let mut sor = coupled_contacts();
+
+        let (gs_iter, _)  = solve(gs, 1.0e-8, 1.0);
+        let (sor_iter, _) = solve(sor, 1.0e-8, 1.4);
+
+        assert!(sor_iter < gs_iter);
+        assert_coupled_solution(sor);
+    }
+
+    #[test]
+    fn test_under_relaxation_scales_the_impulse_changes() {
+        let mut constraints = ~[ground_contact(Vec3::new(0.0, 1.0, 0.0), 2.0)];
+        let mut friction    = ~[];
+        let mut result      = ~[Velocities::new()];
+
+        projected_gauss_seidel_solve(constraints, friction, result, 1, 1, &0.0, &0.5, &Sequential,
+                                     None, true);
+
+        assert!(constraints[0].impulse == 1.0);
+    }
+
+    #[test]
+    fn test_every_ordering_converges_to_the_same_solution() {
+        let mut alternating = coupled_contacts();
+        let mut random1     = coupled_contacts();
+        let mut random2     = coupled_contacts();
+
+        solve_ordered(alternating, 1.0e-8, 1.0, &Alternating);
+        solve_ordered(random1, 1.0e-8, 1.0, &Randomized(42));
+        solve_ordered(random2, 1.0e-8, 1.0, &Randomized(42));
+
+        assert_coupled_solution(alternating);
+        assert_coupled_solution(random1);
+        // the same seed gives the same sweeps
+        assert!(random1 == random2);
+    }
+
+    #[test]
+    fn test_manifolds_are_found_and_solved_exactly() {
+        let mut constraints = coupled_contacts();
+        // not a manifold: another body, and a negative lower bound
+        let mut joint = ground_contact(Vec3::new(0.0, 1.0, 0.0), 1.0);
+
+        joint.id1     = 1;
+        joint.lobound = -1.0;
+
+        constraints.push(joint.clone());
+        constraints.push(joint);
+
+        let mut friction  = ~[];
+        let mut result    = ~[Velocities::new(), Velocities::new()];
+        let mut workspace = BlockWorkspace::new();
+
+        workspace.find_blocks(constraints, 2, true);
+
+        assert!(workspace.num_blocks() == 3);
+
+        // a single sweep solves the manifold
+        projected_gauss_seidel_solve(constraints, friction, result, 2, 1, &0.0, &1.0, &Sequential,
+                                     Some(&mut workspace), true);
+
+        for c in constraints.slice_to(2).iter() {
+            assert!((c.impulse - 1.0 / 1.9).abs() < 1.0e-10);
+        }
+
+        // the blocks are kept with the manifolds only
+        workspace.find_blocks(constraints, 2, false);
+
+        assert!(workspace.num_blocks() == 2);
+    }
+
     #[bench]
     fn bench_pgs(bh: &mut BenchHarness) {
         let mut constraints = ~[];
@@ -152,6 +661,7 @@ mod test {
             let mut constraint = VelocityConstraint::new();
 
             constraint.normal = Vec3::new(i as f64 * 1.0, 3.0f64, i as f64).normalized();
+            constraint.normal2 = constraint.normal.clone();
             constraint.weighted_normal1 = Vec3::new(4.0, 3.0f64, i as f64);
             constraint.weighted_normal2 = Vec3::new(4.0, 3.0f64, i as f64);
 
@@ -173,10 +683,12 @@ mod test {
             constraints.push(constraint);
         }
 
-        let mut empty = ~[];
+        let mut empty  = ~[];
+        let mut result = vec::from_elem(1000, Velocities::new());
 
         do bh.iter {
-            projected_gauss_seidel_solve(constraints, empty, 1000, 40, false);
+            projected_gauss_seidel_solve(constraints, empty, result, 1000, 40, &0.0, &1.0,
+                                         &Sequential, None, false);
         }
     }
 }
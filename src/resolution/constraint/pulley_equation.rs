@@ -0,0 +1,149 @@
+use std::num::Bounded;
+use nalgebra::na;
+use detection::joint::pulley::Pulley;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use resolution::constraint::contact_equation::CorrectionParameters;
+use resolution::constraint::contact_equation;
+use resolution::constraint::ball_in_socket_equation;
+use resolution::constraint::distance_equation;
+use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform,
+                      NPhysicsInertia};
+
+/// Fills the single velocity constraint of a pulley.
+///
+/// Each rope pulls its anchor toward its ground point, so the constraint acts along a different
+/// direction on each body. The rope never pushes: while it is slack, the constraint only prevents
+/// the weighted length from exceeding the rest length during the step.
+pub fn fill_second_order_equation<N:  Clone + NPhysicsScalar,
+                                  LV: Clone + NPhysicsDirection<N, AV>,
+                                  AV: Clone + NPhysicsOrientation<N>,
+                                  M:  Clone + NPhysicsTransform<LV, AV>,
+                                  II: Clone + NPhysicsInertia<N, LV, AV, M>>(
+                                  dt:          N,
+                                  joint:       &Pulley<N, LV, AV, M, II>,
+                                  constraints: &mut [VelocityConstraint<LV, AV, N>],
+                                  correction:  &CorrectionParameters<N>) {
+    let constraint = &mut constraints[0];
+    let global1    = joint.anchor1_pos();
+    let global2    = joint.anchor2_pos();
+    let ratio      = joint.ratio();
+
+    // directions from the ground points to the anchors
+    let (axis1, length1) = distance_equation::axis_and_length(joint.ground1(), &global1);
+    let (axis2, length2) = distance_equation::axis_and_length(joint.ground2(), &global2);
+
+    let anchor1   = joint.anchor1();
+    let anchor2   = joint.anchor2();
+    let normal1   = -axis1;
+    let normal2   = axis2 * ratio;
+    let rot_axis1 = na::cross(&(global1 - anchor1.center_of_mass()), &-normal1);
+    let rot_axis2 = na::cross(&(global2 - anchor2.center_of_mass()), &normal2);
+
+    let opt_b1  = ball_in_socket_equation::write_anchor_id(anchor1, &mut constraint.id1);
+    let opt_b2  = ball_in_socket_equation::write_anchor_id(anchor2, &mut constraint.id2);
+    let opt_rb1 = match opt_b1 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+    let opt_rb2 = match opt_b2 { Some(b) => Some(b.to_rigid_body_or_fail()), None => None };
+
+    // the rate of change of the weighted rope length
+    let dvel = contact_equation::relative_velocity(opt_rb1, None, &normal1, &rot_axis1,
+                                                   &rot_axis2, &dt) +
+               contact_equation::relative_velocity(None, opt_rb2, &normal2, &rot_axis1,
+                                                   &rot_axis2, &dt);
+
+    contact_equation::fill_coupling_constraint_geometry(
+        normal1,
+        normal2,
+        rot_axis1,
+        rot_axis2,
+        opt_rb1,
+        opt_rb2,
+        constraint
+    );
+
+    let error = length1 + length2 * ratio - joint.length();
+    let _M: N = Bounded::max_value();
+    let erp   = if error > na::zero() { correction.joint_corr.clone() } else { na::one() };
+
+    constraint.cfm       = na::zero();
+    constraint.lobound   = -_M;
+    constraint.hibound   = na::zero();
+    constraint.objective = -dvel - error * erp / dt;
+    constraint.impulse   = na::zero(); // FIXME: cache
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::{Vec3, Translation};
+    use nalgebra::na;
+    use ncollide::geom::Geom;
+    use aliases::dim3::{BodyWorld3d, Body3d, Pulley3d};
+    use world::BodyWorld;
+    use object::{RigidBody, Dynamic, RB};
+    use detection::joint::anchor::Anchor;
+    use detection::joint::pulley::Pulley;
+
+    // two balls hanging two units below their ground points, at `(-2, 2, 0)` and `(2, 2, 0)`,
+    // from a pulley with a ratio of 2
+    fn hanging_balls(length: f64)
+                     -> (BodyWorld3d<f64>, @mut Body3d<f64>, @mut Body3d<f64>, @mut Pulley3d<f64>) {
+        let mut world = BodyWorld::new();
+
+        world.set_gravity(Vec3::new(0.0f64, -9.81, 0.0));
+
+        let mut rb1 = RigidBody::new(Geom::new_ball(0.1f64), 1.0, Dynamic, 0.3, 0.6);
+        let mut rb2 = RigidBody::new(Geom::new_ball(0.1f64), 1.0, Dynamic, 0.3, 0.6);
+
+        rb1.append_translation(&Vec3::new(-2.0, 0.0, 0.0));
+        rb2.append_translation(&Vec3::new(2.0, 0.0, 0.0));
+
+        let b1 = @mut RB(rb1);
+        let b2 = @mut RB(rb2);
+
+        world.add_body(b1);
+        world.add_body(b2);
+
+        let pulley: @mut Pulley3d<f64> =
+            @mut Pulley::new(Anchor::new(Some(b1), na::zero()), Anchor::new(Some(b2), na::zero()),
+                             Vec3::new(-2.0, 2.0, 0.0), Vec3::new(2.0, 2.0, 0.0), 2.0, length);
+
+        world.add_pulley(pulley);
+
+        (world, b1, b2, pulley)
+    }
+
+    fn run(world: &mut BodyWorld3d<f64>, num_steps: uint) {
+        for _ in range(0u, num_steps) {
+            world.step(0.016)
+        }
+    }
+
+    #[test]
+    fn test_taut_pulley_applies_its_ratio() {
+        // the rope is taut: 2 + 2 * 2
+        let (mut world, b1, b2, pulley) = hanging_balls(6.0);
+
+        run(&mut world, 50);
+
+        let v1      = b1.to_rigid_body_or_fail().lin_vel();
+        let v2      = b2.to_rigid_body_or_fail().lin_vel();
+        let length1 = na::norm(&(pulley.anchor1_pos() - *pulley.ground1()));
+        let length2 = na::norm(&(pulley.anchor2_pos() - *pulley.ground2()));
+
+        // the first ball goes down twice as fast as the second one goes up
+        assert!(v1.y < -1.0);
+        assert!((v1.y + 2.0 * v2.y).abs() < 0.05);
+        assert!((length1 + 2.0 * length2 - 6.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn test_slack_pulley_does_not_pull() {
+        let (mut world, b1, b2, _) = hanging_balls(10.0);
+
+        run(&mut world, 10);
+
+        // both balls fall freely
+        for b in [b1, b2].iter() {
+            assert!((b.to_rigid_body_or_fail().lin_vel().y + 9.81 * 0.16).abs() < 1.0e-6);
+        }
+    }
+}
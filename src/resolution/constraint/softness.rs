@@ -0,0 +1,112 @@
+use std::num::Zero;
+use nalgebra::na;
+use resolution::constraint::velocity_constraint::VelocityConstraint;
+use aliases::traits::NPhysicsScalar;
+
+/// Parameters making a constraint behave like a damped spring instead of a rigid link.
+///
+/// They are converted, at each time step, into an error reduction parameter (ERP, the fraction
+/// of the positional error corrected during the step) and a constraint force mixing (CFM, which
+/// lets the constraint be violated proportionally to the impulse it applies). The spring
+/// stiffness and damping are deduced from the effective mass of the constraint, so the same
+/// parameters give the same behaviour whatever the masses of the bodies.
+#[deriving(Eq, ToStr, Clone)]
+pub struct Softness<N> {
+    /// The natural frequency of the spring, in Hertz.
+    frequency:     N,
+    /// The damping ratio: 1 for critical damping, smaller values make the spring oscillate.
+    damping_ratio: N
+}
+
+impl<N: Clone + NPhysicsScalar> Softness<N> {
+    /// Creates the parameters of a spring with the given natural frequency and damping ratio.
+    pub fn new(frequency: N, damping_ratio: N) -> Softness<N> {
+        assert!(frequency > na::zero(), "The frequency of a soft constraint must be positive.");
+        assert!(damping_ratio >= na::zero(), "The damping ratio must not be negative.");
+
+        Softness {
+            frequency:     frequency,
+            damping_ratio: damping_ratio
+        }
+    }
+
+    /// The fraction of the positional error corrected during a time step of length `dt`.
+    pub fn erp(&self, dt: &N) -> N {
+        let hw: N = *dt * self.angular_frequency();
+
+        hw / (self.damping_ratio * na::cast(2.0) + hw)
+    }
+
+    /// Turns a constraint into a spring.
+    ///
+    /// `constraint` must have been filled by `contact_equation::fill_constraint_geometry`: its
+    /// inverse projected mass is scaled down and its constraint force mixing is set. Its
+    /// objective must use the error reduction parameter `self.erp(dt)`.
+    pub fn soften<LV, AV>(&self, dt: &N, constraint: &mut VelocityConstraint<LV, AV, N>) {
+        if constraint.inv_projected_mass.is_zero() {
+            return
+        }
+
+        let hw: N = *dt * self.angular_frequency();
+        let d     = hw * (self.damping_ratio * na::cast(2.0) + hw);
+        let _1: N = na::one();
+
+        constraint.cfm                = _1 / (constraint.inv_projected_mass * d);
+        constraint.inv_projected_mass = constraint.inv_projected_mass * d / (_1 + d);
+    }
+
+    #[inline]
+    fn angular_frequency(&self) -> N {
+        let two_pi: N = Real::two_pi();
+
+        two_pi * self.frequency
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use nalgebra::na::Vec3;
+    use resolution::constraint::velocity_constraint::VelocityConstraint;
+    use resolution::constraint::projected_gauss_seidel_solver::Velocities;
+    use pgs = resolution::constraint::projected_gauss_seidel_solver;
+    use super::Softness;
+
+    #[test]
+    fn test_soft_constraint_is_an_implicit_damped_spring() {
+        let dt       = 0.01f64;
+        let error    = 0.1f64;
+        let softness = Softness::new(2.0f64, 0.5);
+        let mut c: VelocityConstraint<Vec3<f64>, Vec3<f64>, f64> = VelocityConstraint::new();
+
+        // a unit mass body, at rest, `error` away from its target position
+        c.normal             = Vec3::new(1.0, 0.0, 0.0);
+        c.weighted_normal1   = Vec3::new(1.0, 0.0, 0.0);
+        c.inv_projected_mass = 1.0;
+        c.lobound            = -1000.0;
+        c.hibound            = 1000.0;
+        c.objective          = softness.erp(&dt) * error / dt;
+        c.id1                = 0;
+
+        softness.soften(&dt, &mut c);
+
+        let mut result = ~[Velocities::new()];
+
+        pgs::solve_velocity_constraint(&mut c, result, &1.0);
+
+        // the impulse of a spring of the same frequency and damping ratio, integrated implicitly
+        let pi: f64   = Real::pi();
+        let w         = 2.0 * pi * 2.0;
+        let stiffness = w * w;
+        let damping   = 2.0 * 0.5 * w;
+        let expected  = dt * stiffness * error / (1.0 + dt * damping + dt * dt * stiffness);
+
+        assert!((c.impulse - expected).abs() < 1.0e-10);
+    }
+
+    #[test]
+    fn test_stiff_springs_are_almost_rigid() {
+        let softness = Softness::new(1.0e6f64, 1.0);
+
+        assert!(softness.erp(&0.01) > 0.999);
+    }
+}
@@ -4,6 +4,7 @@ use nalgebra::na;
 #[deriving(Eq, ToStr, Clone)]
 pub struct VelocityConstraint<LV, AV, N> {
     normal:             LV,
+    normal2:            LV, // differs from `normal` only for rows coupling two directions
 
     weighted_normal1:   LV,
     weighted_normal2:   LV,
@@ -15,6 +16,7 @@ pub struct VelocityConstraint<LV, AV, N> {
     weighted_rot_axis2: AV,
 
     inv_projected_mass: N,
+    cfm:                N,
 
     impulse:            N,
     lobound:            N,
@@ -30,6 +32,7 @@ impl<LV: Zero, AV: Zero, N: Zero> VelocityConstraint<LV, AV, N> {
     pub fn new() -> VelocityConstraint<LV, AV, N> {
         VelocityConstraint {
             normal:             na::zero(),
+            normal2:            na::zero(),
 
             weighted_normal1:   na::zero(),
             weighted_normal2:   na::zero(),
@@ -41,6 +44,7 @@ impl<LV: Zero, AV: Zero, N: Zero> VelocityConstraint<LV, AV, N> {
             weighted_rot_axis2: na::zero(),
 
             inv_projected_mass: na::zero(),
+            cfm:                na::zero(),
 
             impulse:            na::zero(),
             hibound:            na::zero(),
@@ -1,6 +1,10 @@
 // NOTE: we re-export here only the things commonly used by the user
 pub use resolution::constraint::accumulated_impulse_solver::AccumulatedImpulseSolver;
-pub use resolution::constraint::contact_equation::{VelocityAndPosition, Velocity, VelocityAndPositionThresold};
+pub use resolution::constraint::contact_equation::{VelocityAndPosition, Velocity, VelocityAndPositionThresold,
+                                                  SplitImpulse};
+pub use resolution::constraint::lcp_solver::{LCPSolver, ProjectedGaussSeidel, BlockGaussSeidel, ProjectedJacobi,
+                                             Dantzig};
+pub use resolution::constraint::softness::Softness;
 
 
 pub mod solver;
@@ -8,9 +12,19 @@ pub mod solver;
 pub mod constraint {
     pub mod impulse_cache;
     pub mod accumulated_impulse_solver;
+    pub mod lcp_solver;
     pub mod projected_gauss_seidel_solver;
+    pub mod block_gauss_seidel_solver;
+    pub mod jacobi_solver;
+    pub mod direct_solver;
     pub mod velocity_constraint;
+    pub mod softness;
     pub mod contact_equation;
     pub mod ball_in_socket_equation;
     pub mod fixed_equation;
+    pub mod distance_equation;
+    pub mod pulley_equation;
+    pub mod gear_equation;
+    pub mod generic_joint_equation;
+    pub mod cone_twist_equation;
 }
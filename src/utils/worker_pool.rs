@@ -0,0 +1,82 @@
+//! Tasks kept alive between steps to process batches of jobs.
+
+use std::task;
+use std::comm::{Port, Chan};
+
+/// A set of tasks applying the same function to each job of the batches they receive.
+///
+/// The tasks are spawned once by `new` and exit when the pool is dropped.
+pub struct WorkerPool<T> {
+    priv workers: ~[(Chan<~[T]>, Port<~[T]>)],
+    priv work:    fn(&mut T)
+}
+
+impl<T: 'static + Send> WorkerPool<T> {
+    /// Spawns `num_workers` tasks applying `work` to the jobs they receive.
+    pub fn new(num_workers: uint, work: fn(&mut T)) -> WorkerPool<T> {
+        let mut workers = ~[];
+
+        for _ in range(0u, num_workers) {
+            let (job_port, job_chan) = Chan::new();
+            let (res_port, res_chan) = Chan::new();
+
+            do task::spawn {
+                // `recv_opt` returns `None` once the pool, and thus `job_chan`, is dropped
+                loop {
+                    match job_port.recv_opt() {
+                        Some(batch) => {
+                            let mut batch = batch;
+
+                            for job in batch.mut_iter() {
+                                work(job)
+                            }
+
+                            res_chan.send(batch)
+                        },
+                        None => break
+                    }
+                }
+            }
+
+            workers.push((job_chan, res_port));
+        }
+
+        WorkerPool {
+            workers: workers,
+            work:    work
+        }
+    }
+
+    /// The number of tasks of this pool.
+    pub fn num_workers(&self) -> uint {
+        self.workers.len()
+    }
+
+    /// Applies the work function to every job and returns them in the same order.
+    ///
+    /// The first batch is processed by the calling task, and each other one by its own worker, so
+    /// there must be at most `self.num_workers() + 1` batches.
+    pub fn run(&self, batches: ~[~[T]]) -> ~[T] {
+        assert!(batches.len() <= self.workers.len() + 1, "Not enough workers for those batches.");
+
+        let mut batches  = batches.move_iter();
+        let mut res      = batches.next().unwrap_or(~[]);
+        let mut num_sent = 0;
+
+        for (batch, &(ref chan, _)) in batches.zip(self.workers.iter()) {
+            chan.send(batch);
+            num_sent = num_sent + 1;
+        }
+
+        for job in res.mut_iter() {
+            (self.work)(job)
+        }
+
+        // collect the results in the same order as they were sent
+        for &(_, ref port) in self.workers.slice_to(num_sent).iter() {
+            res.push_all_move(port.recv());
+        }
+
+        res
+    }
+}
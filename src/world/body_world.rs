@@ -4,19 +4,24 @@ use ncollide::bounding_volume::AABB;
 use ncollide::broad::DBVTBroadPhase;
 use ncollide::ray::Ray;
 use integration::{Integrator, BodyForceGenerator, BodySmpEulerIntegrator, SweptBallMotionClamping};
-use detection::collision::bodies_bodies::PairwiseDetector;
+use detection::collision::bodies_bodies::{PairwiseDetector, RayHit};
 use detection::{BodiesBodies, BodiesBodiesDispatcher};
 use detection::detector::Detector;
 use detection::constraint::Constraint;
 use detection::joint::joint_manager::JointManager;
 use detection::joint::ball_in_socket::BallInSocket;
 use detection::joint::fixed::Fixed;
+use detection::joint::distance::Distance;
+use detection::joint::cone_twist::ConeTwist;
+use detection::joint::generic_joint::GenericJoint;
+use detection::joint::gear::Gear;
+use detection::joint::pulley::Pulley;
 use detection::IslandActivationManager;
 use resolution::{AccumulatedImpulseSolver, VelocityAndPosition};
 use resolution::solver::Solver;
-use world::World;
+use world::{World, StepStatistics};
 use aliases::traits::{NPhysicsScalar, NPhysicsDirection, NPhysicsOrientation, NPhysicsTransform, NPhysicsInertia};
-use object::Body;
+use object::{Body, RB, SB, MB};
 use signal::signal::SignalEmiter;
 
 type BF<N, LV, AV, M, II> =
@@ -31,13 +36,19 @@ type BF<N, LV, AV, M, II> =
 
 pub struct BodyWorld<N, LV, AV, M, II, CM> {
     world:      World<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
+    events:     @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>,
     forces:     @mut BodyForceGenerator<N, LV, AV, M, II>,
     integrator: @mut BodySmpEulerIntegrator<N, LV, AV, M, II>,
     detector:   @mut BodiesBodies<N, LV, AV, M, II, BF<N, LV, AV, M, II>>,
     sleep:      @mut IslandActivationManager<N, LV, AV, M, II>,
     ccd:        @mut SweptBallMotionClamping<N, LV, AV, M, II, BF<N, LV, AV, M, II>>,
     joints:     @mut JointManager<N, LV, AV, M, II>,
-    solver:     @mut AccumulatedImpulseSolver<N, LV, AV, M, II, CM>
+    solver:     @mut AccumulatedImpulseSolver<N, LV, AV, M, II, CM>,
+
+    // fixed timestep
+    timestep:     N,
+    max_substeps: uint,
+    accumulator:  N
 }
 
 impl<N:  'static + NPhysicsScalar,
@@ -47,6 +58,16 @@ impl<N:  'static + NPhysicsScalar,
      II: 'static + Clone + NPhysicsInertia<N, LV, AV, M>,
      CM: Row<AV>>
 BodyWorld<N, LV, AV, M, II, CM> {
+    /// Creates a new world.
+    ///
+    /// Its pipeline stages are named:
+    ///  * integrators: `"forces"`, `"integrator"` and `"ccd"`.
+    ///  * detectors: `"collisions"`, `"joints"` and `"sleep"`.
+    ///  * solvers: `"solver"`.
+    ///
+    /// Those names can be used with the methods of `self.world_mut()` to remove or replace a
+    /// stage. Note that the accessors of this structure (like `self.integrator()`) keep returning
+    /// the original stages.
     pub fn new() -> BodyWorld<N, LV, AV, M, II, CM> {
         /*
          * Setup the physics world
@@ -87,34 +108,142 @@ BodyWorld<N, LV, AV, M, II, CM> {
             10,
             10);
 
+        // the solver splits the constraints along the islands found by the activation manager
+        solver.set_activation_manager(Some(sleep));
+
         /*
          * Add everything to the world
          */
-        world.add_integrator(forces);
-        world.add_integrator(integrator);
-        world.add_integrator(ccd);
-        world.add_detector(detector);
-        world.add_detector(joints);
-        world.add_detector(sleep);
-        world.add_solver(solver);
+        world.add_integrator("forces", forces);
+        world.add_integrator("integrator", integrator);
+        world.add_integrator("ccd", ccd);
+        world.add_detector("collisions", detector);
+        world.add_detector("joints", joints);
+        world.add_detector("sleep", sleep);
+        world.add_solver("solver", solver);
 
         BodyWorld {
             world:      world,
+            events:     events,
             forces:     forces,
             integrator: integrator,
             detector:   detector,
             sleep:      sleep,
             ccd:        ccd,
             joints:     joints,
-            solver:     solver
+            solver:     solver,
+
+            timestep:     na::cast(1.0 / 60.0),
+            max_substeps: 5,
+            accumulator:  na::zero()
         }
     }
 
     pub fn step(&mut self, dt: N) {
-        self.world.step(dt)
+        self.world.step(dt);
+
+        /*
+         * Gather the statistics the generic world does not know about.
+         */
+        let mut num_active = 0u;
+
+        for o in self.world.objects().iter() {
+            if o.is_active() {
+                num_active = num_active + 1;
+            }
+        }
+
+        let num_pairs = self.detector.num_pairs();
+
+        let stats = self.world.statistics_mut();
+
+        stats.num_active_bodies  = num_active;
+        stats.num_pairs          = num_pairs;
+        stats.num_contacts       = self.solver.num_contacts();
+        stats.num_joints         = self.solver.num_joints();
+        stats.num_islands        = self.sleep.num_islands();
+        stats.num_pgs_iterations = self.solver.num_iterations();
+        stats.num_fallbacks      = self.solver.num_fallbacks();
+    }
+
+    /// Timings and counters of the last step.
+    pub fn statistics<'r>(&'r self) -> &'r StepStatistics {
+        self.world.statistics()
+    }
+
+    /// Advances the simulation by `frame_dt` using fixed-size steps of length `self.timestep()`.
+    ///
+    /// The time that does not fit in a whole step is accumulated for the next call. At most
+    /// `self.max_substeps()` steps are performed: any excess time is dropped so that a slow frame
+    /// does not make the next one even slower.
+    ///
+    /// Returns the interpolation factor (between 0.0 and 1.0) to use to blend each rigid body
+    /// `prev_transform_ref()` with its `transform_ref()` for rendering.
+    pub fn advance(&mut self, frame_dt: N) -> N {
+        self.accumulator = self.accumulator + frame_dt;
+
+        let mut num_steps = 0;
+
+        while self.accumulator >= self.timestep && num_steps < self.max_substeps {
+            self.save_transformations();
+            self.step(self.timestep.clone());
+
+            self.accumulator = self.accumulator - self.timestep;
+            num_steps        = num_steps + 1;
+        }
+
+        if self.accumulator >= self.timestep {
+            // we hit the substep limit: skip the remaining whole steps
+            self.accumulator = self.accumulator % self.timestep;
+        }
+
+        self.accumulator / self.timestep
+    }
+
+    fn save_transformations(&mut self) {
+        for o in self.world.objects().iter() {
+            match **o {
+                RB(ref mut rb) => rb.save_transformation(),
+                SB(_)          => { },
+                MB(ref mut mb) => mb.mut_rigid_body().save_transformation()
+            }
+        }
+    }
+
+    pub fn timestep(&self) -> N {
+        self.timestep.clone()
+    }
+
+    pub fn set_timestep(&mut self, timestep: N) {
+        assert!(timestep > na::zero(), "The timestep must be positive.");
+
+        self.timestep = timestep
+    }
+
+    pub fn max_substeps(&self) -> uint {
+        self.max_substeps
+    }
+
+    pub fn set_max_substeps(&mut self, max_substeps: uint) {
+        self.max_substeps = max_substeps
+    }
+
+    pub fn num_substeps(&self) -> uint {
+        self.world.num_substeps()
+    }
+
+    pub fn set_num_substeps(&mut self, substeps: uint) {
+        self.world.set_num_substeps(substeps)
     }
 
     pub fn add_body(&mut self, b: @mut Body<N, LV, AV, M, II>) {
+        // the body must not be interpolated from wherever it was before being added
+        match *b {
+            RB(ref mut rb) => rb.save_transformation(),
+            SB(_)          => { },
+            MB(ref mut mb) => mb.mut_rigid_body().save_transformation()
+        }
+
         self.world.add_object(b)
     }
 
@@ -130,6 +259,13 @@ BodyWorld<N, LV, AV, M, II, CM> {
         &'r mut self.world
     }
 
+    /// The signal emiter used by the default pipeline stages.
+    ///
+    /// Use it to create new stages meant to replace the default ones.
+    pub fn events(&self) -> @mut SignalEmiter<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>> {
+        self.events
+    }
+
     pub fn forces_generator(&self) -> @mut BodyForceGenerator<N, LV, AV, M, II> {
         self.forces
     }
@@ -174,10 +310,32 @@ BodyWorld<N, LV, AV, M, II, CM> {
         self.forces.ang_acc()
     }
 
-    pub fn cast_ray(&self, ray: &Ray<LV>, out: &mut ~[(@mut Body<N, LV, AV, M, II>, N)]) {
+    /// Appends every body hit by `ray`, with its time of impact, in no particular order.
+    pub fn interferences_with_ray(&self,
+                                  ray: &Ray<LV>,
+                                  out: &mut ~[(@mut Body<N, LV, AV, M, II>, N)]) {
         self.detector.interferences_with_ray(ray, out)
     }
 
+    /// Appends the bodies hit by `ray` before `max_toi` and accepted by `filter`, sorted by time of
+    /// impact.
+    pub fn cast_ray(&self,
+                    ray:     &Ray<LV>,
+                    max_toi: &N,
+                    filter:  |@mut Body<N, LV, AV, M, II>| -> bool,
+                    out:     &mut ~[RayHit<N, LV, AV, M, II>]) {
+        self.detector.cast_ray(ray, max_toi, filter, out)
+    }
+
+    /// The first body hit by `ray` before `max_toi` and accepted by `filter`.
+    pub fn cast_ray_closest(&self,
+                            ray:     &Ray<LV>,
+                            max_toi: &N,
+                            filter:  |@mut Body<N, LV, AV, M, II>| -> bool)
+                            -> Option<RayHit<N, LV, AV, M, II>> {
+        self.detector.cast_ray_closest(ray, max_toi, filter)
+    }
+
     pub fn add_ccd_to(&mut self,
                       body:                @mut Body<N, LV, AV, M, II>,
                       swept_sphere_radius: N,
@@ -201,17 +359,127 @@ BodyWorld<N, LV, AV, M, II, CM> {
         self.joints.remove_fixed(joint)
     }
 
+    pub fn add_distance(&mut self, joint: @mut Distance<N, LV, AV, M, II>) {
+        self.joints.add_distance(joint)
+    }
+
+    pub fn remove_distance(&mut self, joint: @mut Distance<N, LV, AV, M, II>) {
+        self.joints.remove_distance(joint)
+    }
+
+    pub fn add_cone_twist(&mut self, joint: @mut ConeTwist<N, LV, AV, M, II>) {
+        self.joints.add_cone_twist(joint)
+    }
+
+    pub fn remove_cone_twist(&mut self, joint: @mut ConeTwist<N, LV, AV, M, II>) {
+        self.joints.remove_cone_twist(joint)
+    }
+
+    pub fn add_generic_joint(&mut self, joint: @mut GenericJoint<N, LV, AV, M, II>) {
+        self.joints.add_generic_joint(joint)
+    }
+
+    pub fn remove_generic_joint(&mut self, joint: @mut GenericJoint<N, LV, AV, M, II>) {
+        self.joints.remove_generic_joint(joint)
+    }
+
+    pub fn add_gear(&mut self, joint: @mut Gear<N, LV, AV, M, II>) {
+        self.joints.add_gear(joint)
+    }
+
+    pub fn remove_gear(&mut self, joint: @mut Gear<N, LV, AV, M, II>) {
+        self.joints.remove_gear(joint)
+    }
+
+    pub fn add_pulley(&mut self, joint: @mut Pulley<N, LV, AV, M, II>) {
+        self.joints.add_pulley(joint)
+    }
+
+    pub fn remove_pulley(&mut self, joint: @mut Pulley<N, LV, AV, M, II>) {
+        self.joints.remove_pulley(joint)
+    }
+
     pub fn add_detector<D: 'static + Detector<N, Body<N, LV, AV, M, II>, Constraint<N, LV, AV, M, II>>>(
                         &mut self,
-                        d: @mut D) {
-        self.world.add_detector(d)
+                        name: &str,
+                        d:    @mut D) {
+        self.world.add_detector(name, d)
     }
 
-    pub fn add_integrator<I: 'static + Integrator<N, Body<N, LV, AV, M, II>>>(&mut self, i: @mut I) {
-        self.world.add_integrator(i)
+    pub fn add_integrator<I: 'static + Integrator<N, Body<N, LV, AV, M, II>>>(&mut self,
+                                                                             name: &str,
+                                                                             i:    @mut I) {
+        self.world.add_integrator(name, i)
     }
 
-    pub fn add_solver<S: 'static + Solver<N, Constraint<N, LV, AV, M, II>>>(&mut self, s: @mut S) {
-        self.world.add_solver(s)
+    pub fn add_solver<S: 'static + Solver<N, Constraint<N, LV, AV, M, II>>>(&mut self,
+                                                                           name: &str,
+                                                                           s:    @mut S) {
+        self.world.add_solver(name, s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use integration::Integrator;
+    use aliases::dim3::BodyWorld3d;
+    use world::BodyWorld;
+
+    // counts the steps performed by the world
+    struct StepCounter {
+        num_steps: uint
+    }
+
+    impl<N, O> Integrator<N, O> for StepCounter {
+        fn add(&mut self, _: @mut O) { }
+        fn remove(&mut self, _: @mut O) { }
+
+        fn update(&mut self, _: N) {
+            self.num_steps = self.num_steps + 1
+        }
+
+        fn priority(&self) -> f64 {
+            0.0
+        }
+    }
+
+    #[test]
+    fn test_advance_accumulates_the_remaining_time() {
+        let mut world: BodyWorld3d<f64> = BodyWorld::new();
+        let counter = @mut StepCounter { num_steps: 0 };
+
+        world.set_timestep(0.1);
+        world.add_integrator("counter", counter);
+
+        let alpha = world.advance(0.25);
+
+        assert!(counter.num_steps == 2);
+        assert!((alpha - 0.5).abs() < 1.0e-6);
+
+        // the remaining 0.05 is used by the next frame
+        let alpha = world.advance(0.1);
+
+        assert!(counter.num_steps == 3);
+        assert!((alpha - 0.5).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn test_advance_drops_the_time_exceeding_max_substeps() {
+        let mut world: BodyWorld3d<f64> = BodyWorld::new();
+        let counter = @mut StepCounter { num_steps: 0 };
+
+        world.set_timestep(0.1);
+        world.set_max_substeps(3);
+        world.add_integrator("counter", counter);
+
+        let alpha = world.advance(1.05);
+
+        assert!(counter.num_steps == 3);
+        assert!((alpha - 0.5).abs() < 1.0e-6);
+
+        // the dropped steps are not caught up
+        world.advance(0.0);
+
+        assert!(counter.num_steps == 3);
     }
 }
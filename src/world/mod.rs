@@ -1,5 +1,7 @@
 pub use world::world::World;
 pub use world::body_world::BodyWorld;
+pub use world::step_statistics::{StepStatistics, StageTiming};
 
 pub mod world;
 pub mod body_world;
+pub mod step_statistics;
@@ -0,0 +1,85 @@
+/// Time spent by one stage of the pipeline during the last step.
+#[deriving(ToStr, Clone)]
+pub struct StageTiming {
+    /// The name the stage was registered with.
+    name:     ~str,
+    /// The `priority()` of the stage.
+    priority: f64,
+    /// Time spent in the stage, in seconds.
+    time:     f64
+}
+
+impl StageTiming {
+    pub fn new(name: ~str, priority: f64) -> StageTiming {
+        StageTiming {
+            name:     name,
+            priority: priority,
+            time:     0.0
+        }
+    }
+}
+
+/// Timings and counters gathered during the last call to `World::step`.
+///
+/// If the world is configured to use substeps, the timings are accumulated over all the substeps
+/// while the counters are those of the last substep.
+#[deriving(ToStr, Clone)]
+pub struct StepStatistics {
+    /// Timings of each integrator, in execution order.
+    integrators:        ~[StageTiming],
+    /// Timings of each detector, in execution order.
+    detectors:          ~[StageTiming],
+    /// Timings of each solver, in execution order.
+    solvers:            ~[StageTiming],
+    /// Total time spent in the step, in seconds.
+    total_time:         f64,
+
+    num_bodies:         uint,
+    num_active_bodies:  uint,
+    num_interferences:  uint,
+    num_pairs:          uint,
+    num_contacts:       uint,
+    num_joints:         uint,
+    num_islands:        uint,
+    num_pgs_iterations: uint,
+    /// Number of islands the direct solver failed to solve, and which were solved iteratively.
+    num_fallbacks:      uint
+}
+
+impl StepStatistics {
+    pub fn new() -> StepStatistics {
+        StepStatistics {
+            integrators:        ~[],
+            detectors:          ~[],
+            solvers:            ~[],
+            total_time:         0.0,
+            num_bodies:         0,
+            num_active_bodies:  0,
+            num_interferences:  0,
+            num_pairs:          0,
+            num_contacts:       0,
+            num_joints:         0,
+            num_islands:        0,
+            num_pgs_iterations: 0,
+            num_fallbacks:      0
+        }
+    }
+
+    /// Resets every timing and counter to zero.
+    pub fn reset(&mut self) {
+        for t in self.integrators.mut_iter().chain(self.detectors.mut_iter()).chain(self.solvers.mut_iter()) {
+            t.time = 0.0
+        }
+
+        self.total_time         = 0.0;
+        self.num_bodies         = 0;
+        self.num_active_bodies  = 0;
+        self.num_interferences  = 0;
+        self.num_pairs          = 0;
+        self.num_contacts       = 0;
+        self.num_joints         = 0;
+        self.num_islands        = 0;
+        self.num_pgs_iterations = 0;
+        self.num_fallbacks      = 0;
+    }
+}
@@ -1,33 +1,72 @@
 use std::managed;
+use extra::time;
+use nalgebra::na::Cast;
 use resolution::solver::Solver;
 use integration::Integrator;
 use detection::detector::Detector;
+use world::step_statistics::{StepStatistics, StageTiming};
 
 pub struct World<N, O, C> {
-    objects:     ~[@mut O],
-    integrators: ~[@mut Integrator<N, O>],
-    detectors:   ~[@mut Detector<N, O, C>],
-    solvers:     ~[@mut Solver<N, C>]
+    objects:          ~[@mut O],
+    integrators:      ~[@mut Integrator<N, O>],
+    detectors:        ~[@mut Detector<N, O, C>],
+    solvers:          ~[@mut Solver<N, C>],
+    // names of the pipeline stages, in the same order as the stages themselves
+    integrator_names: ~[~str],
+    detector_names:   ~[~str],
+    solver_names:     ~[~str],
+    substeps:         uint,
+    stats:            StepStatistics
 }
 
 impl<N, O, C> World<N, O, C> {
     pub fn new() -> World<N, O, C> {
         World {
-            objects:     ~[],
-            integrators: ~[],
-            detectors:   ~[],
-            solvers:     ~[]
+            objects:          ~[],
+            integrators:      ~[],
+            detectors:        ~[],
+            solvers:          ~[],
+            integrator_names: ~[],
+            detector_names:   ~[],
+            solver_names:     ~[],
+            substeps:         1,
+            stats:            StepStatistics::new()
         }
     }
 }
 
-impl<N: Clone, O, C> World<N, O, C> {
+impl<N: Clone + Num + Cast<f32>, O, C> World<N, O, C> {
+    /// Advances the simulation by `dt`.
+    ///
+    /// If the number of substeps is greater than 1, the whole pipeline (integration, detection
+    /// and resolution) is run that many times, each with a time step of `dt / num_substeps`.
     pub fn step(&mut self, dt: N) {
+        self.init_statistics();
+
+        let begin = time::precise_time_s();
+
+        if self.substeps == 1 {
+            self.substep(dt)
+        }
+        else {
+            let subdt = dt / Cast::from(self.substeps as f32);
+
+            for _ in range(0u, self.substeps) {
+                self.substep(subdt.clone())
+            }
+        }
+
+        self.stats.total_time = time::precise_time_s() - begin;
+    }
+
+    fn substep(&mut self, dt: N) {
         //
         // Integration
         //
-        for i in self.integrators.mut_iter() {
-            i.update(dt.clone())
+        for (i, t) in self.integrators.mut_iter().zip(self.stats.integrators.mut_iter()) {
+            let begin = time::precise_time_s();
+            i.update(dt.clone());
+            t.time = t.time + time::precise_time_s() - begin;
         }
 
         //
@@ -35,37 +74,326 @@ impl<N: Clone, O, C> World<N, O, C> {
         //
         let mut interferences = ~[];
 
-        for d in self.detectors.mut_iter() {
+        for (d, t) in self.detectors.mut_iter().zip(self.stats.detectors.mut_iter()) {
+            let begin = time::precise_time_s();
             d.update();
             d.interferences(&mut interferences);
+            t.time = t.time + time::precise_time_s() - begin;
         }
 
         //
         // Resolution
         //
-        for s in self.solvers.mut_iter() {
+        for (s, t) in self.solvers.mut_iter().zip(self.stats.solvers.mut_iter()) {
+            let begin = time::precise_time_s();
             s.solve(dt.clone(), interferences);
+            t.time = t.time + time::precise_time_s() - begin;
         }
+
+        self.stats.num_bodies        = self.objects.len();
+        self.stats.num_interferences = interferences.len();
+    }
+
+    fn init_statistics(&mut self) {
+        self.stats.reset();
+
+        // FIXME: avoid allocation when the pipeline did not change
+        self.stats.integrators = self.integrators.iter().zip(self.integrator_names.iter()).map(
+            |(i, n)| StageTiming::new(n.clone(), i.priority())).collect();
+        self.stats.detectors   = self.detectors.iter().zip(self.detector_names.iter()).map(
+            |(d, n)| StageTiming::new(n.clone(), d.priority())).collect();
+        self.stats.solvers     = self.solvers.iter().zip(self.solver_names.iter()).map(
+            |(s, n)| StageTiming::new(n.clone(), s.priority())).collect();
+    }
+
+    /// The number of substeps each call to `step` is split into.
+    pub fn num_substeps(&self) -> uint {
+        self.substeps
+    }
+
+    /// Sets the number of substeps each call to `step` is split into.
+    ///
+    /// More substeps with fewer solver iterations each usually give more stable joint chains and
+    /// stacks with high mass ratios than a single step with many iterations.
+    pub fn set_num_substeps(&mut self, substeps: uint) {
+        assert!(substeps != 0, "The number of substeps must be at least 1.");
+
+        self.substeps = substeps
+    }
+
+    /// Adds a detector named `name`, ordered by its `priority()`.
+    pub fn add_detector<D: 'static + Detector<N, O, C>>(&mut self, name: &str, d: @mut D) {
+        let pos = sorted_position(self.detectors, |e| d.priority() < e.priority());
+
+        self.insert_detector_at(pos, name, d)
+    }
+
+    /// Adds a detector named `name` right before the detector named `before`.
+    pub fn insert_detector_before<D: 'static + Detector<N, O, C>>(&mut self,
+                                                                  before: &str,
+                                                                  name: &str,
+                                                                  d: @mut D) {
+        let pos = position_or_fail(self.detector_names, before);
+
+        self.insert_detector_at(pos, name, d)
+    }
+
+    /// Adds a detector named `name` right after the detector named `after`.
+    pub fn insert_detector_after<D: 'static + Detector<N, O, C>>(&mut self,
+                                                                 after: &str,
+                                                                 name: &str,
+                                                                 d: @mut D) {
+        let pos = position_or_fail(self.detector_names, after);
+
+        self.insert_detector_at(pos + 1, name, d)
+    }
+
+    /// Replaces the detector named `name`, keeping its name and position.
+    ///
+    /// The objects of the world are removed from the replaced detector and added to the new one.
+    /// Returns the replaced detector, or `None` (and does nothing) if there is no such detector.
+    pub fn replace_detector<D: 'static + Detector<N, O, C>>(&mut self, name: &str, d: @mut D)
+                          -> Option<@mut Detector<N, O, C>> {
+        match self.detector_names.iter().position(|n| n.as_slice() == name) {
+            Some(pos) => {
+                let old = self.detectors[pos];
+
+                // the objects are removed first in case both stages share some state
+                for o in self.objects.iter() {
+                    old.remove(*o)
+                }
+
+                self.detectors[pos] = d as @mut Detector<N, O, C>;
+
+                for o in self.objects.iter() {
+                    d.add(*o)
+                }
+
+                Some(old)
+            },
+            None      => None
+        }
+    }
+
+    /// Removes the detector named `name`, after removing the objects of the world from it.
+    pub fn remove_detector(&mut self, name: &str) -> Option<@mut Detector<N, O, C>> {
+        match self.detector_names.iter().position(|n| n.as_slice() == name) {
+            Some(pos) => {
+                self.detector_names.remove(pos);
+
+                let old = self.detectors.remove(pos);
+
+                for o in self.objects.iter() {
+                    old.remove(*o)
+                }
+
+                Some(old)
+            },
+            None      => None
+        }
+    }
+
+    /// The detector named `name`.
+    pub fn detector(&self, name: &str) -> Option<@mut Detector<N, O, C>> {
+        self.detector_names.iter().position(|n| n.as_slice() == name).map(|pos| self.detectors[pos])
     }
 
-    pub fn add_detector<D: 'static + Detector<N, O, C>>(&mut self, d: @mut D) {
-        sorted_insert(&mut self.detectors, d as @mut Detector<N, O, C>, |a, b| a.priority() < b.priority());
+    /// The names of the detectors, in execution order.
+    pub fn detector_names<'r>(&'r self) -> &'r [~str] {
+        let res: &'r [~str] = self.detector_names;
+
+        res
+    }
+
+    fn insert_detector_at<D: 'static + Detector<N, O, C>>(&mut self,
+                                                          pos: uint,
+                                                          name: &str,
+                                                          d: @mut D) {
+        assert!(!self.detector_names.iter().any(|n| n.as_slice() == name),
+                "A detector with this name already exists.");
+
+        self.detectors.insert(pos, d as @mut Detector<N, O, C>);
+        self.detector_names.insert(pos, name.to_owned());
 
         for o in self.objects.iter() {
             d.add(*o)
         }
     }
 
-    pub fn add_integrator<I: 'static + Integrator<N, O>>(&mut self, i: @mut I) {
-        sorted_insert(&mut self.integrators, i as @mut Integrator<N, O>, |a, b| a.priority() < b.priority());
+    /// Adds an integrator named `name`, ordered by its `priority()`.
+    pub fn add_integrator<I: 'static + Integrator<N, O>>(&mut self, name: &str, i: @mut I) {
+        let pos = sorted_position(self.integrators, |e| i.priority() < e.priority());
+
+        self.insert_integrator_at(pos, name, i)
+    }
+
+    /// Adds an integrator named `name` right before the integrator named `before`.
+    pub fn insert_integrator_before<I: 'static + Integrator<N, O>>(&mut self,
+                                                                   before: &str,
+                                                                   name: &str,
+                                                                   i: @mut I) {
+        let pos = position_or_fail(self.integrator_names, before);
+
+        self.insert_integrator_at(pos, name, i)
+    }
+
+    /// Adds an integrator named `name` right after the integrator named `after`.
+    pub fn insert_integrator_after<I: 'static + Integrator<N, O>>(&mut self,
+                                                                  after: &str,
+                                                                  name: &str,
+                                                                  i: @mut I) {
+        let pos = position_or_fail(self.integrator_names, after);
+
+        self.insert_integrator_at(pos + 1, name, i)
+    }
+
+    /// Replaces the integrator named `name`, keeping its name and position.
+    ///
+    /// The objects of the world are removed from the replaced integrator and added to the new one.
+    /// Returns the replaced integrator, or `None` (and does nothing) if there is no such
+    /// integrator.
+    pub fn replace_integrator<I: 'static + Integrator<N, O>>(&mut self, name: &str, i: @mut I)
+                          -> Option<@mut Integrator<N, O>> {
+        match self.integrator_names.iter().position(|n| n.as_slice() == name) {
+            Some(pos) => {
+                let old = self.integrators[pos];
+
+                // the objects are removed first in case both stages share some state
+                for o in self.objects.iter() {
+                    old.remove(*o)
+                }
+
+                self.integrators[pos] = i as @mut Integrator<N, O>;
+
+                for o in self.objects.iter() {
+                    i.add(*o)
+                }
+
+                Some(old)
+            },
+            None      => None
+        }
+    }
+
+    /// Removes the integrator named `name`, after removing the objects of the world from it.
+    pub fn remove_integrator(&mut self, name: &str) -> Option<@mut Integrator<N, O>> {
+        match self.integrator_names.iter().position(|n| n.as_slice() == name) {
+            Some(pos) => {
+                self.integrator_names.remove(pos);
 
-        for o in self.objects.mut_iter() {
+                let old = self.integrators.remove(pos);
+
+                for o in self.objects.iter() {
+                    old.remove(*o)
+                }
+
+                Some(old)
+            },
+            None      => None
+        }
+    }
+
+    /// The integrator named `name`.
+    pub fn integrator(&self, name: &str) -> Option<@mut Integrator<N, O>> {
+        self.integrator_names.iter().position(|n| n.as_slice() == name).map(|pos| self.integrators[pos])
+    }
+
+    /// The names of the integrators, in execution order.
+    pub fn integrator_names<'r>(&'r self) -> &'r [~str] {
+        let res: &'r [~str] = self.integrator_names;
+
+        res
+    }
+
+    fn insert_integrator_at<I: 'static + Integrator<N, O>>(&mut self,
+                                                           pos: uint,
+                                                           name: &str,
+                                                           i: @mut I) {
+        assert!(!self.integrator_names.iter().any(|n| n.as_slice() == name),
+                "An integrator with this name already exists.");
+
+        self.integrators.insert(pos, i as @mut Integrator<N, O>);
+        self.integrator_names.insert(pos, name.to_owned());
+
+        for o in self.objects.iter() {
             i.add(*o)
         }
     }
 
-    pub fn add_solver<S: 'static + Solver<N, C>>(&mut self, s: @mut S) {
-        sorted_insert(&mut self.solvers, s as @mut Solver<N, C>, |a, b| a.priority() < b.priority());
+    /// Adds a solver named `name`, ordered by its `priority()`.
+    pub fn add_solver<S: 'static + Solver<N, C>>(&mut self, name: &str, s: @mut S) {
+        let pos = sorted_position(self.solvers, |e| s.priority() < e.priority());
+
+        self.insert_solver_at(pos, name, s)
+    }
+
+    /// Adds a solver named `name` right before the solver named `before`.
+    pub fn insert_solver_before<S: 'static + Solver<N, C>>(&mut self,
+                                                           before: &str,
+                                                           name: &str,
+                                                           s: @mut S) {
+        let pos = position_or_fail(self.solver_names, before);
+
+        self.insert_solver_at(pos, name, s)
+    }
+
+    /// Adds a solver named `name` right after the solver named `after`.
+    pub fn insert_solver_after<S: 'static + Solver<N, C>>(&mut self,
+                                                          after: &str,
+                                                          name: &str,
+                                                          s: @mut S) {
+        let pos = position_or_fail(self.solver_names, after);
+
+        self.insert_solver_at(pos + 1, name, s)
+    }
+
+    /// Replaces the solver named `name`, keeping its name and position.
+    ///
+    /// Returns the replaced solver, or `None` (and does nothing) if there is no such solver.
+    pub fn replace_solver<S: 'static + Solver<N, C>>(&mut self, name: &str, s: @mut S)
+                          -> Option<@mut Solver<N, C>> {
+        match self.solver_names.iter().position(|n| n.as_slice() == name) {
+            Some(pos) => {
+                let old = self.solvers[pos];
+
+                self.solvers[pos] = s as @mut Solver<N, C>;
+
+                Some(old)
+            },
+            None      => None
+        }
+    }
+
+    /// Removes the solver named `name`.
+    pub fn remove_solver(&mut self, name: &str) -> Option<@mut Solver<N, C>> {
+        match self.solver_names.iter().position(|n| n.as_slice() == name) {
+            Some(pos) => {
+                self.solver_names.remove(pos);
+
+                Some(self.solvers.remove(pos))
+            },
+            None      => None
+        }
+    }
+
+    /// The solver named `name`.
+    pub fn solver(&self, name: &str) -> Option<@mut Solver<N, C>> {
+        self.solver_names.iter().position(|n| n.as_slice() == name).map(|pos| self.solvers[pos])
+    }
+
+    /// The names of the solvers, in execution order.
+    pub fn solver_names<'r>(&'r self) -> &'r [~str] {
+        let res: &'r [~str] = self.solver_names;
+
+        res
+    }
+
+    fn insert_solver_at<S: 'static + Solver<N, C>>(&mut self, pos: uint, name: &str, s: @mut S) {
+        assert!(!self.solver_names.iter().any(|n| n.as_slice() == name),
+                "A solver with this name already exists.");
+
+        self.solvers.insert(pos, s as @mut Solver<N, C>);
+        self.solver_names.insert(pos, name.to_owned());
     }
 
     pub fn add_object(&mut self, b: @mut O) {
@@ -84,8 +412,8 @@ impl<N: Clone, O, C> World<N, O, C> {
 
     pub fn remove_object(&mut self, b: @mut O) {
         match self.objects.iter().position(|o| managed::mut_ptr_eq(b, *o)) {
-            Some(i) => {
-                self.objects.swap_remove(i);
+            Some(pos) => {
+                self.objects.swap_remove(pos);
 
                 for d in self.integrators.mut_iter() {
                     d.remove(b)
@@ -99,6 +427,15 @@ impl<N: Clone, O, C> World<N, O, C> {
         }
     }
 
+    /// Timings and counters of the last step.
+    pub fn statistics<'r>(&'r self) -> &'r StepStatistics {
+        &'r self.stats
+    }
+
+    pub fn statistics_mut<'r>(&'r mut self) -> &'r mut StepStatistics {
+        &'r mut self.stats
+    }
+
     pub fn objects<'r>(&'r self) -> &'r [@mut O] {
         let res: &'r [@mut O] = self.objects;
 
@@ -125,15 +462,222 @@ impl<N: Clone, O, C> World<N, O, C> {
 }
 
 #[inline(always)]
-fn sorted_insert<T>(vec: &mut ~[T], t: T, lt: |&T, &T| -> bool) {
-    let mut iinsert = vec.len();
-
+fn sorted_position<T>(vec: &[T], lt: |&T| -> bool) -> uint {
     for (i, e) in vec.iter().enumerate() {
-        if lt(&t, e) {
-            iinsert = i;
-            break;
+        if lt(e) {
+            return i
         }
     }
 
-    vec.insert(iinsert, t)
+    vec.len()
+}
+
+#[inline(always)]
+fn position_or_fail(names: &[~str], name: &str) -> uint {
+    match names.iter().position(|n| n.as_slice() == name) {
+        Some(i) => i,
+        None    => fail!("There is no pipeline stage named: " + name)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use integration::Integrator;
+    use detection::detector::Detector;
+    use resolution::solver::Solver;
+    use super::World;
+
+    // a pipeline stage recording the time steps and the number of objects it is given
+    struct Recorder {
+        priority:    f64,
+        num_objects: uint,
+        dts:         ~[f64]
+    }
+
+    impl Recorder {
+        fn new(priority: f64) -> Recorder {
+            Recorder {
+                priority:    priority,
+                num_objects: 0,
+                dts:         ~[]
+            }
+        }
+    }
+
+    impl Integrator<f64, int> for Recorder {
+        fn add(&mut self, _: @mut int) {
+            self.num_objects = self.num_objects + 1
+        }
+
+        fn remove(&mut self, _: @mut int) {
+            self.num_objects = self.num_objects - 1
+        }
+
+        fn update(&mut self, dt: f64) {
+            self.dts.push(dt)
+        }
+
+        fn priority(&self) -> f64 {
+            self.priority
+        }
+    }
+
+    impl Detector<f64, int, int> for Recorder {
+        fn add(&mut self, _: @mut int) {
+            self.num_objects = self.num_objects + 1
+        }
+
+        fn remove(&mut self, _: @mut int) {
+            self.num_objects = self.num_objects - 1
+        }
+
+        fn update(&mut self) { }
+
+        fn interferences(&mut self, out: &mut ~[int]) {
+            out.push(self.num_objects as int)
+        }
+
+        fn priority(&self) -> f64 {
+            self.priority
+        }
+    }
+
+    impl Solver<f64, int> for Recorder {
+        fn solve(&mut self, dt: f64, _: &[int]) {
+            self.dts.push(dt)
+        }
+
+        fn priority(&self) -> f64 {
+            self.priority
+        }
+    }
+
+    #[test]
+    fn test_substeps_split_the_time_step() {
+        let mut world: World<f64, int, int> = World::new();
+        let integrator = @mut Recorder::new(0.0);
+        let solver     = @mut Recorder::new(0.0);
+
+        world.add_integrator("integrator", integrator);
+        world.add_solver("solver", solver);
+        world.set_num_substeps(4);
+        world.step(1.0);
+
+        assert!(integrator.dts == ~[0.25, 0.25, 0.25, 0.25]);
+        assert!(solver.dts == integrator.dts);
+
+        world.set_num_substeps(1);
+        world.step(1.0);
+
+        assert!(integrator.dts.len() == 5);
+        assert!(*integrator.dts.last() == 1.0);
+    }
+
+    #[test]
+    fn test_statistics_follow_the_pipeline() {
+        let mut world: World<f64, int, int> = World::new();
+
+        world.add_integrator("second", @mut Recorder::new(1.0));
+        world.add_integrator("first", @mut Recorder::new(0.0));
+        world.add_detector("detector", @mut Recorder::new(0.0));
+        world.add_object(@mut 0);
+        world.add_object(@mut 1);
+        world.set_num_substeps(2);
+        world.step(1.0);
+
+        let stats = world.statistics();
+
+        assert!(stats.integrators.len() == 2);
+        assert!(stats.integrators[0].name == ~"first");
+        assert!(stats.integrators[1].name == ~"second");
+        assert!(stats.integrators[1].priority == 1.0);
+        assert!(stats.detectors.len() == 1);
+        assert!(stats.solvers.is_empty());
+        assert!(stats.num_bodies == 2);
+        // the counters are those of the last substep
+        assert!(stats.num_interferences == 1);
+        assert!(stats.total_time >= stats.integrators[0].time + stats.integrators[1].time);
+    }
+
+    #[test]
+    fn test_stages_can_be_inserted_replaced_and_removed() {
+        let mut world: World<f64, int, int> = World::new();
+        let a = @mut Recorder::new(0.0);
+        let b = @mut Recorder::new(0.0);
+        let c = @mut Recorder::new(0.0);
+
+        world.add_object(@mut 0);
+        world.add_integrator("a", a);
+        world.add_integrator("c", c);
+        world.insert_integrator_before("c", "b", b);
+
+        assert!(world.integrator_names() == &[~"a", ~"b", ~"c"]);
+        assert!(b.num_objects == 1);
+
+        // the replacement keeps the name and position, and takes the objects
+        let new_b = @mut Recorder::new(0.0);
+
+        assert!(world.replace_integrator("b", new_b).is_some());
+        assert!(world.integrator_names() == &[~"a", ~"b", ~"c"]);
+        assert!(b.num_objects == 0);
+        assert!(new_b.num_objects == 1);
+
+        world.step(1.0);
+
+        assert!(b.dts.is_empty());
+        assert!(new_b.dts == ~[1.0]);
+
+        assert!(world.remove_integrator("a").is_some());
+        assert!(world.remove_integrator("a").is_none());
+        assert!(world.replace_integrator("a", @mut Recorder::new(0.0)).is_none());
+        assert!(world.integrator_names() == &[~"b", ~"c"]);
+        assert!(a.num_objects == 0);
+    }
+
+    #[test]
+    fn test_detectors_and_solvers_are_found_by_name() {
+        let mut world: World<f64, int, int> = World::new();
+        let d1 = @mut Recorder::new(0.0);
+        let d2 = @mut Recorder::new(0.0);
+        let s1 = @mut Recorder::new(0.0);
+        let s2 = @mut Recorder::new(0.0);
+
+        world.add_detector("d1", d1);
+        world.insert_detector_after("d1", "d2", d2);
+        world.add_solver("s1", s1);
+        world.insert_solver_before("s1", "s2", s2);
+        world.add_object(@mut 0);
+
+        assert!(world.detector_names() == &[~"d1", ~"d2"]);
+        assert!(world.solver_names() == &[~"s2", ~"s1"]);
+        assert!(world.detector("d2").is_some());
+        assert!(world.solver("d2").is_none());
+        assert!(d2.num_objects == 1);
+
+        assert!(world.remove_detector("d2").is_some());
+        assert!(d2.num_objects == 0);
+        assert!(world.remove_solver("s2").is_some());
+
+        world.step(1.0);
+
+        assert!(s2.dts.is_empty());
+        assert!(s1.dts == ~[1.0]);
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_duplicate_stage_names_fail() {
+        let mut world: World<f64, int, int> = World::new();
+
+        world.add_solver("solver", @mut Recorder::new(0.0));
+        world.add_solver("solver", @mut Recorder::new(1.0));
+    }
+
+    #[test]
+    #[should_fail]
+    fn test_zero_substeps_fail() {
+        let mut world: World<f64, int, int> = World::new();
+
+        world.set_num_substeps(0)
+    }
 }